@@ -0,0 +1,28 @@
+#![no_main]
+
+use aseprite::ase::{AseFile, LazyAseFile};
+use aseprite::Limits;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut limits = Limits::default();
+    limits.max_width = 1024;
+    limits.max_height = 1024;
+    limits.max_frames = 64;
+    limits.max_chunk_size = 1 << 20;
+    limits.max_cel_size = 1 << 22;
+
+    if let Ok(ase) = AseFile::from_bytes_with_limits(data, &limits) {
+        for frame in 0..ase.frames.len() {
+            ase.render_frame(frame);
+            ase.render_frame_indices(frame);
+        }
+        if let Ok(bytes) = ase.to_bytes() {
+            // Recompressed cels may exceed the chunk limit, so use the defaults.
+            AseFile::from_bytes(&bytes).expect("written files parse");
+        }
+    }
+    if let Ok(lazy) = LazyAseFile::with_limits(std::io::Cursor::new(data), &limits) {
+        let _ = lazy.load();
+    }
+});
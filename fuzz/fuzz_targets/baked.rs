@@ -0,0 +1,18 @@
+#![no_main]
+
+use aseprite::baked::{self, BakedSheet};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(sheet) = BakedSheet::parse(data) {
+        for tag in sheet.tags() {
+            sheet.tag(tag.name).expect("listed tags are found");
+        }
+        for slice in sheet.slices() {
+            slice.keys().count();
+        }
+        let owned = sheet.to_sheet();
+        let bytes = baked::bake(&owned).expect("parsed sheets bake");
+        assert_eq!(owned, baked::load(&bytes).expect("baked sheets load"));
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use aseprite::animation::Animation;
+use aseprite::{Limits, SpritesheetData};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut limits = Limits::default();
+    limits.max_frames = 1024;
+
+    if let Ok(sheet) = SpritesheetData::from_json_with_limits(data, &limits) {
+        for tag in &sheet.meta.frame_tags {
+            let mut animation = Animation::new(&sheet, tag);
+            animation.update(1000);
+        }
+        serde_json::to_string(&sheet).expect("parsed sheets serialize");
+    }
+});
@@ -0,0 +1,334 @@
+//! Rendering tags to animated GIF and APNG images.
+//!
+//! Writing GIFs needs the `gif` feature and writing APNGs the `png` one.
+
+use crate::animation::{tag_playback, tag_sequence};
+use crate::ase::AseFile;
+use crate::image::RgbaImage;
+use crate::{Color, Error, Frametag};
+
+/// How frames are drawn into an animation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct AnimationOptions {
+    /// How many times larger than the sprite the images are, 1 or more.
+    pub scale: u32,
+    /// Color drawn behind the frames, transparent if `None`.
+    pub background: Option<Color>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            background: None,
+        }
+    }
+}
+
+/// Frame shown in an animation.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub struct AnimatedFrame {
+    /// Index of the image in [`RenderedAnimation::images`].
+    pub image: usize,
+    /// How long the frame is shown in milliseconds.
+    pub duration: u32,
+}
+
+/// Frames of a tag or of the whole timeline, ready to be encoded.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct RenderedAnimation {
+    /// Width of the images in pixels.
+    pub width: u32,
+    /// Height of the images in pixels.
+    pub height: u32,
+    /// Image of every sprite frame that is shown, each one only once.
+    pub images: Vec<RgbaImage>,
+    /// Frames in the order they're shown.
+    pub frames: Vec<AnimatedFrame>,
+    /// Whether the animation starts over after the last frame, rather than
+    /// playing once.
+    pub looping: bool,
+}
+
+impl RenderedAnimation {
+    /// Renders `tag`, or every frame in order if it's `None`.
+    ///
+    /// Frame durations come from the file. A tag that repeats a number of
+    /// times is played out in full and then stops, other animations loop.
+    pub fn from_ase(
+        file: &AseFile,
+        tag: Option<&Frametag>,
+        options: &AnimationOptions,
+    ) -> Result<Self, Error> {
+        let durations: Vec<u32> = file.frames.iter().map(|f| f.duration as u32).collect();
+        Self::new(&durations, tag, options, |frame| file.render_frame(frame))
+    }
+
+    /// Animates frames rendered beforehand, such as ones cut from a sprite
+    /// sheet, with `durations` holding how long each one lasts.
+    pub fn from_frames(
+        frames: &[RgbaImage],
+        durations: &[u32],
+        tag: Option<&Frametag>,
+        options: &AnimationOptions,
+    ) -> Result<Self, Error> {
+        if frames.len() != durations.len() {
+            return Err(Error::format(format!(
+                "{} frames but {} durations",
+                frames.len(),
+                durations.len()
+            )));
+        }
+        if frames
+            .windows(2)
+            .any(|f| (f[0].width, f[0].height) != (f[1].width, f[1].height))
+        {
+            return Err(Error::format("frames differ in size"));
+        }
+        Self::new(durations, tag, options, |frame| frames[frame].clone())
+    }
+
+    fn new(
+        durations: &[u32],
+        tag: Option<&Frametag>,
+        options: &AnimationOptions,
+        mut render: impl FnMut(usize) -> RgbaImage,
+    ) -> Result<Self, Error> {
+        let (sequence, looping) = match tag {
+            None => ((0..durations.len() as u32).collect(), true),
+            Some(tag) => match tag_playback(tag) {
+                Some(sequence) => (sequence, false),
+                None => (tag_sequence(tag), true),
+            },
+        };
+        if let Some(&frame) = sequence.iter().find(|&&f| f as usize >= durations.len()) {
+            return Err(Error::format(format!(
+                "frame {} is out of range, there are {}",
+                frame,
+                durations.len()
+            )));
+        }
+        if sequence.is_empty() {
+            return Err(Error::format("no frames to animate"));
+        }
+
+        let scale = options.scale.max(1);
+        let mut indices = vec![None; durations.len()];
+        let mut images = Vec::new();
+        let mut frames = Vec::with_capacity(sequence.len());
+        for frame in sequence {
+            let frame = frame as usize;
+            let image = *indices[frame].get_or_insert_with(|| {
+                let mut image = render(frame);
+                if let Some(color) = options.background {
+                    let mut background = RgbaImage::filled(image.width, image.height, color);
+                    background.draw(&image, 0, 0, 255);
+                    image = background;
+                }
+                if scale > 1 {
+                    image = image.scale(scale);
+                }
+                images.push(image);
+                images.len() - 1
+            });
+            frames.push(AnimatedFrame {
+                image,
+                duration: durations[frame],
+            });
+        }
+        Ok(Self {
+            width: images[0].width,
+            height: images[0].height,
+            images,
+            frames,
+            looping,
+        })
+    }
+
+    /// Writes the animation as a GIF.
+    ///
+    /// Pixels less than half opaque become transparent and the others
+    /// opaque. Frames with more than 256 colors are quantized, and delays
+    /// are rounded to hundredths of a second.
+    #[cfg(feature = "gif")]
+    pub fn write_gif<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let gif_error = |e: gif::EncodingError| Error::format(format!("gif: {}", e));
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height))
+        else {
+            return Err(Error::format(format!(
+                "{}x{} is too large for a gif",
+                self.width, self.height
+            )));
+        };
+        let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(gif_error)?;
+        // Without the extension, a GIF plays once.
+        if self.looping {
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(gif_error)?;
+        }
+
+        let images: Vec<gif::Frame> = self
+            .images
+            .iter()
+            .map(|image| {
+                let mut pixels = image.data.clone();
+                for pixel in pixels.chunks_exact_mut(4) {
+                    if pixel[3] < 128 {
+                        pixel.copy_from_slice(&[0; 4]);
+                    }
+                }
+                let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+                frame.dispose = gif::DisposalMethod::Background;
+                frame
+            })
+            .collect();
+        for frame in &self.frames {
+            let mut image = images[frame.image].clone();
+            image.delay = (frame.duration.saturating_add(5) / 10).min(u16::MAX as u32) as u16;
+            encoder.write_frame(&image).map_err(gif_error)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the animation as an animated PNG.
+    ///
+    /// Delays longer than a minute are cut down to 65.535 seconds.
+    #[cfg(feature = "png")]
+    pub fn write_apng<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let png_error = |e: png::EncodingError| Error::format(format!("png: {}", e));
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(self.frames.len() as u32, !self.looping as u32)
+            .map_err(png_error)?;
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        for frame in &self.frames {
+            let delay = frame.duration.min(u16::MAX as u32) as u16;
+            writer.set_frame_delay(delay, 1000).map_err(png_error)?;
+            writer
+                .write_image_data(&self.images[frame.image].data)
+                .map_err(png_error)?;
+        }
+        writer.finish().map_err(png_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complex() -> AseFile {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        AseFile::from_bytes(file.src_aseprite).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let ase = complex();
+        let mut tag = ase
+            .tags
+            .iter()
+            .find(|t| t.name == "ping-pong")
+            .unwrap()
+            .clone();
+        let options = AnimationOptions {
+            scale: 2,
+            background: Some(Color {
+                r: 255,
+                g: 0,
+                b: 255,
+                a: 255,
+            }),
+        };
+        let animation = RenderedAnimation::from_ase(&ase, Some(&tag), &options).unwrap();
+        assert!(animation.looping);
+        assert_eq!(2, animation.images.len());
+        assert_eq!(ase.header.width as u32 * 2, animation.width);
+        let frame = ase.render_frame(tag.from as usize);
+        let mut expected =
+            RgbaImage::filled(frame.width, frame.height, options.background.unwrap());
+        expected.draw(&frame, 0, 0, 255);
+        assert_eq!(expected.scale(2), animation.images[0]);
+        assert!(animation.images[0].data.chunks(4).all(|p| p[3] == 255));
+
+        tag.repeat = Some(3);
+        let animation =
+            RenderedAnimation::from_ase(&ase, Some(&tag), &AnimationOptions::default()).unwrap();
+        assert!(!animation.looping);
+        assert_eq!(4, animation.frames.len());
+        assert_eq!(
+            vec![0, 1, 0, 1],
+            animation.frames.iter().map(|f| f.image).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            ase.frames[tag.from as usize].duration as u32,
+            animation.frames[2].duration
+        );
+        assert_eq!(ase.render_frame(tag.to as usize), animation.images[1]);
+
+        let timeline =
+            RenderedAnimation::from_ase(&ase, None, &AnimationOptions::default()).unwrap();
+        assert_eq!(ase.frames.len(), timeline.frames.len());
+        tag.to = 100;
+        assert!(
+            RenderedAnimation::from_ase(&ase, Some(&tag), &AnimationOptions::default()).is_err()
+        );
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn test_gif() {
+        let ase = complex();
+        let animation =
+            RenderedAnimation::from_ase(&ase, None, &AnimationOptions::default()).unwrap();
+        let mut data = Vec::new();
+        animation.write_gif(&mut data).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(data.as_slice()).unwrap();
+        assert_eq!(Some(gif::Repeat::Infinite), Some(decoder.repeat()));
+        for (i, frame) in ase.frames.iter().enumerate() {
+            let decoded = decoder.read_next_frame().unwrap().unwrap();
+            assert_eq!(frame.duration / 10, decoded.delay);
+            let expected = ase.render_frame(i);
+            for (e, d) in expected.data.chunks(4).zip(decoded.buffer.chunks(4)) {
+                if e[3] >= 128 {
+                    assert_eq!(&e[..3], &d[..3]);
+                } else {
+                    assert_eq!(0, d[3]);
+                }
+            }
+        }
+        assert!(decoder.read_next_frame().unwrap().is_none());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_apng() {
+        let ase = complex();
+        let mut tag = ase.tags[0].clone();
+        tag.repeat = Some(2);
+        let animation =
+            RenderedAnimation::from_ase(&ase, Some(&tag), &AnimationOptions::default()).unwrap();
+        let mut data = Vec::new();
+        animation.write_apng(&mut data).unwrap();
+
+        let mut reader = png::Decoder::new(data.as_slice()).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!(animation.frames.len() as u32, control.num_frames);
+        assert_eq!(1, control.num_plays);
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        for frame in &animation.frames {
+            reader.next_frame(&mut buffer).unwrap();
+            let control = reader.info().frame_control.unwrap();
+            assert_eq!(frame.duration as u16, control.delay_num);
+            assert_eq!(animation.images[frame.image].data, buffer);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Playback of tags and the sprite timeline.
+
+use alloc::vec::Vec;
+
+use crate::root_motion::RootMotion;
+use crate::{Direction, Frametag, Offset, SpritesheetData};
+
+/// Frame numbers in the order a tag plays them during one cycle.
+///
+/// A ping-pong tag doesn't repeat its first and last frames when it
+/// turns around, so `0..=2` plays as `[0, 1, 2, 1]`.
+pub fn tag_sequence(tag: &Frametag) -> Vec<u32> {
+    let (from, to) = (tag.from.min(tag.to), tag.from.max(tag.to));
+    match tag.direction {
+        Direction::Forward => (from..=to).collect(),
+        Direction::Reverse => (from..=to).rev().collect(),
+        Direction::Pingpong => (from..=to).chain((from + 1..to).rev()).collect(),
+        Direction::PingpongReverse => (from..=to).rev().chain(from + 1..to).collect(),
+    }
+}
+
+/// Frame numbers a tag plays from start to end, `None` if it loops forever.
+///
+/// Aseprite counts each pass of a ping-pong tag as one repeat, so `0..=2`
+/// repeated twice plays as `[0, 1, 2, 1, 0]`.
+pub fn tag_playback(tag: &Frametag) -> Option<Vec<u32>> {
+    let repeat = tag.repeat? as usize;
+    let cycle = tag_sequence(tag);
+    let len = match tag.direction {
+        Direction::Pingpong | Direction::PingpongReverse if cycle.len() > 1 => {
+            tag.from.abs_diff(tag.to) as usize * repeat + 1
+        }
+        _ => cycle.len() * repeat,
+    };
+    Some(cycle.into_iter().cycle().take(len).collect())
+}
+
+fn frame_durations(sheet: &SpritesheetData) -> Vec<u32> {
+    sheet.frames.iter().map(|frame| frame.duration).collect()
+}
+
+/// Playback state of a tag, looping forever.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<u32>,
+    durations: Vec<u32>,
+    position: usize,
+    elapsed: u32,
+    root_motion: Option<RootMotion>,
+    motion: Offset,
+}
+
+impl Animation {
+    /// Plays `tag` in its direction.
+    pub fn new(sheet: &SpritesheetData, tag: &Frametag) -> Self {
+        Self::from_durations(tag_sequence(tag), &frame_durations(sheet))
+    }
+
+    /// Plays every frame of the sprite in order.
+    pub fn timeline(sheet: &SpritesheetData) -> Self {
+        let durations = frame_durations(sheet);
+        Self::from_durations((0..durations.len() as u32).collect(), &durations)
+    }
+
+    /// Plays the frames of `sequence` in order.
+    ///
+    /// `durations` holds how long each frame number lasts in milliseconds. Frames past its end last zero milliseconds.
+    pub fn from_durations(sequence: Vec<u32>, durations: &[u32]) -> Self {
+        let durations = sequence
+            .iter()
+            .map(|&frame| durations.get(frame as usize).copied().unwrap_or(0))
+            .collect();
+        Self {
+            frames: sequence,
+            durations,
+            position: 0,
+            elapsed: 0,
+            root_motion: None,
+            motion: Offset::default(),
+        }
+    }
+
+    /// Accumulates `root_motion` while playing.
+    ///
+    /// The motion has to be extracted for the same tag this animation plays.
+    pub fn with_root_motion(mut self, root_motion: RootMotion) -> Self {
+        self.root_motion = Some(root_motion);
+        self
+    }
+
+    /// Current frame number.
+    pub fn frame(&self) -> u32 {
+        self.frames.get(self.position).copied().unwrap_or(0)
+    }
+
+    /// Position of the current frame within one cycle.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Frame numbers of one cycle, in playback order.
+    pub fn sequence(&self) -> &[u32] {
+        &self.frames
+    }
+
+    /// Milliseconds spent on the current frame so far.
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    /// Length of one cycle in milliseconds, `u32::MAX` if it's longer.
+    pub fn cycle_duration(&self) -> u32 {
+        u32::try_from(self.cycle_millis()).unwrap_or(u32::MAX)
+    }
+
+    fn cycle_millis(&self) -> u64 {
+        self.durations.iter().map(|&d| d as u64).sum()
+    }
+
+    /// Adds `(x, y)` to the accumulated motion, saturating rather than
+    /// overflowing.
+    fn add_motion(&mut self, x: i64, y: i64) {
+        let clamp = |v: i64| v.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.motion = Offset {
+            x: clamp(self.motion.x as i64 + x),
+            y: clamp(self.motion.y as i64 + y),
+        };
+    }
+
+    /// Goes back to the first frame and drops any accumulated root motion.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.elapsed = 0;
+        self.motion = Offset::default();
+    }
+
+    /// Advances the animation by `elapsed` milliseconds.
+    ///
+    /// Returns how many frames were stepped over, at most `u32::MAX`.
+    pub fn update(&mut self, elapsed: u32) -> u32 {
+        let cycle = self.cycle_millis();
+        if cycle == 0 {
+            return 0;
+        }
+
+        let mut elapsed = self.elapsed as u64 + elapsed as u64;
+        let mut steps = 0u64;
+        let cycles = elapsed / cycle;
+        if cycles > 0 {
+            elapsed -= cycles * cycle;
+            steps += cycles * self.frames.len() as u64;
+            if let Some(root_motion) = &self.root_motion {
+                let total = root_motion.cycle_total();
+                let cycles = cycles as i64;
+                self.add_motion(total.x as i64 * cycles, total.y as i64 * cycles);
+            }
+        }
+
+        while elapsed >= self.durations[self.position] as u64 {
+            elapsed -= self.durations[self.position] as u64;
+            self.position = (self.position + 1) % self.frames.len();
+            steps += 1;
+            if let Some(root_motion) = &self.root_motion {
+                let delta = root_motion.delta(self.position);
+                self.add_motion(delta.x as i64, delta.y as i64);
+            }
+        }
+        // Less than one cycle is left, which was under `u32::MAX` before.
+        self.elapsed = elapsed as u32;
+        u32::try_from(steps).unwrap_or(u32::MAX)
+    }
+
+    /// Root motion accumulated since the last call.
+    ///
+    /// Always zero unless [`Animation::with_root_motion`] was used.
+    pub fn take_root_motion(&mut self) -> Offset {
+        core::mem::take(&mut self.motion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complex() -> SpritesheetData {
+        let complex = aseprite_test_data::FileSet::complex_1_2_25();
+        serde_json::from_slice(complex.array_json).unwrap()
+    }
+
+    #[test]
+    fn test_tag_sequence() {
+        let sheet = complex();
+        let sequence = |name| tag_sequence(sheet.frame_tag(name).unwrap());
+        assert_eq!(vec![0, 1, 2], sequence("start"));
+        assert_eq!(vec![2, 3], sequence("ping-pong"));
+        assert_eq!(vec![5, 4], sequence("reverse"));
+
+        let mut tag = sheet.frame_tag("end").unwrap().clone();
+        tag.direction = Direction::Pingpong;
+        assert_eq!(vec![6, 7, 8, 7], tag_sequence(&tag));
+        tag.direction = Direction::PingpongReverse;
+        assert_eq!(vec![8, 7, 6, 7], tag_sequence(&tag));
+    }
+
+    #[test]
+    fn test_tag_playback() {
+        let sheet = complex();
+        let mut tag = sheet.frame_tag("end").unwrap().clone();
+        tag.repeat = None;
+        assert_eq!(None, tag_playback(&tag));
+        tag.repeat = Some(2);
+        assert_eq!(Some(vec![6, 7, 8, 6, 7, 8]), tag_playback(&tag));
+        tag.direction = Direction::Pingpong;
+        assert_eq!(Some(vec![6, 7, 8, 7, 6]), tag_playback(&tag));
+        tag.direction = Direction::PingpongReverse;
+        tag.repeat = Some(3);
+        assert_eq!(Some(vec![8, 7, 6, 7, 8, 7, 6]), tag_playback(&tag));
+        tag.to = tag.from;
+        assert_eq!(Some(vec![6; 3]), tag_playback(&tag));
+    }
+
+    #[test]
+    fn test_update() {
+        let sheet = complex();
+        // Frame n lasts 100 * (n + 1) milliseconds.
+        let mut animation = Animation::new(&sheet, sheet.frame_tag("reverse").unwrap());
+        assert_eq!(5, animation.frame());
+        assert_eq!(0, animation.update(599));
+        assert_eq!(1, animation.update(1));
+        assert_eq!(4, animation.frame());
+        assert_eq!(1, animation.update(500));
+        assert_eq!(5, animation.frame());
+        assert_eq!(5, animation.update(2 * 1100 + 650));
+        assert_eq!(4, animation.frame());
+        assert_eq!(50, animation.elapsed());
+
+        // A long pause on a short cycle steps over more frames than fit.
+        let mut animation = Animation::from_durations(vec![0, 1], &[1, 0]);
+        assert_eq!(u32::MAX, animation.update(u32::MAX));
+        assert_eq!(0, animation.frame());
+        let mut animation = Animation::from_durations(vec![0], &[2]);
+        animation.update(1);
+        assert_eq!(u32::MAX / 2 + 1, animation.update(u32::MAX));
+        assert_eq!(0, animation.elapsed());
+    }
+}
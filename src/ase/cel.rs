@@ -0,0 +1,131 @@
+use super::reader::{inflate, Reader};
+use super::writer::Writer;
+use super::{ColorMode, Tilemap, UserData};
+use crate::Error;
+
+/// Pixels of a cel, in the color mode of the sprite.
+///
+/// RGBA pixels take four bytes, grayscale pixels two (value and alpha)
+/// and indexed pixels one, the palette index.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct CelImage {
+    /// Width in pixels.
+    pub width: u16,
+    /// Height in pixels.
+    pub height: u16,
+    /// Pixel data, row by row.
+    pub data: Vec<u8>,
+}
+
+/// What a cel contains.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum CelContent {
+    /// Pixels of the cel.
+    Image(CelImage),
+    /// Same content as the cel of this layer in the given frame.
+    Linked(u16),
+    /// Tiles of a tilemap layer.
+    Tilemap(Tilemap),
+}
+
+/// Contents of a layer in a single frame.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct Cel {
+    /// Index of the layer.
+    pub layer: u16,
+    /// X position on the canvas.
+    pub x: i16,
+    /// Y position on the canvas.
+    pub y: i16,
+    /// Opacity of the cel.
+    pub opacity: u8,
+    /// Offset from the layer's position in the stack when drawing.
+    pub z_index: i16,
+    /// Cel contents.
+    pub content: CelContent,
+    /// Custom data.
+    pub user_data: UserData,
+}
+
+const CEL_RAW: u16 = 0;
+const CEL_LINKED: u16 = 1;
+const CEL_COMPRESSED: u16 = 2;
+const CEL_TILEMAP: u16 = 3;
+
+impl Cel {
+    /// Reads a cel chunk, `None` for kinds of cels that aren't supported.
+    ///
+    /// Pixels and tiles may take up to `max_size` bytes once decompressed.
+    pub(crate) fn parse(
+        r: &mut Reader,
+        mode: ColorMode,
+        max_size: usize,
+    ) -> Result<Option<Self>, Error> {
+        let layer = r.word()?;
+        let x = r.short()?;
+        let y = r.short()?;
+        let opacity = r.byte()?;
+        let kind = r.word()?;
+        let z_index = r.short()?;
+        r.skip(5)?;
+
+        let content = match kind {
+            CEL_RAW | CEL_COMPRESSED => {
+                let width = r.word()?;
+                let height = r.word()?;
+                let size = width as usize * height as usize * mode.bytes_per_pixel();
+                let what = format!("cel of {}x{} pixels", width, height);
+                let data = if kind == CEL_RAW {
+                    r.bytes(size)?.to_vec()
+                } else {
+                    inflate(r.bytes(r.remaining())?, size, max_size, &what)?
+                };
+                CelContent::Image(CelImage {
+                    width,
+                    height,
+                    data,
+                })
+            }
+            CEL_LINKED => CelContent::Linked(r.word()?),
+            CEL_TILEMAP => CelContent::Tilemap(Tilemap::parse(r, max_size)?),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            layer,
+            x,
+            y,
+            opacity,
+            z_index,
+            content,
+            user_data: UserData::default(),
+        }))
+    }
+
+    /// Writes a cel chunk, compressing images.
+    pub(crate) fn write(&self, w: &mut Writer) {
+        w.word(self.layer);
+        w.short(self.x);
+        w.short(self.y);
+        w.byte(self.opacity);
+        w.word(match self.content {
+            CelContent::Image(_) => CEL_COMPRESSED,
+            CelContent::Linked(_) => CEL_LINKED,
+            CelContent::Tilemap(_) => CEL_TILEMAP,
+        });
+        w.short(self.z_index);
+        w.zeros(5);
+        match &self.content {
+            CelContent::Image(image) => {
+                w.word(image.width);
+                w.word(image.height);
+                w.bytes(&miniz_oxide::deflate::compress_to_vec_zlib(&image.data, 6));
+            }
+            CelContent::Linked(frame) => w.word(*frame),
+            CelContent::Tilemap(tilemap) => tilemap.write(w),
+        }
+    }
+}
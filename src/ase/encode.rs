@@ -0,0 +1,361 @@
+//! Writing .ase files.
+
+use std::io::Write;
+
+use super::meta::{self, SLICE_COLOR};
+use super::writer::{count, Writer};
+use super::*;
+
+/// Frame being written, counting its chunks.
+struct FrameWriter {
+    w: Writer,
+    chunks: usize,
+}
+
+impl FrameWriter {
+    fn chunk(
+        &mut self,
+        kind: u16,
+        write: impl FnOnce(&mut Writer) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let start = self.w.len();
+        self.w.dword(0);
+        self.w.word(kind);
+        write(&mut self.w)?;
+        let size = count(self.w.len() - start, "bytes in a chunk")?;
+        self.w.patch_dword(start, size);
+        self.chunks += 1;
+        Ok(())
+    }
+
+    fn user_data(&mut self, user_data: &UserData) -> Result<(), Error> {
+        self.chunk(CHUNK_USER_DATA, |w| user_data.write(w))
+    }
+
+    /// Writes user data unless it's empty, for things that don't need a
+    /// chunk per item.
+    fn optional_user_data(&mut self, user_data: &UserData) -> Result<(), Error> {
+        if *user_data != UserData::default() {
+            self.user_data(user_data)?;
+        }
+        Ok(())
+    }
+}
+
+impl AseFile {
+    /// Writes the file in the .ase format.
+    ///
+    /// Cels and tilesets are compressed. The header's file size and frame
+    /// count are taken from what is written rather than from `header`.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Encodes the file in the .ase format, see [`AseFile::write`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut w = Writer::new();
+        self.write_header(&mut w)?;
+        for index in 0..self.frames.len() {
+            self.write_frame(&mut w, index)?;
+        }
+        let size = count(w.len(), "bytes in a file")?;
+        w.patch_dword(0, size);
+        Ok(w.into_bytes())
+    }
+
+    fn write_header(&self, w: &mut Writer) -> Result<(), Error> {
+        let header = &self.header;
+        w.dword(0);
+        w.word(HEADER_MAGIC);
+        w.word(count(self.frames.len(), "frames")?);
+        w.word(header.width);
+        w.word(header.height);
+        w.word(header.color_depth);
+        w.dword(header.flags);
+        w.word(header.speed);
+        w.zeros(8);
+        w.byte(header.transparent_index);
+        w.zeros(3);
+        w.word(header.num_colors);
+        w.byte(header.pixel_width);
+        w.byte(header.pixel_height);
+        w.short(header.grid_x);
+        w.short(header.grid_y);
+        w.word(header.grid_width);
+        w.word(header.grid_height);
+        w.zeros(84);
+        debug_assert_eq!(HEADER_SIZE, w.len());
+        Ok(())
+    }
+
+    fn write_frame(&self, w: &mut Writer, index: usize) -> Result<(), Error> {
+        let frame = &self.frames[index];
+        let mut f = FrameWriter {
+            w: Writer::new(),
+            chunks: 0,
+        };
+
+        if index == 0 {
+            self.write_sprite_chunks(&mut f)?;
+        }
+        for cel in &frame.cels {
+            f.chunk(CHUNK_CEL, |w| {
+                cel.write(w);
+                Ok(())
+            })?;
+            f.optional_user_data(&cel.user_data)?;
+        }
+
+        let size = count(FRAME_HEADER_SIZE + f.w.len(), "bytes in a frame")?;
+        w.dword(size);
+        w.word(FRAME_MAGIC);
+        w.word(f.chunks.min(0xFFFF) as u16);
+        w.word(frame.duration);
+        w.zeros(2);
+        w.dword(count(f.chunks, "chunks")?);
+        w.bytes(&f.w.into_bytes());
+        Ok(())
+    }
+
+    /// Chunks that describe the whole sprite, which go in the first frame.
+    fn write_sprite_chunks(&self, f: &mut FrameWriter) -> Result<(), Error> {
+        if !self.external_files.is_empty() {
+            f.chunk(CHUNK_EXTERNAL_FILES, |w| {
+                meta::write_external_files(w, &self.external_files)
+            })?;
+        }
+
+        if !self.palette.is_empty() {
+            // Older readers only know the old chunk, which has no alpha.
+            if self.palette.len() <= 256 {
+                f.chunk(CHUNK_OLD_PALETTE_256, |w| {
+                    write_old_palette(w, &self.palette);
+                    Ok(())
+                })?;
+            }
+            f.chunk(CHUNK_PALETTE, |w| write_palette(w, &self.palette))?;
+            f.optional_user_data(&self.user_data)?;
+        }
+
+        for tileset in &self.tilesets {
+            f.chunk(CHUNK_TILESET, |w| tileset.write(w))?;
+            if tileset
+                .tile_user_data
+                .iter()
+                .any(|u| *u != UserData::default())
+            {
+                f.user_data(&tileset.user_data)?;
+                for tile in &tileset.tile_user_data {
+                    f.user_data(tile)?;
+                }
+            } else {
+                f.optional_user_data(&tileset.user_data)?;
+            }
+        }
+
+        let has_uuid = self.header.flags & Header::FLAG_LAYER_UUID != 0;
+        for layer in &self.layers {
+            f.chunk(CHUNK_LAYER, |w| layer.write(w, has_uuid))?;
+            f.optional_user_data(&layer.user_data)?;
+        }
+
+        if !self.tags.is_empty() {
+            f.chunk(CHUNK_TAGS, |w| meta::write_tags(w, &self.tags))?;
+            // Every tag gets a chunk, to keep the following ones in place.
+            for (i, tag) in self.tags.iter().enumerate() {
+                let mut user_data = self.tag_user_data.get(i).cloned().unwrap_or_default();
+                user_data.text = tag.data.clone();
+                // Opaque colors fit in the tag chunk itself.
+                user_data.color = match (user_data.color, tag.color) {
+                    (None, Some(color)) if color.a == 255 => None,
+                    (_, color) => color,
+                };
+                f.user_data(&user_data)?;
+            }
+        }
+
+        for (i, slice) in self.slices.iter().enumerate() {
+            f.chunk(CHUNK_SLICE, |w| meta::write_slice(w, slice))?;
+            let mut user_data = self.slice_user_data.get(i).cloned().unwrap_or_default();
+            user_data.text = slice.data.clone();
+            user_data.color = match user_data.color {
+                None if slice.color == SLICE_COLOR => None,
+                _ => Some(slice.color),
+            };
+            f.optional_user_data(&user_data)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_palette(w: &mut Writer, palette: &Palette) -> Result<(), Error> {
+    w.dword(count(palette.len(), "palette entries")?);
+    w.dword(0);
+    w.dword(count(palette.len() - 1, "palette entries")?);
+    w.zeros(8);
+    for entry in &palette.entries {
+        w.word(entry.name.is_some() as u16);
+        let c = entry.color;
+        w.bytes(&[c.r, c.g, c.b, c.a]);
+        if let Some(name) = &entry.name {
+            w.string(name)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_old_palette(w: &mut Writer, palette: &Palette) {
+    w.word(1);
+    w.byte(0);
+    // A count of zero means 256 colors.
+    w.byte(palette.len() as u8);
+    for c in palette.colors() {
+        w.bytes(&[c.r, c.g, c.b]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, Rect};
+
+    /// Clears what differs between equal sprites written differently.
+    fn normalize(mut ase: AseFile) -> AseFile {
+        ase.header.file_size = 0;
+        ase
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for file in aseprite_test_data::FileSet::list() {
+            let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+            let bytes = ase.to_bytes().unwrap();
+            let parsed = AseFile::from_bytes(&bytes).unwrap();
+            assert_eq!(bytes.len() as u32, parsed.header.file_size);
+            assert_eq!(
+                normalize(ase.clone()),
+                normalize(parsed.clone()),
+                "{}",
+                file.name
+            );
+            assert_eq!(bytes, parsed.to_bytes().unwrap(), "{}", file.name);
+            for frame in 0..ase.frames.len() {
+                assert_eq!(ase.render_frame(frame), parsed.render_frame(frame));
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice_off_canvas() {
+        let mut w = Writer::new();
+        w.dword(1);
+        w.dword(meta::SLICE_NINE_PATCH | meta::SLICE_PIVOT);
+        w.dword(0);
+        w.string("hitbox").unwrap();
+        w.dword(0);
+        for v in [-5, -2, 20, 10, 6, 4, 4, 2, 8, 1] {
+            w.long(v);
+        }
+        let bytes = w.into_bytes();
+        let slice = meta::parse_slice(&mut Reader::new(&bytes)).unwrap();
+        let key = &slice.keys[0];
+        let rect = |x, y, w, h| Rect { x, y, w, h };
+        assert_eq!(rect(0, 0, 15, 8), key.bounds);
+        assert_eq!(Some(rect(1, 2, 4, 2)), key.center);
+        assert_eq!(Some(Point { x: 3, y: 0 }), key.pivot);
+
+        let mut w = Writer::new();
+        meta::write_slice(&mut w, &slice).unwrap();
+        let bytes = w.into_bytes();
+        assert_eq!(slice, meta::parse_slice(&mut Reader::new(&bytes)).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip_extras() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let mut ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+
+        let mut properties = Properties::new();
+        properties.insert("hp".to_string(), PropertyValue::U16(30));
+        properties.insert(
+            "mixed".to_string(),
+            PropertyValue::Vector(vec![
+                PropertyValue::String("a".to_string()),
+                PropertyValue::Rect {
+                    x: -1,
+                    y: 2,
+                    w: 3,
+                    h: 4,
+                },
+                PropertyValue::Map(Properties::new()),
+            ]),
+        );
+        ase.user_data.properties.insert(0, properties.clone());
+        ase.user_data.text = Some("sprite".to_string());
+        ase.frames[1].cels[0]
+            .user_data
+            .properties
+            .insert(3, properties);
+        ase.tags[0].color = Some(Color {
+            r: 1,
+            g: 2,
+            b: 3,
+            a: 4,
+        });
+        ase.tag_user_data[0].color = ase.tags[0].color;
+        ase.external_files.push(ExternalFile {
+            id: 3,
+            kind: ExternalFileKind::PropertiesExtension,
+            name: "my-extension".to_string(),
+        });
+        ase.header.flags |= Header::FLAG_LAYER_UUID;
+        for (i, layer) in ase.layers.iter_mut().enumerate() {
+            layer.uuid = Some([i as u8; 16]);
+        }
+
+        let mut tileset = Tileset {
+            id: 0,
+            flags: Tileset::FLAG_TILES | Tileset::FLAG_EMPTY_TILE_0,
+            name: "tiles".to_string(),
+            tile_width: 2,
+            tile_height: 2,
+            base_index: 1,
+            tile_count: 2,
+            tiles: vec![
+                CelImage {
+                    width: 2,
+                    height: 2,
+                    data: vec![0, 1, 2, 3],
+                };
+                2
+            ],
+            link: None,
+            user_data: UserData::default(),
+            tile_user_data: vec![UserData::default(); 2],
+        };
+        tileset.tile_user_data[1].text = Some("wall".to_string());
+        ase.tilesets.push(tileset);
+        let mut layer = ase.layers[1].clone();
+        layer.kind = LayerKind::Tilemap(0);
+        ase.layers.push(layer);
+        let mut cel = ase.frames[0].cels[0].clone();
+        cel.layer = ase.layers.len() as u16 - 1;
+        cel.content = CelContent::Tilemap(Tilemap {
+            width: 2,
+            height: 1,
+            tiles: vec![
+                Tile {
+                    id: 1,
+                    flip_y: true,
+                    ..Tile::default()
+                },
+                Tile::default(),
+            ],
+        });
+        ase.frames[0].cels.push(cel);
+
+        let parsed = AseFile::from_bytes(&ase.to_bytes().unwrap()).unwrap();
+        assert_eq!(normalize(ase), normalize(parsed));
+    }
+}
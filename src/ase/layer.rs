@@ -0,0 +1,166 @@
+use super::reader::Reader;
+use super::writer::Writer;
+use super::UserData;
+use crate::{BlendMode, Error};
+
+/// Kind of layer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum LayerKind {
+    /// Layer with image cels.
+    Normal,
+    /// Group of the layers that follow it with a higher child level.
+    Group,
+    /// Layer whose cels are tilemaps, with the index of its tileset.
+    Tilemap(u32),
+}
+
+/// Layer of an .ase file.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct AseLayer {
+    /// Layer name.
+    pub name: String,
+    /// Layer flags, see the `FLAG_*` constants.
+    pub flags: u16,
+    /// Kind of layer.
+    pub kind: LayerKind,
+    /// Nesting depth; a layer belongs to the closest preceding group with a lower level.
+    pub child_level: u16,
+    /// Blend mode.
+    pub blend_mode: BlendMode,
+    /// Opacity, only meaningful if the header says layer opacity is valid.
+    pub opacity: u8,
+    /// Unique id, present in files that have layer UUIDs enabled.
+    pub uuid: Option<[u8; 16]>,
+    /// Custom data.
+    pub user_data: UserData,
+}
+
+impl AseLayer {
+    /// The layer is visible.
+    pub const FLAG_VISIBLE: u16 = 1;
+    /// The layer can be edited.
+    pub const FLAG_EDITABLE: u16 = 2;
+    /// The layer's position is locked.
+    pub const FLAG_LOCK_MOVEMENT: u16 = 4;
+    /// The layer is the opaque background layer.
+    pub const FLAG_BACKGROUND: u16 = 8;
+    /// Linked cels are preferred for new frames.
+    pub const FLAG_PREFER_LINKED_CELS: u16 = 16;
+    /// The group is shown collapsed.
+    pub const FLAG_COLLAPSED: u16 = 32;
+    /// The layer is a reference layer, which isn't part of the sprite.
+    pub const FLAG_REFERENCE: u16 = 64;
+
+    /// Whether the layer itself is visible, disregarding the groups it's in.
+    pub fn is_visible(&self) -> bool {
+        self.flags & Self::FLAG_VISIBLE != 0
+    }
+
+    /// Whether this is the background layer.
+    pub fn is_background(&self) -> bool {
+        self.flags & Self::FLAG_BACKGROUND != 0
+    }
+
+    /// Whether this is a reference layer.
+    pub fn is_reference(&self) -> bool {
+        self.flags & Self::FLAG_REFERENCE != 0
+    }
+
+    pub(crate) fn parse(r: &mut Reader, has_uuid: bool) -> Result<Self, Error> {
+        let flags = r.word()?;
+        let kind = r.word()?;
+        let child_level = r.word()?;
+        let _default_width = r.word()?;
+        let _default_height = r.word()?;
+        let blend_mode = blend_mode_from_index(r.word()?);
+        let opacity = r.byte()?;
+        r.skip(3)?;
+        let name = r.string()?;
+        let kind = match kind {
+            0 => LayerKind::Normal,
+            1 => LayerKind::Group,
+            2 => LayerKind::Tilemap(r.dword()?),
+            n => return Err(Error::format(format!("unknown layer type {}", n))),
+        };
+        let uuid = if has_uuid {
+            let mut uuid = [0; 16];
+            uuid.copy_from_slice(r.bytes(16)?);
+            Some(uuid)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            flags,
+            kind,
+            child_level,
+            blend_mode,
+            opacity,
+            uuid,
+            user_data: UserData::default(),
+        })
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer, has_uuid: bool) -> Result<(), Error> {
+        w.word(self.flags);
+        w.word(match self.kind {
+            LayerKind::Normal => 0,
+            LayerKind::Group => 1,
+            LayerKind::Tilemap(_) => 2,
+        });
+        w.word(self.child_level);
+        w.zeros(4);
+        w.word(blend_mode_index(self.blend_mode));
+        w.byte(self.opacity);
+        w.zeros(3);
+        w.string(&self.name)?;
+        if let LayerKind::Tilemap(tileset) = self.kind {
+            w.dword(tileset);
+        }
+        if has_uuid {
+            w.bytes(&self.uuid.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+/// Blend modes in the order the file format numbers them.
+pub(crate) const BLEND_MODES: [BlendMode; 19] = [
+    BlendMode::Normal,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+    BlendMode::Overlay,
+    BlendMode::Darken,
+    BlendMode::Lighten,
+    BlendMode::ColorDodge,
+    BlendMode::ColorBurn,
+    BlendMode::HardLight,
+    BlendMode::SoftLight,
+    BlendMode::Difference,
+    BlendMode::Exclusion,
+    BlendMode::HslHue,
+    BlendMode::HslSaturation,
+    BlendMode::HslColor,
+    BlendMode::HslLuminosity,
+    BlendMode::Addition,
+    BlendMode::Subtract,
+    BlendMode::Divide,
+];
+
+/// Unknown blend modes from newer versions fall back to normal.
+fn blend_mode_from_index(index: u16) -> BlendMode {
+    BLEND_MODES
+        .get(index as usize)
+        .copied()
+        .unwrap_or(BlendMode::Normal)
+}
+
+fn blend_mode_index(blend_mode: BlendMode) -> u16 {
+    BLEND_MODES
+        .iter()
+        .position(|&mode| mode == blend_mode)
+        .unwrap_or(0) as u16
+}
@@ -0,0 +1,143 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use super::reader::Reader;
+use super::{check_tilemap_size, AseFile, Cel, CelContent, CelImage, Parser, UserData};
+use crate::{Error, Limits};
+
+/// Where the chunk of a cel that hasn't been loaded is.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct CelEntry {
+    pub(crate) layer: u16,
+    /// Offset of the chunk data, after the chunk header.
+    pub(crate) offset: u64,
+    pub(crate) len: u64,
+    pub(crate) user_data: UserData,
+}
+
+/// .ase file whose cels are only read when asked for.
+///
+/// Everything but the cels is parsed up front, so listing layers, tags or
+/// slices of a large file doesn't decompress any pixels.
+#[derive(Debug)]
+pub struct LazyAseFile<S> {
+    source: S,
+    file: AseFile,
+    cels: Vec<Vec<CelEntry>>,
+    limits: Limits,
+}
+
+impl<'a> LazyAseFile<Cursor<&'a [u8]>> {
+    /// Indexes an .ase file held in memory.
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, Error> {
+        Self::new(Cursor::new(data))
+    }
+}
+
+impl<S: Read + Seek> LazyAseFile<S> {
+    /// Parses the metadata of an .ase file and notes where its cels are.
+    pub fn new(source: S) -> Result<Self, Error> {
+        Self::with_limits(source, &Limits::default())
+    }
+
+    /// Parses the metadata of an .ase file and notes where its cels are,
+    /// rejecting the file or cels that exceed `limits`.
+    pub fn with_limits(mut source: S, limits: &Limits) -> Result<Self, Error> {
+        let mut parser = Parser::parse(&mut source, true, limits)?;
+        let cels = parser.lazy_cels.take().unwrap_or_default();
+        Ok(Self {
+            source,
+            file: parser.finish()?,
+            cels,
+            limits: *limits,
+        })
+    }
+
+    /// Everything but the cels, whose frames are all empty.
+    pub fn metadata(&self) -> &AseFile {
+        &self.file
+    }
+
+    /// Number of frames.
+    pub fn frame_count(&self) -> usize {
+        self.cels.len()
+    }
+
+    /// Reads the cel of a layer in a frame, `None` if there's none.
+    pub fn cel(&mut self, frame: usize, layer: usize) -> Result<Option<Cel>, Error> {
+        let Some(entry) = self
+            .cels
+            .get(frame)
+            .and_then(|cels| cels.iter().find(|cel| cel.layer as usize == layer))
+        else {
+            return Ok(None);
+        };
+        let user_data = entry.user_data.clone();
+        self.source.seek(SeekFrom::Start(entry.offset))?;
+        let mut data = Vec::new();
+        (&mut self.source).take(entry.len).read_to_end(&mut data)?;
+        if (data.len() as u64) < entry.len {
+            return Err(Error::format("unexpected end of file"));
+        }
+        let mode = self.file.header.color_mode();
+        let cel = Cel::parse(&mut Reader::new(&data), mode, self.limits.max_cel_size)?;
+        if let Some(cel) = &cel {
+            check_tilemap_size(&self.file, cel, self.limits.max_cel_size)?;
+        }
+        Ok(cel.map(|cel| Cel { user_data, ..cel }))
+    }
+
+    /// Reads the pixels of the cel of a layer in a frame, following linked
+    /// cels, like [`AseFile::cel_image`].
+    pub fn cel_image(&mut self, frame: usize, layer: usize) -> Result<Option<CelImage>, Error> {
+        let mut frame = frame;
+        for _ in 0..=self.cels.len() {
+            match self.cel(frame, layer)?.map(|cel| cel.content) {
+                Some(CelContent::Image(image)) => return Ok(Some(image)),
+                Some(CelContent::Linked(linked)) => frame = linked as usize,
+                _ => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads every cel, giving the same file [`AseFile::read`] would.
+    pub fn load(mut self) -> Result<AseFile, Error> {
+        for frame in 0..self.cels.len() {
+            for i in 0..self.cels[frame].len() {
+                let layer = self.cels[frame][i].layer as usize;
+                if let Some(cel) = self.cel(frame, layer)? {
+                    self.file.frames[frame].cels.push(cel);
+                }
+            }
+        }
+        Ok(self.file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+
+        let mut lazy = LazyAseFile::from_bytes(file.src_aseprite).unwrap();
+        assert_eq!(ase.layers, lazy.metadata().layers);
+        assert_eq!(ase.tags, lazy.metadata().tags);
+        assert!(lazy.metadata().frames.iter().all(|f| f.cels.is_empty()));
+        assert_eq!(ase.cel(3, 1).cloned(), lazy.cel(3, 1).unwrap());
+        assert_eq!(ase.cel_image(3, 1).cloned(), lazy.cel_image(3, 1).unwrap());
+        assert_eq!(None, lazy.cel(3, 100).unwrap());
+
+        // From a seekable reader, which doesn't start at the file.
+        let mut data = vec![0; 5];
+        data.extend_from_slice(file.src_aseprite);
+        let mut reader = Cursor::new(data);
+        reader.seek(SeekFrom::Start(5)).unwrap();
+        let lazy = LazyAseFile::new(reader).unwrap();
+        assert_eq!(ase.frames.len(), lazy.frame_count());
+        assert_eq!(ase, lazy.load().unwrap());
+    }
+}
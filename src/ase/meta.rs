@@ -0,0 +1,244 @@
+use super::reader::Reader;
+use super::writer::{count, Writer};
+use crate::{Color, Direction, Error, Frametag, Point, Rect, Slice, SliceKey};
+
+/// Default color of slices without user data.
+pub(crate) const SLICE_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 255,
+    a: 255,
+};
+
+pub(crate) fn parse_tags(r: &mut Reader) -> Result<Vec<Frametag>, Error> {
+    let len = r.word()?;
+    r.skip(8)?;
+    let mut tags = Vec::new();
+    for _ in 0..len {
+        let from = r.word()? as u32;
+        let to = r.word()? as u32;
+        let direction = match r.byte()? {
+            0 => Direction::Forward,
+            1 => Direction::Reverse,
+            2 => Direction::Pingpong,
+            3 => Direction::PingpongReverse,
+            n => return Err(Error::format(format!("unknown tag direction {}", n))),
+        };
+        let repeat = match r.word()? {
+            0 => None,
+            n => Some(n as u32),
+        };
+        r.skip(6)?;
+        let color = Color {
+            r: r.byte()?,
+            g: r.byte()?,
+            b: r.byte()?,
+            a: 255,
+        };
+        r.skip(1)?;
+        let name = r.string()?;
+        tags.push(Frametag {
+            name,
+            from,
+            to,
+            direction,
+            repeat,
+            color: Some(color),
+            data: None,
+        });
+    }
+    Ok(tags)
+}
+
+pub(crate) fn write_tags(w: &mut Writer, tags: &[Frametag]) -> Result<(), Error> {
+    w.word(count(tags.len(), "tags")?);
+    w.zeros(8);
+    for tag in tags {
+        w.word(count(tag.from as usize, "frames")?);
+        w.word(count(tag.to as usize, "frames")?);
+        w.byte(match tag.direction {
+            Direction::Forward => 0,
+            Direction::Reverse => 1,
+            Direction::Pingpong => 2,
+            Direction::PingpongReverse => 3,
+        });
+        w.word(count(tag.repeat.unwrap_or(0) as usize, "tag repeats")?);
+        w.zeros(6);
+        let color = tag.color.unwrap_or(Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        });
+        w.bytes(&[color.r, color.g, color.b, 0]);
+        w.string(&tag.name)?;
+    }
+    Ok(())
+}
+
+pub(crate) const SLICE_NINE_PATCH: u32 = 1;
+pub(crate) const SLICE_PIVOT: u32 = 2;
+
+/// Slices may reach past the top left of the canvas, which `Rect` can't
+/// hold, so that part is cut off.
+fn clamped_rect(x: i32, y: i32, w: u32, h: u32) -> Rect {
+    Rect {
+        x: x.max(0) as u32,
+        y: y.max(0) as u32,
+        w: w.saturating_sub(x.min(0).unsigned_abs()),
+        h: h.saturating_sub(y.min(0).unsigned_abs()),
+    }
+}
+
+pub(crate) fn parse_slice(r: &mut Reader) -> Result<Slice, Error> {
+    let len = r.dword()?;
+    let flags = r.dword()?;
+    r.skip(4)?;
+    let name = r.string()?;
+    let mut keys = Vec::new();
+    for _ in 0..len {
+        let frame = r.dword()?;
+        let (x, y) = (r.long()?, r.long()?);
+        let bounds = clamped_rect(x, y, r.dword()?, r.dword()?);
+        // The center and pivot are relative to the slice, so they move
+        // back by whatever was cut off it. A pivot left of or above the
+        // slice still ends up on its edge.
+        let (cut_x, cut_y) = (x.min(0), y.min(0));
+        let center = if flags & SLICE_NINE_PATCH != 0 {
+            let (x, y) = (r.long()?, r.long()?);
+            Some(clamped_rect(
+                x.saturating_add(cut_x),
+                y.saturating_add(cut_y),
+                r.dword()?,
+                r.dword()?,
+            ))
+        } else {
+            None
+        };
+        let pivot = if flags & SLICE_PIVOT != 0 {
+            Some(Point {
+                x: r.long()?.saturating_add(cut_x).max(0) as u32,
+                y: r.long()?.saturating_add(cut_y).max(0) as u32,
+            })
+        } else {
+            None
+        };
+        keys.push(SliceKey {
+            frame,
+            bounds,
+            pivot,
+            center,
+        });
+    }
+    Ok(Slice {
+        name,
+        color: SLICE_COLOR,
+        keys,
+        data: None,
+    })
+}
+
+pub(crate) fn write_slice(w: &mut Writer, slice: &Slice) -> Result<(), Error> {
+    let mut flags = 0;
+    if slice.keys.iter().any(|key| key.center.is_some()) {
+        flags |= SLICE_NINE_PATCH;
+    }
+    if slice.keys.iter().any(|key| key.pivot.is_some()) {
+        flags |= SLICE_PIVOT;
+    }
+    let rect = |w: &mut Writer, rect: Rect| {
+        w.long(rect.x as i32);
+        w.long(rect.y as i32);
+        w.dword(rect.w);
+        w.dword(rect.h);
+    };
+
+    w.dword(count(slice.keys.len(), "slice keys")?);
+    w.dword(flags);
+    w.dword(0);
+    w.string(&slice.name)?;
+    for key in &slice.keys {
+        w.dword(key.frame);
+        rect(w, key.bounds);
+        if flags & SLICE_NINE_PATCH != 0 {
+            rect(
+                w,
+                key.center.unwrap_or(Rect {
+                    x: 0,
+                    y: 0,
+                    w: 0,
+                    h: 0,
+                }),
+            );
+        }
+        if flags & SLICE_PIVOT != 0 {
+            let pivot = key.pivot.unwrap_or(Point { x: 0, y: 0 });
+            w.long(pivot.x as i32);
+            w.long(pivot.y as i32);
+        }
+    }
+    Ok(())
+}
+
+/// What an external file entry refers to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ExternalFileKind {
+    /// Palette file.
+    Palette,
+    /// Tileset file.
+    Tileset,
+    /// Extension that owns user data properties.
+    PropertiesExtension,
+    /// Extension that manages tiles.
+    TileManagementExtension,
+}
+
+/// Entry of the external files chunk.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct ExternalFile {
+    /// Id other chunks refer to the entry by.
+    pub id: u32,
+    /// What the entry refers to.
+    pub kind: ExternalFileKind,
+    /// File name, or extension id for extensions.
+    pub name: String,
+}
+
+pub(crate) fn parse_external_files(r: &mut Reader) -> Result<Vec<ExternalFile>, Error> {
+    let len = r.dword()?;
+    r.skip(8)?;
+    let mut files = Vec::new();
+    for _ in 0..len {
+        let id = r.dword()?;
+        let kind = match r.byte()? {
+            0 => ExternalFileKind::Palette,
+            1 => ExternalFileKind::Tileset,
+            2 => ExternalFileKind::PropertiesExtension,
+            3 => ExternalFileKind::TileManagementExtension,
+            n => return Err(Error::format(format!("unknown external file type {}", n))),
+        };
+        r.skip(7)?;
+        let name = r.string()?;
+        files.push(ExternalFile { id, kind, name });
+    }
+    Ok(files)
+}
+
+pub(crate) fn write_external_files(w: &mut Writer, files: &[ExternalFile]) -> Result<(), Error> {
+    w.dword(count(files.len(), "external files")?);
+    w.zeros(8);
+    for file in files {
+        w.dword(file.id);
+        w.byte(match file.kind {
+            ExternalFileKind::Palette => 0,
+            ExternalFileKind::Tileset => 1,
+            ExternalFileKind::PropertiesExtension => 2,
+            ExternalFileKind::TileManagementExtension => 3,
+        });
+        w.zeros(7);
+        w.string(&file.name)?;
+    }
+    Ok(())
+}
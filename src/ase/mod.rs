@@ -0,0 +1,973 @@
+//! Loading and saving Aseprite's own `.ase`/`.aseprite` files.
+//!
+//! The format is described at
+//! <https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md>.
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use crate::palette::{Palette, PaletteEntry};
+use crate::{Color, Error, Frametag, Limits, Slice};
+
+mod cel;
+mod encode;
+pub(crate) mod layer;
+mod lazy;
+mod meta;
+mod reader;
+mod render;
+mod tileset;
+mod user_data;
+pub(crate) mod writer;
+
+pub use cel::{Cel, CelContent, CelImage};
+pub use layer::{AseLayer, LayerKind};
+use lazy::CelEntry;
+pub use lazy::LazyAseFile;
+pub use meta::{ExternalFile, ExternalFileKind};
+use reader::Reader;
+pub use tileset::{Tile, Tilemap, Tileset, TilesetLink};
+pub use user_data::{Properties, PropertyValue, UserData};
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+const FRAME_HEADER_SIZE: usize = 16;
+const CHUNK_HEADER_SIZE: usize = 6;
+
+const CHUNK_OLD_PALETTE_256: u16 = 0x0004;
+const CHUNK_OLD_PALETTE_64: u16 = 0x0011;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_EXTERNAL_FILES: u16 = 0x2008;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_USER_DATA: u16 = 0x2020;
+const CHUNK_SLICE: u16 = 0x2022;
+const CHUNK_TILESET: u16 = 0x2023;
+
+/// How pixels are stored.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ColorMode {
+    /// Four bytes per pixel: red, green, blue and alpha.
+    Rgba,
+    /// Two bytes per pixel: value and alpha.
+    Grayscale,
+    /// One byte per pixel, an index into the palette.
+    Indexed,
+}
+
+impl ColorMode {
+    /// Number of bytes each pixel takes.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            ColorMode::Rgba => 4,
+            ColorMode::Grayscale => 2,
+            ColorMode::Indexed => 1,
+        }
+    }
+}
+
+/// File header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub struct Header {
+    /// Size of the whole file in bytes.
+    pub file_size: u32,
+    /// Number of frames.
+    pub frames: u16,
+    /// Canvas width in pixels.
+    pub width: u16,
+    /// Canvas height in pixels.
+    pub height: u16,
+    /// Bits per pixel: 32 for RGBA, 16 for grayscale and 8 for indexed.
+    pub color_depth: u16,
+    /// Header flags.
+    ///
+    /// Bit 1 means layer opacity is valid.
+    pub flags: u32,
+    /// Frame duration in milliseconds, superseded by the duration of each frame.
+    pub speed: u16,
+    /// Palette index that is transparent, in indexed sprites.
+    pub transparent_index: u8,
+    /// Number of colors in the palette.
+    pub num_colors: u16,
+    /// Pixel width, for pixel ratios other than 1:1.
+    pub pixel_width: u8,
+    /// Pixel height, for pixel ratios other than 1:1.
+    pub pixel_height: u8,
+    /// X position of the grid.
+    pub grid_x: i16,
+    /// Y position of the grid.
+    pub grid_y: i16,
+    /// Width of a grid cell, zero if there's no grid.
+    pub grid_width: u16,
+    /// Height of a grid cell, zero if there's no grid.
+    pub grid_height: u16,
+}
+
+impl Header {
+    /// Layer opacity is valid.
+    pub const FLAG_LAYER_OPACITY: u32 = 1;
+    /// Layer groups have a blend mode and opacity.
+    pub const FLAG_GROUP_OPACITY: u32 = 2;
+    /// Layers have a UUID.
+    pub const FLAG_LAYER_UUID: u32 = 4;
+
+    /// How pixels are stored, based on the color depth.
+    pub fn color_mode(&self) -> ColorMode {
+        match self.color_depth {
+            8 => ColorMode::Indexed,
+            16 => ColorMode::Grayscale,
+            _ => ColorMode::Rgba,
+        }
+    }
+
+    fn parse(r: &mut Reader) -> Result<Self, Error> {
+        let file_size = r.dword()?;
+        let magic = r.word()?;
+        if magic != HEADER_MAGIC {
+            return Err(Error::format(format!(
+                "not an aseprite file, magic number is {:#06x} instead of {:#06x}",
+                magic, HEADER_MAGIC
+            )));
+        }
+        let frames = r.word()?;
+        let width = r.word()?;
+        let height = r.word()?;
+        let color_depth = r.word()?;
+        if ![8, 16, 32].contains(&color_depth) {
+            return Err(Error::format(format!(
+                "unsupported color depth {}",
+                color_depth
+            )));
+        }
+        let flags = r.dword()?;
+        let speed = r.word()?;
+        r.skip(8)?;
+        let transparent_index = r.byte()?;
+        r.skip(3)?;
+        let num_colors = match r.word()? {
+            0 => 256,
+            n => n,
+        };
+        let pixel_width = r.byte()?;
+        let pixel_height = r.byte()?;
+        let grid_x = r.short()?;
+        let grid_y = r.short()?;
+        let grid_width = r.word()?;
+        let grid_height = r.word()?;
+        r.skip(84)?;
+        debug_assert_eq!(HEADER_SIZE, r.position());
+
+        Ok(Self {
+            file_size,
+            frames,
+            width,
+            height,
+            color_depth,
+            flags,
+            speed,
+            transparent_index,
+            num_colors,
+            pixel_width,
+            pixel_height,
+            grid_x,
+            grid_y,
+            grid_width,
+            grid_height,
+        })
+    }
+}
+
+/// Single frame of an .ase file.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct AseFrame {
+    /// Frame duration in milliseconds.
+    pub duration: u16,
+    /// Cels in the frame, in the order they're stored.
+    pub cels: Vec<Cel>,
+}
+
+/// Parsed .ase file.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct AseFile {
+    /// File header.
+    pub header: Header,
+    /// Frames in order.
+    pub frames: Vec<AseFrame>,
+    /// Layers from bottom to top.
+    pub layers: Vec<AseLayer>,
+    /// Sprite palette.
+    ///
+    /// Taken from the palette chunks, or from the old palette chunks of
+    /// files written before Aseprite 1.1 when there are no new ones.
+    pub palette: Palette,
+    /// Tags, with their color and custom data from the user data.
+    pub tags: Vec<Frametag>,
+    /// User data of each tag, in the same order as `tags`.
+    pub tag_user_data: Vec<UserData>,
+    /// Slices, with their color and custom data from the user data.
+    pub slices: Vec<Slice>,
+    /// User data of each slice, in the same order as `slices`.
+    pub slice_user_data: Vec<UserData>,
+    /// User data of the sprite itself.
+    pub user_data: UserData,
+    /// Files and extensions the sprite refers to.
+    pub external_files: Vec<ExternalFile>,
+    /// Tilesets of the tilemap layers.
+    pub tilesets: Vec<Tileset>,
+}
+
+impl AseFile {
+    /// Reads a whole .ase file.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Parses a whole .ase file held in memory.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes_with_limits(data, &Limits::default())
+    }
+
+    /// Reads a whole .ase file, rejecting it if it exceeds `limits`.
+    pub fn read_with_limits<R: Read>(reader: R, limits: &Limits) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        // Nothing beyond the largest possible file is worth reading.
+        let max_len = (limits.max_frames as u64 + 1).saturating_mul(limits.max_chunk_size);
+        reader.take(max_len).read_to_end(&mut data)?;
+        Self::from_bytes_with_limits(&data, limits)
+    }
+
+    /// Parses a whole .ase file held in memory, rejecting it if it exceeds
+    /// `limits`.
+    pub fn from_bytes_with_limits(data: &[u8], limits: &Limits) -> Result<Self, Error> {
+        Parser::parse(&mut Cursor::new(data), false, limits)?.finish()
+    }
+}
+
+/// Reads exactly `buf.len()` bytes, treating a short file as malformed.
+fn read_exact<S: Read>(source: &mut S, buf: &mut [u8]) -> Result<(), Error> {
+    source.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::format("unexpected end of file"),
+        _ => e.into(),
+    })
+}
+
+/// What the next user data chunk belongs to.
+#[derive(Debug, Clone, Copy)]
+enum UserDataTarget {
+    None,
+    Sprite,
+    Layer(usize),
+    Cel(usize),
+    /// Cel that was skipped when loading lazily.
+    LazyCel(usize),
+    /// Tags get one user data chunk each, starting at this one.
+    Tag(usize),
+    Slice(usize),
+    /// The tileset gets the first user data chunk, then each tile gets one,
+    /// counting from this many chunks in.
+    Tileset(usize, usize),
+}
+
+struct Parser {
+    file: AseFile,
+    old_palette: Palette,
+    has_palette: bool,
+    user_data_target: UserDataTarget,
+    /// Cels that were skipped rather than parsed, for each frame.
+    lazy_cels: Option<Vec<Vec<CelEntry>>>,
+    limits: Limits,
+}
+
+impl Parser {
+    /// Walks the frames and chunks of a file, skipping cel chunks if
+    /// `lazy` is set.
+    fn parse<S: Read + Seek>(source: &mut S, lazy: bool, limits: &Limits) -> Result<Self, Error> {
+        let mut buf = [0; HEADER_SIZE];
+        read_exact(source, &mut buf)?;
+        let header = Header::parse(&mut Reader::new(&buf))?;
+        if header.width as u32 > limits.max_width || header.height as u32 > limits.max_height {
+            return Err(Error::format(format!(
+                "canvas of {}x{} pixels is larger than the limit of {}x{}",
+                header.width, header.height, limits.max_width, limits.max_height
+            )));
+        }
+        if header.frames as u32 > limits.max_frames {
+            return Err(Error::format(format!(
+                "{} frames are more than the limit of {}",
+                header.frames, limits.max_frames
+            )));
+        }
+        let mut parser = Parser {
+            file: AseFile {
+                header,
+                frames: Vec::new(),
+                layers: Vec::new(),
+                palette: Palette::default(),
+                tags: Vec::new(),
+                tag_user_data: Vec::new(),
+                slices: Vec::new(),
+                slice_user_data: Vec::new(),
+                user_data: UserData::default(),
+                external_files: Vec::new(),
+                tilesets: Vec::new(),
+            },
+            old_palette: Palette::default(),
+            has_palette: false,
+            user_data_target: UserDataTarget::None,
+            lazy_cels: if lazy { Some(Vec::new()) } else { None },
+            limits: *limits,
+        };
+
+        for frame in 0..header.frames {
+            let start = source.stream_position()?;
+            let mut buf = [0; FRAME_HEADER_SIZE];
+            read_exact(source, &mut buf)?;
+            let mut r = Reader::new(&buf);
+            let size = r.dword()? as u64;
+            let magic = r.word()?;
+            if magic != FRAME_MAGIC {
+                return Err(Error::format(format!(
+                    "frame {} has magic number {:#06x} instead of {:#06x}",
+                    frame, magic, FRAME_MAGIC
+                )));
+            }
+            let old_chunks = r.word()?;
+            let duration = r.word()?;
+            r.skip(2)?;
+            let chunks = match r.dword()? {
+                0 => old_chunks as u32,
+                n => n,
+            };
+            let mut body = size.checked_sub(FRAME_HEADER_SIZE as u64).ok_or_else(|| {
+                Error::format(format!("frame {} has invalid size {}", frame, size))
+            })?;
+            parser.user_data_target = UserDataTarget::None;
+            parser.file.frames.push(AseFrame {
+                duration,
+                cels: Vec::new(),
+            });
+            if let Some(lazy_cels) = &mut parser.lazy_cels {
+                lazy_cels.push(Vec::new());
+            }
+
+            for _ in 0..chunks {
+                let mut buf = [0; CHUNK_HEADER_SIZE];
+                read_exact(source, &mut buf)?;
+                let mut r = Reader::new(&buf);
+                let size = r.dword()? as u64;
+                let kind = r.word()?;
+                let len = size
+                    .checked_sub(CHUNK_HEADER_SIZE as u64)
+                    .filter(|_| size <= body)
+                    .ok_or_else(|| {
+                        Error::format(format!(
+                            "chunk {:#06x} in frame {} has invalid size {}",
+                            kind, frame, size
+                        ))
+                    })?;
+                body -= size;
+                if len > limits.max_chunk_size {
+                    return Err(Error::format(format!(
+                        "chunk {:#06x} in frame {} of {} bytes is larger than the limit of {}",
+                        kind, frame, len, limits.max_chunk_size
+                    )));
+                }
+
+                let result = if kind == CHUNK_CEL && parser.lazy_cels.is_some() {
+                    let offset = source.stream_position()?;
+                    let mut layer = [0; 2];
+                    read_exact(source, &mut layer)?;
+                    source.seek(SeekFrom::Start(offset + len))?;
+                    parser.lazy_cel(CelEntry {
+                        layer: u16::from_le_bytes(layer),
+                        offset,
+                        len,
+                        user_data: UserData::default(),
+                    });
+                    Ok(())
+                } else {
+                    // Reading through `take` only allocates what the file has.
+                    let mut data = Vec::new();
+                    source.take(len).read_to_end(&mut data)?;
+                    if (data.len() as u64) < len {
+                        return Err(Error::format("unexpected end of file"));
+                    }
+                    parser.chunk(kind, &data)
+                };
+                result.map_err(|e| match e {
+                    Error::Format(message) => Error::Format(format!(
+                        "chunk {:#06x} in frame {}: {}",
+                        kind, frame, message
+                    )),
+                    e => e,
+                })?;
+            }
+            source.seek(SeekFrom::Start(start + size))?;
+        }
+        Ok(parser)
+    }
+
+    /// Checks what needs the whole file and gives the parsed file.
+    fn finish(mut self) -> Result<AseFile, Error> {
+        if !self.has_palette {
+            self.file.palette = self.old_palette;
+        }
+        // Tilesets may come after the cels that use them.
+        for cel in self.file.frames.iter().flat_map(|frame| &frame.cels) {
+            check_tilemap_size(&self.file, cel, self.limits.max_cel_size)?;
+        }
+        Ok(self.file)
+    }
+
+    fn lazy_cel(&mut self, entry: CelEntry) {
+        let frame = self
+            .lazy_cels
+            .as_mut()
+            .and_then(|frames| frames.last_mut())
+            .expect("chunks belong to a frame");
+        self.user_data_target = UserDataTarget::LazyCel(frame.len());
+        frame.push(entry);
+    }
+
+    fn chunk(&mut self, kind: u16, data: &[u8]) -> Result<(), Error> {
+        let mut r = Reader::new(data).with_max_string_len(self.limits.max_string_len);
+        let max_cel_size = self.limits.max_cel_size;
+        let header = self.file.header;
+        let first_frame = self.file.frames.len() == 1;
+        if kind != CHUNK_USER_DATA {
+            self.user_data_target = UserDataTarget::None;
+        }
+        match kind {
+            CHUNK_LAYER => {
+                let has_uuid = header.flags & Header::FLAG_LAYER_UUID != 0;
+                let layer = AseLayer::parse(&mut r, has_uuid)?;
+                self.user_data_target = UserDataTarget::Layer(self.file.layers.len());
+                self.file.layers.push(layer);
+            }
+            CHUNK_CEL => {
+                if let Some(cel) = Cel::parse(&mut r, header.color_mode(), max_cel_size)? {
+                    let frame = self
+                        .file
+                        .frames
+                        .last_mut()
+                        .expect("chunks belong to a frame");
+                    self.user_data_target = UserDataTarget::Cel(frame.cels.len());
+                    frame.cels.push(cel);
+                }
+            }
+            CHUNK_PALETTE => {
+                self.has_palette = true;
+                read_palette(&mut r, &mut self.file.palette)?;
+                if first_frame {
+                    self.user_data_target = UserDataTarget::Sprite;
+                }
+            }
+            CHUNK_OLD_PALETTE_256 => read_old_palette(&mut r, &mut self.old_palette, |c| c)?,
+            CHUNK_OLD_PALETTE_64 => {
+                read_old_palette(&mut r, &mut self.old_palette, |c| (c << 2) | (c >> 4))?
+            }
+            CHUNK_TAGS => {
+                let tags = meta::parse_tags(&mut r)?;
+                self.user_data_target = UserDataTarget::Tag(self.file.tags.len());
+                self.file
+                    .tag_user_data
+                    .resize(self.file.tags.len() + tags.len(), UserData::default());
+                self.file.tags.extend(tags);
+            }
+            CHUNK_SLICE => {
+                let slice = meta::parse_slice(&mut r)?;
+                self.user_data_target = UserDataTarget::Slice(self.file.slices.len());
+                self.file.slices.push(slice);
+                self.file.slice_user_data.push(UserData::default());
+            }
+            CHUNK_TILESET => {
+                let tileset = Tileset::parse(&mut r, header.color_mode(), max_cel_size)?;
+                self.user_data_target = UserDataTarget::Tileset(self.file.tilesets.len(), 0);
+                self.file.tilesets.push(tileset);
+            }
+            CHUNK_EXTERNAL_FILES => {
+                let files = meta::parse_external_files(&mut r)?;
+                self.file.external_files.extend(files);
+            }
+            CHUNK_USER_DATA => self.user_data(UserData::parse(&mut r)?),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn user_data(&mut self, user_data: UserData) {
+        let file = &mut self.file;
+        match self.user_data_target {
+            UserDataTarget::None => {}
+            UserDataTarget::Sprite => file.user_data = user_data,
+            UserDataTarget::Layer(layer) => file.layers[layer].user_data = user_data,
+            UserDataTarget::Cel(cel) => {
+                let frame = file.frames.last_mut().expect("chunks belong to a frame");
+                frame.cels[cel].user_data = user_data;
+            }
+            UserDataTarget::LazyCel(cel) => {
+                if let Some(frame) = self.lazy_cels.as_mut().and_then(|f| f.last_mut()) {
+                    frame[cel].user_data = user_data;
+                }
+            }
+            UserDataTarget::Tag(tag) => {
+                if tag < file.tags.len() {
+                    if user_data.color.is_some() {
+                        file.tags[tag].color = user_data.color;
+                    }
+                    file.tags[tag].data = user_data.text.clone();
+                    file.tag_user_data[tag] = user_data;
+                    self.user_data_target = UserDataTarget::Tag(tag + 1);
+                }
+            }
+            UserDataTarget::Slice(slice) => {
+                if let Some(color) = user_data.color {
+                    file.slices[slice].color = color;
+                }
+                file.slices[slice].data = user_data.text.clone();
+                file.slice_user_data[slice] = user_data;
+            }
+            UserDataTarget::Tileset(tileset, seen) => {
+                let tileset_data = &mut file.tilesets[tileset];
+                match seen {
+                    0 => tileset_data.user_data = user_data,
+                    // Tile data is only kept for tiles the tileset has.
+                    n if n <= tileset_data.tile_count as usize => {
+                        if tileset_data.tile_user_data.len() < n {
+                            tileset_data.tile_user_data.resize(n, UserData::default());
+                        }
+                        tileset_data.tile_user_data[n - 1] = user_data;
+                    }
+                    _ => {}
+                }
+                self.user_data_target = UserDataTarget::Tileset(tileset, seen + 1);
+            }
+        }
+        if !matches!(
+            self.user_data_target,
+            UserDataTarget::Tag(_) | UserDataTarget::Tileset(..)
+        ) {
+            self.user_data_target = UserDataTarget::None;
+        }
+    }
+}
+
+/// Tilemaps are drawn into an image, whose size must stay in bounds.
+fn check_tilemap_size(file: &AseFile, cel: &Cel, max_size: usize) -> Result<(), Error> {
+    let (CelContent::Tilemap(tilemap), Some(layer)) =
+        (&cel.content, file.layers.get(cel.layer as usize))
+    else {
+        return Ok(());
+    };
+    let LayerKind::Tilemap(tileset) = layer.kind else {
+        return Ok(());
+    };
+    let Some(tileset) = file.tilesets.iter().find(|t| t.id == tileset) else {
+        return Ok(());
+    };
+    let size = [
+        tilemap.height as usize,
+        tileset.tile_width as usize,
+        tileset.tile_height as usize,
+        file.header.color_mode().bytes_per_pixel(),
+    ]
+    .into_iter()
+    .fold(tilemap.width as usize, usize::saturating_mul);
+    if size > max_size {
+        return Err(Error::format(format!(
+            "tilemap of {}x{} tiles draws {} bytes, more than the limit of {}",
+            tilemap.width, tilemap.height, size, max_size
+        )));
+    }
+    Ok(())
+}
+
+/// Aseprite palettes have at most this many entries.
+const MAX_PALETTE_SIZE: usize = 1 << 16;
+
+fn read_palette(r: &mut Reader, palette: &mut Palette) -> Result<(), Error> {
+    let size = r.dword()? as usize;
+    let first = r.dword()? as usize;
+    let last = r.dword()? as usize;
+    r.skip(8)?;
+    if first > last || last >= size || size > MAX_PALETTE_SIZE {
+        return Err(Error::format(format!(
+            "palette changes entries {} to {} of {}",
+            first, last, size
+        )));
+    }
+
+    palette.resize(size);
+    for index in first..=last {
+        let flags = r.word()?;
+        let color = Color {
+            r: r.byte()?,
+            g: r.byte()?,
+            b: r.byte()?,
+            a: r.byte()?,
+        };
+        let name = if flags & 1 != 0 {
+            Some(r.string()?)
+        } else {
+            None
+        };
+        palette.set(index, PaletteEntry { color, name });
+    }
+    Ok(())
+}
+
+fn read_old_palette(
+    r: &mut Reader,
+    palette: &mut Palette,
+    scale: impl Fn(u8) -> u8,
+) -> Result<(), Error> {
+    let packets = r.word()?;
+    let mut index = 0;
+    for _ in 0..packets {
+        index += r.byte()? as usize;
+        let colors = match r.byte()? {
+            0 => 256,
+            n => n as usize,
+        };
+        for _ in 0..colors {
+            let color = Color {
+                r: scale(r.byte()?),
+                g: scale(r.byte()?),
+                b: scale(r.byte()?),
+                a: 255,
+            };
+            if index >= 256 {
+                return Err(Error::format("old palette has more than 256 colors"));
+            }
+            palette.set(index, PaletteEntry::new(color));
+            index += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aseprite_test_data() {
+        for file in aseprite_test_data::FileSet::list() {
+            let ase = AseFile::from_bytes(file.src_aseprite)
+                .unwrap_or_else(|e| panic!("{}: {}", file.name, e));
+            assert_eq!(file.n_frames, ase.frames.len(), "{}", file.name);
+            assert_eq!(
+                file.size,
+                [ase.header.width as u32, ase.header.height as u32]
+            );
+            assert_eq!(
+                file.pixel,
+                [ase.header.pixel_width, ase.header.pixel_height],
+                "{}",
+                file.name
+            );
+
+            let palette: Vec<u32> = ase
+                .palette
+                .colors()
+                .map(|c| u32::from_be_bytes([c.r, c.g, c.b, c.a]))
+                .collect();
+            let mut expected: Vec<u32> = match file.ase_color_format {
+                // Grayscale sprites keep a ramp of 256 grays.
+                aseprite_test_data::AseColorFormat::Greyscale => (0..=255)
+                    .map(|v| u32::from_be_bytes([v, v, v, 255]))
+                    .collect(),
+                _ => file.palette.iter().map(|c| c.0).collect(),
+            };
+            // The test data lists the first color as transparent, while the
+            // files store it as opaque black.
+            expected[0] |= 0xff;
+            assert_eq!(expected, palette, "{}", file.name);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        for file in aseprite_test_data::FileSet::list() {
+            let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+            let mode = match file.ase_color_format {
+                aseprite_test_data::AseColorFormat::Greyscale => ColorMode::Grayscale,
+                aseprite_test_data::AseColorFormat::Indexed => ColorMode::Indexed,
+                _ => ColorMode::Rgba,
+            };
+            // The complex file is indexed, though its test data doesn't say so.
+            let Some(pixels) = file.pixels else {
+                continue;
+            };
+            assert_eq!(mode, ase.header.color_mode(), "{}", file.name);
+
+            let image = ase.render_frame(0);
+            let rendered: Vec<u32> = image
+                .data
+                .chunks_exact(4)
+                .map(|p| u32::from_be_bytes([p[0], p[1], p[2], p[3]]))
+                .collect();
+            let expected: Vec<u32> = pixels.iter().map(|p| p.0).collect();
+            assert_eq!(expected, rendered, "{}", file.name);
+
+            let indices = ase.render_frame_indices(0);
+            assert_eq!(mode == ColorMode::Indexed, indices.is_some());
+            if let Some(indices) = indices {
+                let colors: Vec<u32> = indices
+                    .data
+                    .iter()
+                    .map(|&i| match i == ase.header.transparent_index {
+                        true => 0,
+                        false => file.palette[i as usize].0,
+                    })
+                    .collect();
+                assert_eq!(expected, colors, "{}", file.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_layers() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        // The export leaves out the invisible layer.
+        assert_eq!(file.n_layers + 1, ase.layers.len());
+        let visible = ase.visible_layers();
+        assert_eq!(file.n_layers, visible.iter().filter(|&&v| v).count());
+        assert!(!visible[22]);
+        assert_eq!(ColorMode::Indexed, ase.header.color_mode());
+
+        let group = &ase.layers[0];
+        assert_eq!(
+            ("Mode Layers", LayerKind::Group),
+            (&*group.name, group.kind)
+        );
+        let darken = &ase.layers[2];
+        assert_eq!("Layer Darken", darken.name);
+        assert_eq!(1, darken.child_level);
+        assert_eq!(crate::BlendMode::Darken, darken.blend_mode);
+        assert_eq!(127, ase.layers[20].opacity);
+
+        let durations: Vec<u16> = ase.frames.iter().map(|f| f.duration).collect();
+        assert_eq!(vec![100, 200, 300, 400, 500, 600, 700, 800, 900], durations);
+        for frame in 0..ase.frames.len() {
+            let image = ase.render_frame(frame);
+            assert_eq!((8, 8), (image.width, image.height));
+        }
+
+        // A cel on a layer that doesn't exist draws nothing.
+        let mut ase = ase;
+        let missing = ase.layers.len() as u16 + 3;
+        ase.frames[0].cels[0].layer = missing;
+        let shown = vec![true; missing as usize + 1];
+        assert_eq!(
+            ase.render_layers(0, &shown[..ase.layers.len()]),
+            ase.render_layers(0, &shown)
+        );
+    }
+
+    #[test]
+    fn test_tags_and_slices() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        let sheet: crate::SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+
+        let meta_tags = &sheet.meta.frame_tags;
+        let tags: Vec<_> = ase
+            .tags
+            .iter()
+            .map(|t| (&*t.name, t.from, t.to, t.direction))
+            .collect();
+        let expected: Vec<_> = meta_tags
+            .iter()
+            .map(|t| (&*t.name, t.from, t.to, t.direction))
+            .collect();
+        assert_eq!(expected, tags);
+        assert_eq!(ase.tags.len(), ase.tag_user_data.len());
+        let red = ase.tags.iter().find(|t| t.name == "red").unwrap();
+        assert_eq!(
+            Some(Color {
+                r: 0xfe,
+                g: 0x5b,
+                b: 0x59,
+                a: 0xff
+            }),
+            red.color
+        );
+
+        assert_eq!(sheet.meta.slices, ase.slices);
+        let top_left = ase
+            .slices
+            .iter()
+            .position(|s| s.name == "Top Left")
+            .unwrap();
+        assert_eq!(
+            Some("Top Left User Data"),
+            ase.slices[top_left].data.as_deref()
+        );
+        assert_eq!(
+            ase.slice_user_data[top_left].text,
+            ase.slices[top_left].data
+        );
+
+        let layer = ase
+            .layers
+            .iter()
+            .find(|l| l.name == "Layer User Data")
+            .unwrap();
+        assert_eq!(Some("Orange Layer"), layer.user_data.text.as_deref());
+        assert_eq!(
+            Some(Color {
+                r: 0xf7,
+                g: 0xa5,
+                b: 0x47,
+                a: 0xff
+            }),
+            layer.user_data.color
+        );
+    }
+
+    #[test]
+    fn test_properties() {
+        let mut data = Vec::new();
+        data.extend(4u32.to_le_bytes());
+        // Size, then one map of two properties owned by the user.
+        data.extend(0u32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend(2u32.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(b"hp");
+        data.extend(0x0006u16.to_le_bytes());
+        data.extend(30i32.to_le_bytes());
+        data.extend(4u16.to_le_bytes());
+        data.extend(b"path");
+        data.extend(0x0011u16.to_le_bytes());
+        data.extend(2u32.to_le_bytes());
+        data.extend(0x000Eu16.to_le_bytes());
+        data.extend([1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0]);
+
+        let user_data = UserData::parse(&mut Reader::new(&data)).unwrap();
+        let properties = user_data.user_properties().unwrap();
+        assert_eq!(Some(&PropertyValue::I32(30)), properties.get("hp"));
+        assert_eq!(
+            Some(&PropertyValue::Vector(vec![
+                PropertyValue::Point { x: 1, y: 2 },
+                PropertyValue::Point { x: 3, y: 4 },
+            ])),
+            properties.get("path")
+        );
+
+        // A map that contains itself over and over must not overflow the stack.
+        let mut nested = data[..16].to_vec();
+        for _ in 0..100 {
+            nested.extend(1u32.to_le_bytes());
+            nested.extend(1u16.to_le_bytes());
+            nested.push(b'm');
+            nested.extend(0x0012u16.to_le_bytes());
+        }
+        assert!(UserData::parse(&mut Reader::new(&nested)).is_err());
+
+        // A second map with a type this parser doesn't know is skipped
+        // using the size of the properties.
+        let mut unknown = data.clone();
+        unknown.extend(7u32.to_le_bytes());
+        unknown.extend(1u32.to_le_bytes());
+        unknown.extend(3u16.to_le_bytes());
+        unknown.extend(b"new");
+        unknown.extend(0x00ffu16.to_le_bytes());
+        unknown.extend([1, 2, 3]);
+        let size = (unknown.len() - 4) as u32;
+        unknown[4..8].copy_from_slice(&size.to_le_bytes());
+        unknown[8..12].copy_from_slice(&2u32.to_le_bytes());
+        unknown.extend(b"next chunk");
+        let mut r = Reader::new(&unknown);
+        let parsed = UserData::parse(&mut r).unwrap();
+        assert_eq!(user_data, parsed);
+        assert_eq!(b"next chunk", r.bytes(r.remaining()).unwrap());
+        unknown.truncate(unknown.len() - 12);
+        assert!(UserData::parse(&mut Reader::new(&unknown)).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let data = file.src_aseprite;
+        let mut limits = Limits::default();
+        assert!(AseFile::from_bytes_with_limits(data, &limits).is_ok());
+        limits.max_width = 4;
+        assert!(AseFile::from_bytes_with_limits(data, &limits).is_err());
+        let limits = Limits {
+            max_string_len: 4,
+            ..Limits::default()
+        };
+        assert!(AseFile::read_with_limits(data, &limits).is_err());
+        let limits = Limits {
+            max_cel_size: 16,
+            ..Limits::default()
+        };
+        assert!(AseFile::from_bytes_with_limits(data, &limits).is_err());
+        assert!(LazyAseFile::with_limits(Cursor::new(data), &limits).is_ok());
+
+        // A cel claiming to be huge, which would inflate a zlib bomb.
+        let mut cel = vec![0; 7];
+        cel.extend(2u16.to_le_bytes());
+        cel.extend([0; 7]);
+        cel.extend([0xff; 4]);
+        cel.extend(miniz_oxide::deflate::compress_to_vec_zlib(
+            &[0; 1 << 16],
+            10,
+        ));
+        let error = Cel::parse(&mut Reader::new(&cel), ColorMode::Rgba, 1 << 20).unwrap_err();
+        assert!(error.to_string().contains("limit"), "{}", error);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let data = file.src_aseprite;
+        for len in 0..data.len() {
+            assert!(AseFile::from_bytes(&data[..len]).is_err());
+        }
+        // Damaged bytes may still parse, but must not panic.
+        for i in HEADER_SIZE..data.len() {
+            let mut data = data.to_vec();
+            data[i] ^= 0xff;
+            if let Ok(ase) = AseFile::from_bytes(&data) {
+                for frame in 0..ase.frames.len() {
+                    ase.render_frame(frame);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_old_palette() {
+        let mut palette = Palette::default();
+        // Two packets: colors 0 and 1, then skip to color 5.
+        let data = [2, 0, 0, 2, 63, 0, 0, 0, 63, 0, 3, 1, 0, 0, 63];
+        read_old_palette(&mut Reader::new(&data), &mut palette, |c| {
+            (c << 2) | (c >> 4)
+        })
+        .unwrap();
+        assert_eq!(6, palette.len());
+        assert_eq!(Some(255), palette.get(0).map(|c| c.r));
+        assert_eq!(Some(255), palette.get(1).map(|c| c.g));
+        assert_eq!(Some(255), palette.get(5).map(|c| c.b));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let data = &file.src_aseprite[..file.src_aseprite.len() - 10];
+        assert!(matches!(AseFile::from_bytes(data), Err(Error::Format(_))));
+        assert!(AseFile::from_bytes(b"not an aseprite file").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Little-endian primitives of the .ase format.
+
+use crate::Error;
+
+/// Cursor over the bytes of a file or chunk.
+#[derive(Debug, Clone)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    max_string_len: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            max_string_len: u16::MAX as usize,
+        }
+    }
+
+    /// Makes longer strings an error.
+    pub(crate) fn with_max_string_len(self, max_string_len: usize) -> Self {
+        Self {
+            max_string_len,
+            ..self
+        }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.remaining() {
+            return Err(Error::format(format!(
+                "unexpected end of data at offset {}, wanted {} more bytes but only {} are left",
+                self.pos,
+                len,
+                self.remaining()
+            )));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub(crate) fn word(&mut self) -> Result<u16, Error> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn short(&mut self) -> Result<i16, Error> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    pub(crate) fn dword(&mut self) -> Result<u32, Error> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn long(&mut self) -> Result<i32, Error> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    pub(crate) fn qword(&mut self) -> Result<u64, Error> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, Error> {
+        let len = self.word()? as usize;
+        if len > self.max_string_len {
+            return Err(Error::format(format!(
+                "string at offset {} is {} bytes long, more than the limit of {}",
+                self.pos, len, self.max_string_len
+            )));
+        }
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::format(format!("string at offset {} is not utf-8", self.pos)))
+    }
+}
+
+/// Decompresses zlib data that should hold exactly `size` bytes.
+///
+/// `size` comes from the file, so it's checked against `max_size` before
+/// anything is allocated, and decompression stops once it's reached.
+pub(crate) fn inflate(
+    compressed: &[u8],
+    size: usize,
+    max_size: usize,
+    what: &str,
+) -> Result<Vec<u8>, Error> {
+    if size > max_size {
+        return Err(Error::format(format!(
+            "{} of {} bytes is larger than the limit of {}",
+            what, size, max_size
+        )));
+    }
+    let mut data = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, size)
+        .map_err(|e| Error::format(format!("{} doesn't decompress: {:?}", what, e.status)))?;
+    if data.len() < size {
+        return Err(Error::format(format!(
+            "{} has only {} of {} bytes",
+            what,
+            data.len(),
+            size
+        )));
+    }
+    data.truncate(size);
+    Ok(data)
+}
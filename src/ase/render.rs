@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+
+use super::{AseFile, Cel, CelContent, CelImage, ColorMode, Header, LayerKind, Tilemap, Tileset};
+use crate::image::{IndexedImage, RgbaImage};
+use crate::Color;
+
+const TRANSPARENT: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0,
+};
+
+impl AseFile {
+    /// Cel of a layer in a frame.
+    pub fn cel(&self, frame: usize, layer: usize) -> Option<&Cel> {
+        self.frames
+            .get(frame)?
+            .cels
+            .iter()
+            .find(|cel| cel.layer as usize == layer)
+    }
+
+    /// Contents of the cel of a layer in a frame, following linked cels.
+    fn cel_content(&self, frame: usize, layer: usize) -> Option<&CelContent> {
+        let mut frame = frame;
+        // A link can't point at another link, but don't trust the file on that.
+        for _ in 0..=self.frames.len() {
+            match &self.cel(frame, layer)?.content {
+                CelContent::Linked(linked) => frame = *linked as usize,
+                content => return Some(content),
+            }
+        }
+        None
+    }
+
+    /// Pixels of the cel of a layer in a frame, following linked cels.
+    ///
+    /// The pixels are in the color mode of the sprite, so in an indexed
+    /// sprite they are palette indices. `None` for tilemap cels, see
+    /// [`AseFile::tilemap_image`].
+    pub fn cel_image(&self, frame: usize, layer: usize) -> Option<&CelImage> {
+        match self.cel_content(frame, layer)? {
+            CelContent::Image(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Tiles of the cel of a tilemap layer in a frame, following linked cels.
+    pub fn tilemap(&self, frame: usize, layer: usize) -> Option<&Tilemap> {
+        match self.cel_content(frame, layer)? {
+            CelContent::Tilemap(tilemap) => Some(tilemap),
+            _ => None,
+        }
+    }
+
+    /// Tileset with the given id.
+    pub fn tileset(&self, id: u32) -> Option<&Tileset> {
+        self.tilesets.iter().find(|tileset| tileset.id == id)
+    }
+
+    /// Pixels of the cel of a tilemap layer in a frame, in the color mode
+    /// of the sprite.
+    ///
+    /// Tiles missing from the tileset are left transparent. `None` if the
+    /// cel isn't a tilemap or its tileset is missing.
+    pub fn tilemap_image(&self, frame: usize, layer: usize) -> Option<CelImage> {
+        let LayerKind::Tilemap(tileset) = self.layers.get(layer)?.kind else {
+            return None;
+        };
+        let tileset = self.tileset(tileset)?;
+        let empty: &[u8] = match self.header.color_mode() {
+            ColorMode::Rgba => &[0; 4],
+            ColorMode::Grayscale => &[0; 2],
+            ColorMode::Indexed => &[self.header.transparent_index],
+        };
+        self.tilemap(frame, layer)?.render(tileset, empty)
+    }
+
+    /// Pixels of an image or tilemap cel.
+    fn cel_pixels(&self, frame: usize, layer: usize) -> Option<Cow<'_, CelImage>> {
+        match self.cel_image(frame, layer) {
+            Some(image) => Some(Cow::Borrowed(image)),
+            None => self.tilemap_image(frame, layer).map(Cow::Owned),
+        }
+    }
+
+    /// Pixels of the cel of a layer in a frame, as RGBA.
+    ///
+    /// Indexed pixels are looked up in the palette, with the transparent
+    /// index becoming transparent everywhere but on the background layer.
+    /// Tilemap cels are drawn with their tileset.
+    pub fn cel_rgba(&self, frame: usize, layer: usize) -> Option<RgbaImage> {
+        let image = self.cel_pixels(frame, layer)?;
+        let background = self.layers.get(layer).is_some_and(|l| l.is_background());
+        Some(self.to_rgba(&image, background))
+    }
+
+    pub(crate) fn to_rgba(&self, image: &CelImage, background: bool) -> RgbaImage {
+        let data = match self.header.color_mode() {
+            ColorMode::Rgba => image.data.clone(),
+            ColorMode::Grayscale => image
+                .data
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            ColorMode::Indexed => image
+                .data
+                .iter()
+                .flat_map(|&index| {
+                    let color = if index == self.header.transparent_index && !background {
+                        TRANSPARENT
+                    } else {
+                        self.palette.get(index as usize).unwrap_or(TRANSPARENT)
+                    };
+                    [color.r, color.g, color.b, color.a]
+                })
+                .collect(),
+        };
+        RgbaImage {
+            width: image.width as u32,
+            height: image.height as u32,
+            data,
+        }
+    }
+
+    /// Whether each layer is shown, taking the groups it's in into account.
+    ///
+    /// Reference layers are never shown.
+    pub fn visible_layers(&self) -> Vec<bool> {
+        let mut groups: Vec<bool> = Vec::new();
+        self.layers
+            .iter()
+            .map(|layer| {
+                let level = layer.child_level as usize;
+                let parent = level == 0 || groups.get(level - 1).copied().unwrap_or(true);
+                let visible = parent && layer.is_visible() && !layer.is_reference();
+                groups.truncate(level);
+                groups.push(visible);
+                visible
+            })
+            .collect()
+    }
+
+    /// Cels of the `shown` image and tilemap layers of a frame, in drawing order.
+    fn drawn_cels(&self, frame: usize, shown: &[bool]) -> Vec<&Cel> {
+        let mut cels: Vec<&Cel> = self
+            .frames
+            .get(frame)
+            .map(|frame| frame.cels.iter().collect())
+            .unwrap_or_default();
+        cels.retain(|cel| {
+            let layer = cel.layer as usize;
+            shown.get(layer).copied().unwrap_or(false)
+                && self
+                    .layers
+                    .get(layer)
+                    .is_some_and(|l| l.kind != LayerKind::Group)
+        });
+        cels.sort_by_key(|cel| (cel.layer as i32 + cel.z_index as i32, cel.z_index));
+        cels
+    }
+
+    /// Draws the visible layers of a frame onto a transparent canvas.
+    ///
+    /// Every layer is blended with the normal blend mode, using the layer
+    /// and cel opacity.
+    pub fn render_frame(&self, frame: usize) -> RgbaImage {
+        self.render_layers(frame, &self.visible_layers())
+    }
+
+    /// Draws the layers of a frame that are `shown`, by layer index, like
+    /// [`AseFile::render_frame`] does the visible ones.
+    ///
+    /// Group layers draw nothing themselves, so show their children.
+    pub fn render_layers(&self, frame: usize, shown: &[bool]) -> RgbaImage {
+        let mut canvas = RgbaImage::new(self.header.width as u32, self.header.height as u32);
+        let layer_opacity = self.header.flags & Header::FLAG_LAYER_OPACITY != 0;
+        for cel in self.drawn_cels(frame, shown) {
+            let Some(image) = self.cel_rgba(frame, cel.layer as usize) else {
+                continue;
+            };
+            let layer = &self.layers[cel.layer as usize];
+            let opacity = if layer_opacity {
+                (layer.opacity as u32 * cel.opacity as u32 / 255) as u8
+            } else {
+                cel.opacity
+            };
+            canvas.draw(&image, cel.x as i32, cel.y as i32, opacity);
+        }
+        canvas
+    }
+
+    /// Palette indices of the visible layers of a frame, for indexed sprites.
+    ///
+    /// Each pixel takes the index of the topmost cel that isn't
+    /// transparent there; opacity is ignored. Pixels no cel covers get
+    /// the transparent index. `None` if the sprite isn't indexed.
+    pub fn render_frame_indices(&self, frame: usize) -> Option<IndexedImage> {
+        if self.header.color_mode() != ColorMode::Indexed {
+            return None;
+        }
+        let (width, height) = (self.header.width as i32, self.header.height as i32);
+        let transparent = self.header.transparent_index;
+        let mut canvas = IndexedImage::new(width as u32, height as u32, transparent);
+        for cel in self.drawn_cels(frame, &self.visible_layers()) {
+            let Some(image) = self.cel_pixels(frame, cel.layer as usize) else {
+                continue;
+            };
+            let background = self.layers[cel.layer as usize].is_background();
+            for (i, &index) in image.data.iter().enumerate() {
+                let x = cel.x as i32 + (i % image.width as usize) as i32;
+                let y = cel.y as i32 + (i / image.width as usize) as i32;
+                if (index != transparent || background)
+                    && (0..width).contains(&x)
+                    && (0..height).contains(&y)
+                {
+                    canvas.data[(y * width + x) as usize] = index;
+                }
+            }
+        }
+        Some(canvas)
+    }
+}
@@ -0,0 +1,441 @@
+use super::reader::{inflate, Reader};
+use super::writer::{count, Writer};
+use super::{CelImage, ColorMode, UserData};
+use crate::Error;
+
+/// Where a tileset that lives in another file is found.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub struct TilesetLink {
+    /// Id of the entry in [`AseFile::external_files`](super::AseFile::external_files).
+    pub file_id: u32,
+    /// Id of the tileset in that file.
+    pub tileset_id: u32,
+}
+
+/// Set of equally sized tiles that tilemap layers refer to.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct Tileset {
+    /// Id tilemap layers refer to the tileset by.
+    pub id: u32,
+    /// Tileset flags, see the `FLAG_*` constants.
+    pub flags: u32,
+    /// Tileset name.
+    pub name: String,
+    /// Width of a tile in pixels.
+    pub tile_width: u16,
+    /// Height of a tile in pixels.
+    pub tile_height: u16,
+    /// Number the UI shows for the first tile; only for display.
+    pub base_index: i16,
+    /// Number of tiles, which may only be in the linked file.
+    pub tile_count: u32,
+    /// Tile images, in the color mode of the sprite.
+    ///
+    /// Empty if the tiles are only in the linked file.
+    pub tiles: Vec<CelImage>,
+    /// File the tileset comes from, if it's shared between files.
+    pub link: Option<TilesetLink>,
+    /// Custom data of the tileset.
+    pub user_data: UserData,
+    /// Custom data of the tiles, by tile id; tiles past its end have none.
+    pub tile_user_data: Vec<UserData>,
+}
+
+impl Tileset {
+    /// The tileset is linked to an external file.
+    pub const FLAG_EXTERNAL_FILE: u32 = 1;
+    /// The tiles are stored in this file.
+    pub const FLAG_TILES: u32 = 2;
+    /// Tile id 0 is the empty tile.
+    pub const FLAG_EMPTY_TILE_0: u32 = 4;
+
+    /// Image of a tile, `None` if the id is out of range.
+    pub fn tile(&self, id: u32) -> Option<&CelImage> {
+        self.tiles.get(id as usize)
+    }
+
+    /// Reads a tileset chunk whose tiles take up to `max_size` bytes.
+    pub(crate) fn parse(r: &mut Reader, mode: ColorMode, max_size: usize) -> Result<Self, Error> {
+        let id = r.dword()?;
+        let flags = r.dword()?;
+        let tile_count = r.dword()?;
+        let count = tile_count as usize;
+        let tile_width = r.word()?;
+        let tile_height = r.word()?;
+        let base_index = r.short()?;
+        r.skip(14)?;
+        let name = r.string()?;
+
+        let link = if flags & Self::FLAG_EXTERNAL_FILE != 0 {
+            Some(TilesetLink {
+                file_id: r.dword()?,
+                tileset_id: r.dword()?,
+            })
+        } else {
+            None
+        };
+
+        let mut tiles = Vec::new();
+        if flags & Self::FLAG_TILES != 0 {
+            let len = r.dword()? as usize;
+            let tile_size = tile_width as usize * tile_height as usize * mode.bytes_per_pixel();
+            let size = tile_size.saturating_mul(count);
+            let what = format!("tileset of {} tiles", count);
+            let data = inflate(r.bytes(len)?, size, max_size, &what)?;
+            if tile_size > 0 {
+                tiles = data
+                    .chunks_exact(tile_size)
+                    .map(|tile| CelImage {
+                        width: tile_width,
+                        height: tile_height,
+                        data: tile.to_vec(),
+                    })
+                    .collect();
+            }
+        }
+
+        Ok(Self {
+            id,
+            flags,
+            name,
+            tile_width,
+            tile_height,
+            base_index,
+            tile_count,
+            tiles,
+            link,
+            user_data: UserData::default(),
+            tile_user_data: Vec::new(),
+        })
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer) -> Result<(), Error> {
+        let mut flags = self.flags & !(Self::FLAG_EXTERNAL_FILE | Self::FLAG_TILES);
+        if self.link.is_some() {
+            flags |= Self::FLAG_EXTERNAL_FILE;
+        }
+        if !self.tiles.is_empty() {
+            flags |= Self::FLAG_TILES;
+        }
+        w.dword(self.id);
+        w.dword(flags);
+        // The tiles in the file decide the count, when there are any.
+        w.dword(match self.tiles.len() {
+            0 => self.tile_count,
+            n => count(n, "tiles")?,
+        });
+        w.word(self.tile_width);
+        w.word(self.tile_height);
+        w.short(self.base_index);
+        w.zeros(14);
+        w.string(&self.name)?;
+        if let Some(link) = self.link {
+            w.dword(link.file_id);
+            w.dword(link.tileset_id);
+        }
+        if !self.tiles.is_empty() {
+            let pixels: Vec<u8> = self
+                .tiles
+                .iter()
+                .flat_map(|t| t.data.iter().copied())
+                .collect();
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&pixels, 6);
+            w.dword(count(compressed.len(), "bytes of tiles")?);
+            w.bytes(&compressed);
+        }
+        Ok(())
+    }
+}
+
+/// Reference to a tile in a tilemap cel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct Tile {
+    /// Index of the tile in the tileset.
+    pub id: u32,
+    /// The tile is mirrored horizontally.
+    pub flip_x: bool,
+    /// The tile is mirrored vertically.
+    pub flip_y: bool,
+    /// The tile is mirrored along its main diagonal, before the other flips.
+    pub flip_diagonal: bool,
+}
+
+/// Bits Aseprite uses for 32-bit tiles.
+const TILE_ID: u32 = 0x1fff_ffff;
+const TILE_FLIP_X: u32 = 0x2000_0000;
+const TILE_FLIP_Y: u32 = 0x4000_0000;
+const TILE_FLIP_DIAGONAL: u32 = 0x8000_0000;
+
+/// Grid of tiles of a tilemap cel.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Tilemap {
+    /// Width in tiles.
+    pub width: u16,
+    /// Height in tiles.
+    pub height: u16,
+    /// Tiles, row by row.
+    pub tiles: Vec<Tile>,
+}
+
+impl Tilemap {
+    /// Tile at column `x` and row `y`.
+    pub fn tile(&self, x: u16, y: u16) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// Reads the compressed tilemap of a cel chunk, which takes up to
+    /// `max_size` bytes.
+    pub(crate) fn parse(r: &mut Reader, max_size: usize) -> Result<Self, Error> {
+        let width = r.word()?;
+        let height = r.word()?;
+        let bits = r.word()?;
+        let id_mask = r.dword()?;
+        let x_mask = r.dword()?;
+        let y_mask = r.dword()?;
+        let diagonal_mask = r.dword()?;
+        r.skip(10)?;
+
+        let bytes = match bits {
+            8 | 16 | 32 => bits as usize / 8,
+            n => return Err(Error::format(format!("unsupported {} bits per tile", n))),
+        };
+        let count = width as usize * height as usize;
+        let what = format!("tilemap of {}x{} tiles", width, height);
+        let data = inflate(r.bytes(r.remaining())?, count * bytes, max_size, &what)?;
+
+        let tiles = data
+            .chunks_exact(bytes)
+            .map(|b| {
+                let mut value = [0; 4];
+                value[..bytes].copy_from_slice(b);
+                let value = u32::from_le_bytes(value);
+                Tile {
+                    id: value & id_mask,
+                    flip_x: value & x_mask != 0,
+                    flip_y: value & y_mask != 0,
+                    flip_diagonal: value & diagonal_mask != 0,
+                }
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer) {
+        w.word(self.width);
+        w.word(self.height);
+        w.word(32);
+        for mask in [TILE_ID, TILE_FLIP_X, TILE_FLIP_Y, TILE_FLIP_DIAGONAL] {
+            w.dword(mask);
+        }
+        w.zeros(10);
+        let data: Vec<u8> = self
+            .tiles
+            .iter()
+            .flat_map(|tile| {
+                let mut value = tile.id & TILE_ID;
+                for (flip, mask) in [
+                    (tile.flip_x, TILE_FLIP_X),
+                    (tile.flip_y, TILE_FLIP_Y),
+                    (tile.flip_diagonal, TILE_FLIP_DIAGONAL),
+                ] {
+                    if flip {
+                        value |= mask;
+                    }
+                }
+                value.to_le_bytes()
+            })
+            .collect();
+        w.bytes(&miniz_oxide::deflate::compress_to_vec_zlib(&data, 6));
+    }
+
+    /// Draws the tiles into an image of the tileset's color mode.
+    ///
+    /// Tiles the tileset doesn't have are filled with `empty`, which is
+    /// one pixel's worth of bytes. `None` if the image would be too large
+    /// for a cel.
+    pub(crate) fn render(&self, tileset: &Tileset, empty: &[u8]) -> Option<CelImage> {
+        let bpp = empty.len();
+        let (tw, th) = (tileset.tile_width as usize, tileset.tile_height as usize);
+        let width = self.width as usize * tw;
+        let height = self.height as usize * th;
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return None;
+        }
+        let mut data = empty.repeat(width * height);
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let Some(image) = tileset.tile(tile.id) else {
+                continue;
+            };
+            let (left, top) = (
+                (i % self.width as usize) * tw,
+                (i / self.width as usize) * th,
+            );
+            for y in 0..th {
+                for x in 0..tw {
+                    // The tile is transposed and then flipped, so undo the
+                    // flips first to find the source pixel.
+                    let (mut sx, mut sy) = (x, y);
+                    if tile.flip_x {
+                        sx = tw - 1 - sx;
+                    }
+                    if tile.flip_y {
+                        sy = th - 1 - sy;
+                    }
+                    if tile.flip_diagonal {
+                        // Only square tiles can be transposed in place.
+                        if tw != th {
+                            continue;
+                        }
+                        (sx, sy) = (sy, sx);
+                    }
+                    let src = (sy * tw + sx) * bpp;
+                    let dst = ((top + y) * width + left + x) * bpp;
+                    data[dst..dst + bpp].copy_from_slice(&image.data[src..src + bpp]);
+                }
+            }
+        }
+        Some(CelImage {
+            width: width as u16,
+            height: height as u16,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tileset() -> Tileset {
+        // Two 2x2 grayscale tiles: an empty one and one with a single white pixel.
+        let tile = |data: [u8; 8]| CelImage {
+            width: 2,
+            height: 2,
+            data: data.to_vec(),
+        };
+        Tileset {
+            id: 0,
+            flags: Tileset::FLAG_TILES | Tileset::FLAG_EMPTY_TILE_0,
+            name: String::new(),
+            tile_width: 2,
+            tile_height: 2,
+            base_index: 1,
+            tile_count: 2,
+            tiles: vec![tile([0; 8]), tile([255, 255, 0, 0, 0, 0, 0, 0])],
+            link: None,
+            user_data: UserData::default(),
+            tile_user_data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tilemap() {
+        let values: [u32; 4] = [1, 1 | 0x8000_0000, 1 | 0x4000_0000, 0];
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut data = Vec::new();
+        for word in [2u16, 2, 32] {
+            data.extend(word.to_le_bytes());
+        }
+        for mask in [0x1fff_ffffu32, 0x2000_0000, 0x4000_0000, 0x8000_0000] {
+            data.extend(mask.to_le_bytes());
+        }
+        data.extend([0; 10]);
+        data.extend(miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+
+        let tilemap = Tilemap::parse(&mut Reader::new(&data), usize::MAX).unwrap();
+        assert_eq!((2, 2), (tilemap.width, tilemap.height));
+        let diagonal = tilemap.tile(1, 0).unwrap();
+        assert_eq!(
+            (1, true, false),
+            (diagonal.id, diagonal.flip_diagonal, diagonal.flip_x)
+        );
+        assert!(tilemap.tile(0, 1).unwrap().flip_y);
+        assert_eq!(None, tilemap.tile(2, 0));
+
+        // White pixels: top left, transposed stays top left, flipped moves down.
+        let image = tilemap.render(&tileset(), &[0, 0]).unwrap();
+        assert_eq!((4, 4), (image.width, image.height));
+        let white: Vec<usize> = image
+            .data
+            .chunks_exact(2)
+            .enumerate()
+            .filter(|(_, p)| p[1] == 255)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(vec![0, 2, 12], white);
+    }
+
+    #[test]
+    fn test_render_rotations() {
+        // The white pixel is at the top right, so the flips rotate it.
+        let mut tileset = tileset();
+        tileset.tiles[1].data = vec![0, 0, 255, 255, 0, 0, 0, 0];
+        let rotated = |flip_x, flip_y| Tile {
+            id: 1,
+            flip_x,
+            flip_y,
+            flip_diagonal: true,
+        };
+        let tilemap = Tilemap {
+            width: 2,
+            height: 1,
+            tiles: vec![rotated(true, false), rotated(false, true)],
+        };
+        let image = tilemap.render(&tileset, &[0, 0]).unwrap();
+        let white: Vec<usize> = image
+            .data
+            .chunks_exact(2)
+            .enumerate()
+            .filter(|(_, p)| p[1] == 255)
+            .map(|(i, _)| i)
+            .collect();
+        // Clockwise to the bottom right, counterclockwise to the top left.
+        assert_eq!(vec![2, 5], white);
+    }
+
+    #[test]
+    fn test_parse_tileset() {
+        let pixels = [7u8; 2 * 2 * 3];
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&pixels, 6);
+        let mut data = Vec::new();
+        data.extend(5u32.to_le_bytes());
+        data.extend((Tileset::FLAG_TILES | Tileset::FLAG_EXTERNAL_FILE).to_le_bytes());
+        data.extend(3u32.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(1i16.to_le_bytes());
+        data.extend([0; 14]);
+        data.extend(5u16.to_le_bytes());
+        data.extend(b"tiles");
+        data.extend(9u32.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(&compressed);
+
+        let tileset =
+            Tileset::parse(&mut Reader::new(&data), ColorMode::Indexed, usize::MAX).unwrap();
+        assert_eq!((5, "tiles"), (tileset.id, &*tileset.name));
+        assert_eq!(3, tileset.tiles.len());
+        assert_eq!(Some(&vec![7; 4]), tileset.tile(2).map(|t| &t.data));
+        assert_eq!(
+            Some(TilesetLink {
+                file_id: 9,
+                tileset_id: 0
+            }),
+            tileset.link
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use super::reader::Reader;
+use super::writer::{count, Writer};
+use crate::{Color, Error};
+
+/// Properties by name.
+pub type Properties = BTreeMap<String, PropertyValue>;
+
+/// Value of a user data property, as added in Aseprite 1.3.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum PropertyValue {
+    /// Boolean.
+    Bool(bool),
+    /// 8-bit signed integer.
+    I8(i8),
+    /// 8-bit unsigned integer.
+    U8(u8),
+    /// 16-bit signed integer.
+    I16(i16),
+    /// 16-bit unsigned integer.
+    U16(u16),
+    /// 32-bit signed integer.
+    I32(i32),
+    /// 32-bit unsigned integer.
+    U32(u32),
+    /// 64-bit signed integer.
+    I64(i64),
+    /// 64-bit unsigned integer.
+    U64(u64),
+    /// 16.16 fixed point number, as stored.
+    Fixed(i32),
+    /// Single precision float.
+    F32(f32),
+    /// Double precision float.
+    F64(f64),
+    /// Text.
+    String(String),
+    /// Point.
+    Point {
+        /// X coordinate.
+        x: i32,
+        /// Y coordinate.
+        y: i32,
+    },
+    /// Size.
+    Size {
+        /// Width.
+        w: i32,
+        /// Height.
+        h: i32,
+    },
+    /// Rectangle.
+    Rect {
+        /// X coordinate.
+        x: i32,
+        /// Y coordinate.
+        y: i32,
+        /// Width.
+        w: i32,
+        /// Height.
+        h: i32,
+    },
+    /// List of values.
+    Vector(Vec<PropertyValue>),
+    /// Nested properties.
+    Map(Properties),
+    /// UUID.
+    Uuid([u8; 16]),
+}
+
+impl PropertyValue {
+    /// Converts a [`PropertyValue::Fixed`] to a float.
+    pub fn fixed_to_f64(fixed: i32) -> f64 {
+        fixed as f64 / 65536.0
+    }
+
+    fn type_id(&self) -> u16 {
+        match self {
+            PropertyValue::Bool(_) => 0x0001,
+            PropertyValue::I8(_) => 0x0002,
+            PropertyValue::U8(_) => 0x0003,
+            PropertyValue::I16(_) => 0x0004,
+            PropertyValue::U16(_) => 0x0005,
+            PropertyValue::I32(_) => 0x0006,
+            PropertyValue::U32(_) => 0x0007,
+            PropertyValue::I64(_) => 0x0008,
+            PropertyValue::U64(_) => 0x0009,
+            PropertyValue::Fixed(_) => 0x000A,
+            PropertyValue::F32(_) => 0x000B,
+            PropertyValue::F64(_) => 0x000C,
+            PropertyValue::String(_) => 0x000D,
+            PropertyValue::Point { .. } => 0x000E,
+            PropertyValue::Size { .. } => 0x000F,
+            PropertyValue::Rect { .. } => 0x0010,
+            PropertyValue::Vector(_) => 0x0011,
+            PropertyValue::Map(_) => 0x0012,
+            PropertyValue::Uuid(_) => 0x0013,
+        }
+    }
+
+    fn write(&self, w: &mut Writer) -> Result<(), Error> {
+        match self {
+            PropertyValue::Bool(v) => w.byte(*v as u8),
+            PropertyValue::I8(v) => w.byte(*v as u8),
+            PropertyValue::U8(v) => w.byte(*v),
+            PropertyValue::I16(v) => w.short(*v),
+            PropertyValue::U16(v) => w.word(*v),
+            PropertyValue::I32(v) | PropertyValue::Fixed(v) => w.long(*v),
+            PropertyValue::U32(v) => w.dword(*v),
+            PropertyValue::I64(v) => w.qword(*v as u64),
+            PropertyValue::U64(v) => w.qword(*v),
+            PropertyValue::F32(v) => w.dword(v.to_bits()),
+            PropertyValue::F64(v) => w.qword(v.to_bits()),
+            PropertyValue::String(v) => w.string(v)?,
+            PropertyValue::Point { x, y } | PropertyValue::Size { w: x, h: y } => {
+                w.long(*x);
+                w.long(*y);
+            }
+            PropertyValue::Rect { x, y, w: width, h } => {
+                for v in [x, y, width, h] {
+                    w.long(*v);
+                }
+            }
+            PropertyValue::Vector(elements) => {
+                w.dword(count(elements.len(), "vector elements")?);
+                // Mixed vectors store the type of each element.
+                let kind = match elements.first() {
+                    Some(first) if elements.iter().all(|e| e.type_id() == first.type_id()) => {
+                        first.type_id()
+                    }
+                    _ => 0,
+                };
+                w.word(kind);
+                for element in elements {
+                    if kind == 0 {
+                        w.word(element.type_id());
+                    }
+                    element.write(w)?;
+                }
+            }
+            PropertyValue::Map(map) => write_properties(w, map)?,
+            PropertyValue::Uuid(uuid) => w.bytes(uuid),
+        }
+        Ok(())
+    }
+
+    /// Reads a value of type `kind`, `None` if this parser doesn't know
+    /// the type, in which case the reader is left somewhere inside it.
+    fn parse(r: &mut Reader, kind: u16, depth: usize) -> Result<Option<Self>, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::format("properties are nested too deeply"));
+        }
+        let point = |r: &mut Reader| -> Result<(i32, i32), Error> { Ok((r.long()?, r.long()?)) };
+        Ok(Some(match kind {
+            0x0001 => PropertyValue::Bool(r.byte()? != 0),
+            0x0002 => PropertyValue::I8(r.byte()? as i8),
+            0x0003 => PropertyValue::U8(r.byte()?),
+            0x0004 => PropertyValue::I16(r.short()?),
+            0x0005 => PropertyValue::U16(r.word()?),
+            0x0006 => PropertyValue::I32(r.long()?),
+            0x0007 => PropertyValue::U32(r.dword()?),
+            0x0008 => PropertyValue::I64(r.qword()? as i64),
+            0x0009 => PropertyValue::U64(r.qword()?),
+            0x000A => PropertyValue::Fixed(r.long()?),
+            0x000B => PropertyValue::F32(f32::from_bits(r.dword()?)),
+            0x000C => PropertyValue::F64(f64::from_bits(r.qword()?)),
+            0x000D => PropertyValue::String(r.string()?),
+            0x000E => {
+                let (x, y) = point(r)?;
+                PropertyValue::Point { x, y }
+            }
+            0x000F => {
+                let (w, h) = point(r)?;
+                PropertyValue::Size { w, h }
+            }
+            0x0010 => {
+                let (x, y) = point(r)?;
+                let (w, h) = point(r)?;
+                PropertyValue::Rect { x, y, w, h }
+            }
+            0x0011 => {
+                let len = r.dword()?;
+                let element_kind = r.word()?;
+                let mut elements = Vec::new();
+                for _ in 0..len {
+                    let kind = match element_kind {
+                        0 => r.word()?,
+                        kind => kind,
+                    };
+                    match Self::parse(r, kind, depth + 1)? {
+                        Some(element) => elements.push(element),
+                        None => return Ok(None),
+                    }
+                }
+                PropertyValue::Vector(elements)
+            }
+            0x0012 => match parse_properties(r, depth + 1)? {
+                Some(map) => PropertyValue::Map(map),
+                None => return Ok(None),
+            },
+            0x0013 => {
+                let mut uuid = [0; 16];
+                uuid.copy_from_slice(r.bytes(16)?);
+                PropertyValue::Uuid(uuid)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+const MAX_DEPTH: usize = 64;
+
+/// A map of properties, `None` if it holds a type this parser doesn't know.
+fn parse_properties(r: &mut Reader, depth: usize) -> Result<Option<Properties>, Error> {
+    let len = r.dword()?;
+    let mut properties = Properties::new();
+    for _ in 0..len {
+        let name = r.string()?;
+        let kind = r.word()?;
+        match PropertyValue::parse(r, kind, depth)? {
+            Some(value) => properties.insert(name, value),
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(properties))
+}
+
+fn write_properties(w: &mut Writer, properties: &Properties) -> Result<(), Error> {
+    w.dword(count(properties.len(), "properties")?);
+    for (name, value) in properties {
+        w.string(name)?;
+        w.word(value.type_id());
+        value.write(w)?;
+    }
+    Ok(())
+}
+
+/// Custom data attached to the sprite, a layer, cel, tag, slice or tile.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[non_exhaustive]
+pub struct UserData {
+    /// Text.
+    pub text: Option<String>,
+    /// Color.
+    pub color: Option<Color>,
+    /// Properties, keyed by the id of the extension that owns them.
+    ///
+    /// Key `0` holds the user's own properties; other keys are entries of
+    /// [`AseFile::external_files`](super::AseFile::external_files). A map
+    /// with a property type from a newer Aseprite is left out, along with
+    /// the maps after it.
+    pub properties: BTreeMap<u32, Properties>,
+}
+
+impl UserData {
+    const FLAG_TEXT: u32 = 1;
+    const FLAG_COLOR: u32 = 2;
+    const FLAG_PROPERTIES: u32 = 4;
+
+    /// Properties the user set, as opposed to ones set by extensions.
+    pub fn user_properties(&self) -> Option<&Properties> {
+        self.properties.get(&0)
+    }
+
+    pub(crate) fn parse(r: &mut Reader) -> Result<Self, Error> {
+        let flags = r.dword()?;
+        let text = if flags & Self::FLAG_TEXT != 0 {
+            Some(r.string()?)
+        } else {
+            None
+        };
+        let color = if flags & Self::FLAG_COLOR != 0 {
+            Some(Color {
+                r: r.byte()?,
+                g: r.byte()?,
+                b: r.byte()?,
+                a: r.byte()?,
+            })
+        } else {
+            None
+        };
+
+        let mut properties = BTreeMap::new();
+        if flags & Self::FLAG_PROPERTIES != 0 {
+            let start = r.position();
+            let size = r.dword()? as usize;
+            let maps = r.dword()?;
+            for _ in 0..maps {
+                let key = r.dword()?;
+                match parse_properties(r, 0)? {
+                    Some(map) => properties.insert(key, map),
+                    // Types from newer versions of Aseprite can't be read
+                    // past, so leave out the rest of the maps.
+                    None => {
+                        let end = start.saturating_add(size);
+                        if size < 8 || end < r.position() {
+                            return Err(Error::format("unknown property type"));
+                        }
+                        r.skip(end - r.position())?;
+                        break;
+                    }
+                };
+            }
+        }
+
+        Ok(Self {
+            text,
+            color,
+            properties,
+        })
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer) -> Result<(), Error> {
+        let mut flags = 0;
+        if self.text.is_some() {
+            flags |= Self::FLAG_TEXT;
+        }
+        if self.color.is_some() {
+            flags |= Self::FLAG_COLOR;
+        }
+        if !self.properties.is_empty() {
+            flags |= Self::FLAG_PROPERTIES;
+        }
+        w.dword(flags);
+        if let Some(text) = &self.text {
+            w.string(text)?;
+        }
+        if let Some(c) = self.color {
+            w.bytes(&[c.r, c.g, c.b, c.a]);
+        }
+        if !self.properties.is_empty() {
+            let start = w.len();
+            w.dword(0);
+            w.dword(count(self.properties.len(), "property maps")?);
+            for (key, properties) in &self.properties {
+                w.dword(*key);
+                write_properties(w, properties)?;
+            }
+            let size = count(w.len() - start, "bytes of properties")?;
+            w.patch_dword(start, size);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Little-endian primitives for writing the .ase format.
+
+use crate::Error;
+
+/// Growing buffer for a file or chunk.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn zeros(&mut self, len: usize) {
+        self.data.resize(self.data.len() + len, 0);
+    }
+
+    pub(crate) fn byte(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub(crate) fn word(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn short(&mut self, value: i16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn dword(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn long(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn qword(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn string(&mut self, value: &str) -> Result<(), Error> {
+        self.word(count(value.len(), "bytes of a string")?);
+        self.bytes(value.as_bytes());
+        Ok(())
+    }
+
+    /// Overwrites a dword written earlier, for sizes only known afterwards.
+    pub(crate) fn patch_dword(&mut self, at: usize, value: u32) {
+        self.data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Converts a length to the integer type a field stores it in.
+pub(crate) fn count<T: TryFrom<usize>>(len: usize, what: &str) -> Result<T, Error> {
+    T::try_from(len).map_err(|_| Error::format(format!("too many {}: {}", what, len)))
+}
@@ -0,0 +1,565 @@
+//! Packing the frames of many sprite sheets into shared atlas pages.
+//!
+//! Frames are placed with the MaxRects algorithm, best short side fit.
+//! All the frames of a sheet go on the same page, so each packed sheet
+//! still refers to a single image.
+
+use std::collections::HashMap;
+
+use crate::image::RgbaImage;
+use crate::{Dimensions, Error, Rect, SpritesheetData};
+
+/// How atlas pages are laid out.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct AtlasOptions {
+    /// Largest page width in pixels.
+    pub max_width: u32,
+    /// Largest page height in pixels.
+    pub max_height: u32,
+    /// Whether page sides are powers of two.
+    pub power_of_two: bool,
+    /// Transparent pixels kept between frames.
+    pub padding: u32,
+    /// Whether frames with the same pixels, after trimming, share a
+    /// rectangle, including frames of different sheets on the same page.
+    pub dedupe: bool,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            max_width: 2048,
+            max_height: 2048,
+            power_of_two: false,
+            padding: 0,
+            dedupe: true,
+        }
+    }
+}
+
+/// Sprite sheet moved into an atlas page.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct PackedSheet {
+    /// Index of the page in [`Atlas::pages`].
+    pub page: usize,
+    /// The sheet with its frames moved to the page.
+    ///
+    /// `meta.size` is the size of the page, `meta.image` is `None` until
+    /// the page is given a file name.
+    pub data: SpritesheetData,
+}
+
+/// Frames of many sprite sheets packed into a few images.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Atlas {
+    /// Page images.
+    pub pages: Vec<RgbaImage>,
+    /// The packed sheets, in the order they were given.
+    pub sheets: Vec<PackedSheet>,
+    /// Frames that didn't need space of their own.
+    pub dedupe: DedupeReport,
+}
+
+/// Frames found to repeat others.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct DedupeReport {
+    /// Frames sharing the pixels of an earlier frame.
+    pub duplicate_frames: usize,
+    /// Bytes of RGBA pixels that are no longer stored.
+    pub bytes_saved: u64,
+}
+
+/// Free space of a page, as the largest empty rectangles.
+#[derive(Debug, Clone)]
+pub(crate) struct MaxRects {
+    free: Vec<Rect>,
+}
+
+impl MaxRects {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self {
+            free: vec![Rect {
+                x: 0,
+                y: 0,
+                w: width,
+                h: height,
+            }],
+        }
+    }
+
+    /// Place for a `w` by `h` rectangle, `None` if there's no room.
+    pub(crate) fn insert(&mut self, w: u32, h: u32) -> Option<Rect> {
+        let placed = self
+            .free
+            .iter()
+            .filter(|free| free.w >= w && free.h >= h)
+            .min_by_key(|free| {
+                let (dw, dh) = (free.w - w, free.h - h);
+                (dw.min(dh), dw.max(dh), free.y, free.x)
+            })
+            .map(|free| Rect { w, h, ..*free })?;
+
+        let mut split = Vec::with_capacity(self.free.len() + 4);
+        for free in self.free.drain(..) {
+            if !intersects(free, placed) {
+                split.push(free);
+                continue;
+            }
+            if placed.x > free.x {
+                split.push(Rect {
+                    w: placed.x - free.x,
+                    ..free
+                });
+            }
+            if placed.x + placed.w < free.x + free.w {
+                split.push(Rect {
+                    x: placed.x + placed.w,
+                    w: free.x + free.w - placed.x - placed.w,
+                    ..free
+                });
+            }
+            if placed.y > free.y {
+                split.push(Rect {
+                    h: placed.y - free.y,
+                    ..free
+                });
+            }
+            if placed.y + placed.h < free.y + free.h {
+                split.push(Rect {
+                    y: placed.y + placed.h,
+                    h: free.y + free.h - placed.y - placed.h,
+                    ..free
+                });
+            }
+        }
+        // Drop rectangles inside others, keeping the first of equal ones.
+        self.free = split
+            .iter()
+            .enumerate()
+            .filter(|&(i, a)| {
+                !split
+                    .iter()
+                    .enumerate()
+                    .any(|(j, b)| i != j && contains(*b, *a) && (a != b || j < i))
+            })
+            .map(|(_, a)| *a)
+            .collect();
+        Some(placed)
+    }
+}
+
+fn intersects(a: Rect, b: Rect) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+fn contains(outer: Rect, inner: Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.w <= outer.x + outer.w
+        && inner.y + inner.h <= outer.y + outer.h
+}
+
+/// Largest power of two that isn't above `n`, at least 1.
+fn power_of_two_below(n: u32) -> u32 {
+    1 << n.max(1).ilog2()
+}
+
+/// Frames of a sheet that show distinct parts of its image.
+struct SheetFrames {
+    parts: Vec<Part>,
+    /// Index in `parts` of every frame.
+    part_of_frame: Vec<usize>,
+}
+
+/// Pixels of one or more frames.
+struct Part {
+    pixels: RgbaImage,
+    hash: u64,
+}
+
+impl SheetFrames {
+    fn new(sheet: &SpritesheetData, image: &RgbaImage) -> Self {
+        let mut seen = HashMap::new();
+        let mut parts = Vec::new();
+        let part_of_frame = sheet
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let (rect, trimmed) = (frame.frame, frame.sprite_source_size);
+                let key = (rect, frame.rotated, trimmed.w, trimmed.h);
+                *seen.entry(key).or_insert_with(|| {
+                    let pixels = sheet.trimmed_frame(image, i).unwrap_or_default();
+                    let hash = pixels.pixel_hash();
+                    parts.push(Part { pixels, hash });
+                    parts.len() - 1
+                })
+            })
+            .collect();
+        Self {
+            parts,
+            part_of_frame,
+        }
+    }
+
+    fn area(&self) -> u64 {
+        self.parts
+            .iter()
+            .map(|part| part.pixels.width as u64 * part.pixels.height as u64)
+            .sum()
+    }
+}
+
+/// Page being filled.
+#[derive(Clone)]
+struct Page {
+    space: MaxRects,
+    /// Sheet, part and place of the parts on the page, by pixel hash.
+    parts: HashMap<u64, Vec<(usize, usize, Rect)>>,
+}
+
+/// Packs the frames of every sheet, along with the image each refers to.
+///
+/// Frames that share a rectangle in their sheet, such as merged
+/// duplicates, keep sharing one, and others with the same pixels get to
+/// share one too unless `dedupe` is off. Rotated frames are stored upright, and
+/// tags, layers and slices are kept as they are.
+pub fn pack(
+    sheets: &[(&SpritesheetData, &RgbaImage)],
+    options: &AtlasOptions,
+) -> Result<Atlas, Error> {
+    let (mut max_width, mut max_height) = (options.max_width, options.max_height);
+    if options.power_of_two {
+        max_width = power_of_two_below(max_width);
+        max_height = power_of_two_below(max_height);
+    }
+    let padding = options.padding;
+    // Every frame takes its padding to the right and bottom, which the
+    // page gets too so the last frames fit.
+    let empty_page = Page {
+        space: MaxRects::new(
+            max_width.saturating_add(padding),
+            max_height.saturating_add(padding),
+        ),
+        parts: HashMap::new(),
+    };
+
+    let frames: Vec<SheetFrames> = sheets
+        .iter()
+        .map(|(sheet, image)| SheetFrames::new(sheet, image))
+        .collect();
+    // Larger sheets first leave smaller ones to fill the gaps.
+    let mut order: Vec<usize> = (0..sheets.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(frames[i].area()));
+
+    let mut pages: Vec<Page> = Vec::new();
+    let mut placements = vec![(0, Vec::new()); sheets.len()];
+    let mut dedupe = DedupeReport::default();
+    for i in order {
+        let parts = &frames[i].parts;
+        let mut part_order: Vec<usize> = (0..parts.len()).collect();
+        part_order.sort_by_key(|&p| {
+            let (w, h) = (parts[p].pixels.width, parts[p].pixels.height);
+            std::cmp::Reverse((w.max(h), w.min(h)))
+        });
+        if let Some((frame, part)) = parts
+            .iter()
+            .enumerate()
+            .find(|(_, part)| part.pixels.width > max_width || part.pixels.height > max_height)
+        {
+            return Err(Error::format(format!(
+                "frame {} of sheet {} is {}x{}, larger than a {}x{} page",
+                frames[i]
+                    .part_of_frame
+                    .iter()
+                    .position(|&p| p == frame)
+                    .unwrap_or(0),
+                i,
+                part.pixels.width,
+                part.pixels.height,
+                max_width,
+                max_height
+            )));
+        }
+
+        // Places the parts on a copy of the page, along with the bytes the
+        // ones already there saved.
+        let place = |page: &Page| {
+            let mut page = page.clone();
+            let mut rects = vec![
+                Rect {
+                    x: 0,
+                    y: 0,
+                    w: 0,
+                    h: 0
+                };
+                parts.len()
+            ];
+            let mut saved = (0, 0);
+            for &p in &part_order {
+                let part = &parts[p];
+                let (w, h) = (part.pixels.width, part.pixels.height);
+                if w == 0 || h == 0 {
+                    rects[p] = Rect { x: 0, y: 0, w, h };
+                    continue;
+                }
+                let same = page.parts.get(&part.hash).and_then(|placed| {
+                    placed
+                        .iter()
+                        .find(|&&(s, q, _)| frames[s].parts[q].pixels == part.pixels)
+                });
+                if let (true, Some(&(_, _, rect))) = (options.dedupe, same) {
+                    rects[p] = rect;
+                    saved = (saved.0 + 1, saved.1 + w as u64 * h as u64 * 4);
+                    continue;
+                }
+                let rect = page
+                    .space
+                    .insert(w.saturating_add(padding), h.saturating_add(padding))?;
+                rects[p] = Rect { w, h, ..rect };
+                page.parts
+                    .entry(part.hash)
+                    .or_default()
+                    .push((i, p, rects[p]));
+            }
+            Some((page, rects, saved))
+        };
+        let placed = pages
+            .iter()
+            .enumerate()
+            .find_map(|(index, page)| place(page).map(|placed| (index, placed)));
+        let (index, (page, rects, saved)) = match placed {
+            Some(placed) => placed,
+            None => {
+                let placed = place(&empty_page).ok_or_else(|| {
+                    Error::format(format!(
+                        "sheet {} doesn't fit in a {}x{} page",
+                        i, max_width, max_height
+                    ))
+                })?;
+                pages.push(empty_page.clone());
+                (pages.len() - 1, placed)
+            }
+        };
+        pages[index] = page;
+        placements[i] = (index, rects);
+        dedupe.duplicate_frames += frames[i].part_of_frame.len() - (parts.len() - saved.0);
+        dedupe.bytes_saved += saved.1;
+    }
+
+    let mut sizes = vec![(1, 1); pages.len()];
+    for (page, rects) in &placements {
+        for rect in rects {
+            let size = &mut sizes[*page];
+            *size = (size.0.max(rect.x + rect.w), size.1.max(rect.y + rect.h));
+        }
+    }
+    if options.power_of_two {
+        for size in &mut sizes {
+            *size = (size.0.next_power_of_two(), size.1.next_power_of_two());
+        }
+    }
+    let mut pages: Vec<RgbaImage> = sizes
+        .iter()
+        .map(|&(width, height)| RgbaImage::new(width, height))
+        .collect();
+
+    let mut packed = Vec::with_capacity(sheets.len());
+    for (i, &(sheet, _)) in sheets.iter().enumerate() {
+        let (page, rects) = &placements[i];
+        for (part, rect) in frames[i].parts.iter().zip(rects) {
+            pages[*page].draw(&part.pixels, rect.x as i32, rect.y as i32, 255);
+        }
+
+        let mut data = sheet.clone();
+        for (frame, &part) in data.frames.iter_mut().zip(&frames[i].part_of_frame) {
+            frame.frame = rects[part];
+            frame.rotated = false;
+        }
+        data.meta.size = Dimensions {
+            w: pages[*page].width,
+            h: pages[*page].height,
+        };
+        data.meta.image = None;
+        data.meta.format = "RGBA8888".to_string();
+        packed.push(PackedSheet { page: *page, data });
+    }
+    Ok(Atlas {
+        pages,
+        sheets: packed,
+        dedupe,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    /// Sheet with frames of the given sizes side by side, each filled with
+    /// a gradient unique to `seed`.
+    fn sheet(sizes: &[(u32, u32)], seed: u8) -> (SpritesheetData, RgbaImage) {
+        let width = sizes.iter().map(|s| s.0).sum();
+        let height = sizes.iter().map(|s| s.1).max().unwrap_or(0);
+        let mut image = RgbaImage::new(width, height);
+        let mut frames = Vec::new();
+        let mut x = 0;
+        for (i, &(w, h)) in sizes.iter().enumerate() {
+            for py in 0..h {
+                for px in 0..w {
+                    let color = Color {
+                        r: seed,
+                        g: i as u8,
+                        b: (px * 16 + py) as u8,
+                        a: 255,
+                    };
+                    image.set_pixel(x + px, py, color);
+                }
+            }
+            frames.push(serde_json::json!({
+                "filename": format!("{} {}", seed, i),
+                "frame": { "x": x, "y": 0, "w": w, "h": h },
+                "rotated": false,
+                "trimmed": true,
+                "spriteSourceSize": { "x": 1, "y": 2, "w": w, "h": h },
+                "sourceSize": { "w": w + 2, "h": h + 4 },
+                "duration": 100
+            }));
+            x += w;
+        }
+        let json = serde_json::json!({
+            "frames": frames,
+            "meta": {
+                "app": "http://www.aseprite.org/",
+                "version": "1.3",
+                "image": format!("{}.png", seed),
+                "format": "RGBA8888",
+                "size": { "w": width, "h": height },
+                "scale": "1",
+                "frameTags": [
+                    { "name": "walk", "from": 0, "to": 0, "direction": "forward" }
+                ]
+            }
+        });
+        (serde_json::from_value(json).unwrap(), image)
+    }
+
+    fn check(sheets: &[(SpritesheetData, RgbaImage)], atlas: &Atlas, options: &AtlasOptions) {
+        assert_eq!(sheets.len(), atlas.sheets.len());
+        let mut placed: Vec<Vec<Rect>> = vec![Vec::new(); atlas.pages.len()];
+        for ((data, image), packed) in sheets.iter().zip(&atlas.sheets) {
+            let page = &atlas.pages[packed.page];
+            assert_eq!(data.meta.frame_tags, packed.data.meta.frame_tags);
+            assert_eq!(page.width, packed.data.meta.size.w);
+            assert!(page.width <= options.max_width && page.height <= options.max_height);
+            for i in 0..data.frames.len() {
+                assert_eq!(
+                    data.extract_frame(image, i),
+                    packed.data.extract_frame(page, i)
+                );
+                placed[packed.page].push(packed.data.frames[i].frame);
+            }
+        }
+        for rects in &placed {
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    let padded = |r: &Rect| Rect {
+                        w: r.w + options.padding,
+                        h: r.h + options.padding,
+                        ..*r
+                    };
+                    assert!(
+                        a == b || !intersects(padded(a), padded(b)),
+                        "{:?} {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        let sheets = vec![
+            sheet(&[(5, 7), (3, 3), (8, 2)], 1),
+            sheet(&[(16, 16), (1, 1), (4, 9), (9, 4)], 2),
+            sheet(&[(2, 2); 6], 3),
+        ];
+        let inputs: Vec<_> = sheets.iter().map(|(d, i)| (d, i)).collect();
+        let options = AtlasOptions {
+            padding: 1,
+            power_of_two: true,
+            ..AtlasOptions::default()
+        };
+        let atlas = pack(&inputs, &options).unwrap();
+        assert_eq!(1, atlas.pages.len());
+        assert!(atlas.pages[0].width.is_power_of_two() && atlas.pages[0].height.is_power_of_two());
+        assert_eq!(None, atlas.sheets[0].data.meta.image);
+        check(&sheets, &atlas, &options);
+
+        // Frames sharing a rectangle keep sharing it.
+        let (mut data, image) = sheet(&[(4, 4), (4, 4)], 4);
+        data.frames[1].data.frame = data.frames[0].frame;
+        let atlas = pack(&[(&data, &image)], &AtlasOptions::default()).unwrap();
+        let frames = &atlas.sheets[0].data.frames;
+        assert_eq!(frames[0].frame, frames[1].frame);
+        assert_eq!((4, 4), (atlas.pages[0].width, atlas.pages[0].height));
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let sheets = vec![
+            sheet(&[(5, 7), (3, 3)], 1),
+            sheet(&[(5, 7), (3, 3)], 1),
+            sheet(&[(4, 4)], 2),
+        ];
+        let inputs: Vec<_> = sheets.iter().map(|(d, i)| (d, i)).collect();
+        let atlas = pack(&inputs, &AtlasOptions::default()).unwrap();
+        check(&sheets, &atlas, &AtlasOptions::default());
+        assert_eq!(atlas.sheets[0].data.frames, atlas.sheets[1].data.frames);
+        assert_eq!(2, atlas.dedupe.duplicate_frames);
+        assert_eq!((5 * 7 + 3 * 3) * 4, atlas.dedupe.bytes_saved);
+
+        let options = AtlasOptions {
+            dedupe: false,
+            ..AtlasOptions::default()
+        };
+        let atlas = pack(&inputs, &options).unwrap();
+        check(&sheets, &atlas, &options);
+        assert_ne!(atlas.sheets[0].data.frames, atlas.sheets[1].data.frames);
+        assert_eq!(DedupeReport::default(), atlas.dedupe);
+    }
+
+    #[test]
+    fn test_pages() {
+        let sheets: Vec<_> = (0..6).map(|i| sheet(&[(10, 10), (6, 6)], i)).collect();
+        let inputs: Vec<_> = sheets.iter().map(|(d, i)| (d, i)).collect();
+        let options = AtlasOptions {
+            max_width: 24,
+            max_height: 20,
+            padding: 2,
+            ..AtlasOptions::default()
+        };
+        let atlas = pack(&inputs, &options).unwrap();
+        assert!(atlas.pages.len() > 1);
+        check(&sheets, &atlas, &options);
+
+        let large = sheet(&[(30, 1)], 9);
+        assert!(pack(&[(&large.0, &large.1)], &options).is_err());
+    }
+
+    #[test]
+    fn test_max_rects() {
+        let mut page = MaxRects::new(4, 4);
+        let rects: Vec<Rect> = (0..4).map(|_| page.insert(2, 2).unwrap()).collect();
+        assert!(page.insert(1, 1).is_none());
+        for (i, a) in rects.iter().enumerate() {
+            assert!(rects[i + 1..].iter().all(|b| !intersects(*a, *b)));
+        }
+    }
+}
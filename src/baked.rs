@@ -0,0 +1,777 @@
+//! Compact binary form of [`SpritesheetData`] for loading at runtime.
+//!
+//! [`bake`] turns a sheet, usually parsed from Aseprite's JSON at build
+//! time, into bytes that [`load`] reads back without any JSON parsing.
+//! [`BakedSheet`] reads frames, tags and slices straight out of the bytes
+//! instead, borrowing names rather than copying them:
+//!
+//! ```
+//! # let json = include_bytes!("../testdata/export/sheet.json");
+//! # let sheet: aseprite::SpritesheetData = serde_json::from_slice(json).unwrap();
+//! let bytes = aseprite::baked::bake(&sheet).unwrap();
+//! let baked = aseprite::baked::BakedSheet::parse(&bytes).unwrap();
+//! let run = baked.tag("run").unwrap();
+//! assert_eq!("hero run 1.ase", baked.frame(run.to as usize).unwrap().filename);
+//! ```
+//!
+//! The format is little-endian. A header with counts and the sheet's
+//! metadata is followed by fixed-size records for frames, tags, layers,
+//! slices and slice keys, then a table of every distinct string. Records
+//! refer to strings by their index, so names shared between frames are
+//! stored once. Tags are also listed by name for lookups.
+
+use std::collections::HashMap;
+
+use crate::ase::layer::BLEND_MODES;
+use crate::ase::writer::{count, Writer};
+use crate::{
+    Color, Dimensions, Direction, Error, Frame, FrameData, Frametag, Layer, Metadata, Point, Rect,
+    Slice, SliceKey, SpritesheetData,
+};
+
+/// Version [`bake`] writes, and the only one [`BakedSheet::parse`] reads.
+pub const VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"ASPB";
+/// String index of a missing string.
+const NONE: u32 = u32::MAX;
+
+const HEADER_LEN: usize = 60;
+const FRAME_LEN: usize = 52;
+const TAG_LEN: usize = 28;
+const LAYER_LEN: usize = 24;
+const SLICE_LEN: usize = 20;
+const KEY_LEN: usize = 48;
+
+const FRAME_ROTATED: u32 = 1;
+const FRAME_TRIMMED: u32 = 2;
+const TAG_REPEAT: u8 = 1;
+const TAG_COLOR: u8 = 2;
+const LAYER_OPACITY: u8 = 1;
+const LAYER_BLEND_MODE: u8 = 2;
+const LAYER_COLOR: u8 = 4;
+const KEY_PIVOT: u32 = 1;
+const KEY_CENTER: u32 = 2;
+
+/// Strings of a sheet being baked, each stored once.
+#[derive(Default)]
+struct Strings<'a> {
+    indices: HashMap<&'a str, u32>,
+    list: Vec<&'a str>,
+}
+
+impl<'a> Strings<'a> {
+    fn add(&mut self, string: &'a str) -> Result<u32, Error> {
+        if let Some(&index) = self.indices.get(string) {
+            return Ok(index);
+        }
+        let index = count(self.list.len(), "strings")?;
+        if index == NONE {
+            return Err(Error::format("too many strings"));
+        }
+        self.indices.insert(string, index);
+        self.list.push(string);
+        Ok(index)
+    }
+
+    fn add_option(&mut self, string: Option<&'a str>) -> Result<u32, Error> {
+        string.map_or(Ok(NONE), |string| self.add(string))
+    }
+}
+
+fn write_rect(w: &mut Writer, rect: Rect) {
+    for value in [rect.x, rect.y, rect.w, rect.h] {
+        w.dword(value);
+    }
+}
+
+fn write_color(w: &mut Writer, color: Option<Color>) {
+    let color = color.unwrap_or(Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    });
+    w.bytes(&[color.r, color.g, color.b, color.a]);
+}
+
+/// Bakes `sheet` into the binary format.
+///
+/// Fails only if the sheet has more than `u32::MAX` of something.
+pub fn bake(sheet: &SpritesheetData) -> Result<Vec<u8>, Error> {
+    let meta = &sheet.meta;
+    let mut strings = Strings::default();
+
+    let mut frames = Writer::new();
+    for frame in &sheet.frames {
+        frames.dword(strings.add(&frame.filename)?);
+        write_rect(&mut frames, frame.frame);
+        write_rect(&mut frames, frame.sprite_source_size);
+        frames.dword(frame.source_size.w);
+        frames.dword(frame.source_size.h);
+        frames.dword(frame.duration);
+        let rotated = if frame.rotated { FRAME_ROTATED } else { 0 };
+        let trimmed = if frame.trimmed { FRAME_TRIMMED } else { 0 };
+        frames.dword(rotated | trimmed);
+    }
+
+    let mut tags = Writer::new();
+    for tag in &meta.frame_tags {
+        tags.dword(strings.add(&tag.name)?);
+        tags.dword(tag.from);
+        tags.dword(tag.to);
+        tags.byte(match tag.direction {
+            Direction::Forward => 0,
+            Direction::Reverse => 1,
+            Direction::Pingpong => 2,
+            Direction::PingpongReverse => 3,
+        });
+        let repeat = if tag.repeat.is_some() { TAG_REPEAT } else { 0 };
+        let color = if tag.color.is_some() { TAG_COLOR } else { 0 };
+        tags.byte(repeat | color);
+        tags.zeros(2);
+        tags.dword(tag.repeat.unwrap_or(0));
+        write_color(&mut tags, tag.color);
+        tags.dword(strings.add_option(tag.data.as_deref())?);
+    }
+    // Tags by name, keeping the first of equal names first.
+    let mut by_name: Vec<usize> = (0..meta.frame_tags.len()).collect();
+    by_name.sort_by_key(|&i| &meta.frame_tags[i].name);
+
+    let mut layers = Writer::new();
+    for layer in &meta.layers {
+        layers.dword(strings.add(&layer.name)?);
+        layers.dword(strings.add_option(layer.group.as_deref())?);
+        layers.dword(layer.opacity.unwrap_or(0));
+        let blend_mode = layer.blend_mode.unwrap_or_default();
+        let blend_mode = BLEND_MODES.iter().position(|&mode| mode == blend_mode);
+        layers.byte(blend_mode.unwrap_or(0) as u8);
+        let mut flags = 0;
+        if layer.opacity.is_some() {
+            flags |= LAYER_OPACITY;
+        }
+        if layer.blend_mode.is_some() {
+            flags |= LAYER_BLEND_MODE;
+        }
+        if layer.color.is_some() {
+            flags |= LAYER_COLOR;
+        }
+        layers.byte(flags);
+        layers.zeros(2);
+        write_color(&mut layers, layer.color);
+        layers.dword(strings.add_option(layer.data.as_deref())?);
+    }
+
+    let mut slices = Writer::new();
+    let mut keys = Writer::new();
+    let mut key_count = 0;
+    for slice in &meta.slices {
+        slices.dword(strings.add(&slice.name)?);
+        write_color(&mut slices, Some(slice.color));
+        slices.dword(count(key_count, "slice keys")?);
+        slices.dword(count(slice.keys.len(), "slice keys")?);
+        slices.dword(strings.add_option(slice.data.as_deref())?);
+        for key in &slice.keys {
+            keys.dword(key.frame);
+            write_rect(&mut keys, key.bounds);
+            let pivot = key.pivot.unwrap_or(Point { x: 0, y: 0 });
+            keys.dword(pivot.x);
+            keys.dword(pivot.y);
+            let center = key.center.unwrap_or(Rect {
+                x: 0,
+                y: 0,
+                w: 0,
+                h: 0,
+            });
+            write_rect(&mut keys, center);
+            let pivot = if key.pivot.is_some() { KEY_PIVOT } else { 0 };
+            let center = if key.center.is_some() { KEY_CENTER } else { 0 };
+            keys.dword(pivot | center);
+        }
+        key_count += slice.keys.len();
+    }
+
+    let header = [
+        strings.add(&meta.app)?,
+        strings.add(&meta.version)?,
+        strings.add(&meta.format)?,
+        strings.add(&meta.scale)?,
+        strings.add_option(meta.image.as_deref())?,
+    ];
+    let mut w = Writer::new();
+    w.bytes(MAGIC);
+    w.word(VERSION);
+    w.word(0);
+    w.dword(count(strings.list.len(), "strings")?);
+    w.dword(count(sheet.frames.len(), "frames")?);
+    w.dword(count(meta.frame_tags.len(), "tags")?);
+    w.dword(count(meta.layers.len(), "layers")?);
+    w.dword(count(meta.slices.len(), "slices")?);
+    w.dword(count(key_count, "slice keys")?);
+    for string in header {
+        w.dword(string);
+    }
+    w.dword(meta.size.w);
+    w.dword(meta.size.h);
+
+    let mut end = 0;
+    w.dword(0);
+    for string in &strings.list {
+        end += string.len();
+        w.dword(count(end, "bytes of strings")?);
+    }
+    w.bytes(&frames.into_bytes());
+    w.bytes(&tags.into_bytes());
+    for i in by_name {
+        w.dword(i as u32);
+    }
+    w.bytes(&layers.into_bytes());
+    w.bytes(&slices.into_bytes());
+    w.bytes(&keys.into_bytes());
+    for string in &strings.list {
+        w.bytes(string.as_bytes());
+    }
+    Ok(w.into_bytes())
+}
+
+/// Reads a baked sheet into a [`SpritesheetData`].
+pub fn load(bytes: &[u8]) -> Result<SpritesheetData, Error> {
+    Ok(BakedSheet::parse(bytes)?.to_sheet())
+}
+
+fn dword(bytes: &[u8], at: usize) -> u32 {
+    let mut array = [0; 4];
+    array.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(array)
+}
+
+fn rect(bytes: &[u8], at: usize) -> Rect {
+    Rect {
+        x: dword(bytes, at),
+        y: dword(bytes, at + 4),
+        w: dword(bytes, at + 8),
+        h: dword(bytes, at + 12),
+    }
+}
+
+fn color(bytes: &[u8], at: usize) -> Color {
+    Color {
+        r: bytes[at],
+        g: bytes[at + 1],
+        b: bytes[at + 2],
+        a: bytes[at + 3],
+    }
+}
+
+/// Frame of a [`BakedSheet`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct BakedFrame<'a> {
+    /// Name of the frame.
+    pub filename: &'a str,
+    /// Position, size and duration.
+    pub data: FrameData,
+}
+
+/// Tag of a [`BakedSheet`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub struct BakedTag<'a> {
+    /// Tag name.
+    pub name: &'a str,
+    /// Frame start number.
+    pub from: u32,
+    /// Frame end number.
+    pub to: u32,
+    /// Animation direction.
+    pub direction: Direction,
+    /// How many times the animation plays, `None` for forever.
+    pub repeat: Option<u32>,
+    /// Color with which the tag is displayed in Aseprite.
+    pub color: Option<Color>,
+    /// Custom data.
+    pub data: Option<&'a str>,
+}
+
+/// Slice of a [`BakedSheet`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct BakedSlice<'a> {
+    /// Slice name.
+    pub name: &'a str,
+    /// Color with which the slice is displayed in Aseprite.
+    pub color: Color,
+    /// Custom data.
+    pub data: Option<&'a str>,
+    sheet: BakedSheet<'a>,
+    first_key: usize,
+    keys: usize,
+}
+
+impl<'a> BakedSlice<'a> {
+    /// Keys of the slice.
+    pub fn keys(&self) -> impl Iterator<Item = SliceKey> + 'a {
+        let sheet = self.sheet;
+        (self.first_key..self.first_key + self.keys).map(move |i| sheet.key(i))
+    }
+
+    /// Key in effect at `frame`, like [`Slice::key_at`].
+    pub fn key_at(&self, frame: u32) -> Option<SliceKey> {
+        self.keys()
+            .filter(|key| key.frame <= frame)
+            .max_by_key(|key| key.frame)
+    }
+}
+
+/// Baked sheet read in place, without copying its strings.
+///
+/// Every record is checked once by [`parse`](Self::parse), so looking
+/// things up afterwards can't fail.
+#[derive(Debug, Clone, Copy)]
+pub struct BakedSheet<'a> {
+    bytes: &'a [u8],
+    strings: usize,
+    frames: usize,
+    tags: usize,
+    layers: usize,
+    slices: usize,
+    keys: usize,
+}
+
+impl<'a> BakedSheet<'a> {
+    /// Checks `bytes` and reads its header.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(Error::format("not a baked sheet"));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(Error::format(format!(
+                "baked sheet version {} isn't supported, only {}",
+                version, VERSION
+            )));
+        }
+        let count = |at| dword(bytes, at) as usize;
+        let sheet = Self {
+            bytes,
+            strings: count(8),
+            frames: count(12),
+            tags: count(16),
+            layers: count(20),
+            slices: count(24),
+            keys: count(28),
+        };
+        let len = [
+            (sheet.strings as u64 + 1) * 4,
+            sheet.frames as u64 * FRAME_LEN as u64,
+            sheet.tags as u64 * (TAG_LEN as u64 + 4),
+            sheet.layers as u64 * LAYER_LEN as u64,
+            sheet.slices as u64 * SLICE_LEN as u64,
+            sheet.keys as u64 * KEY_LEN as u64,
+        ]
+        .iter()
+        .sum::<u64>()
+            + HEADER_LEN as u64;
+        if (bytes.len() as u64) < len {
+            return Err(Error::format(format!(
+                "baked sheet of {} bytes is cut short, its records alone take {}",
+                bytes.len(),
+                len
+            )));
+        }
+        let strings_len = bytes.len() - sheet.strings_at();
+        let mut start = dword(bytes, sheet.offsets_at());
+        for i in 0..sheet.strings {
+            let end = dword(bytes, sheet.offsets_at() + 4 * (i + 1));
+            if start > end || end as usize > strings_len {
+                return Err(Error::format(format!("string {} is out of bounds", i)));
+            }
+            start = end;
+        }
+
+        sheet.check_string(dword(bytes, 32), false)?;
+        sheet.check_string(dword(bytes, 36), false)?;
+        sheet.check_string(dword(bytes, 40), false)?;
+        sheet.check_string(dword(bytes, 44), false)?;
+        sheet.check_string(dword(bytes, 48), true)?;
+        for i in 0..sheet.frames {
+            sheet.check_string(dword(bytes, sheet.frames_at() + i * FRAME_LEN), false)?;
+        }
+        let mut last_name = None;
+        for i in 0..sheet.tags {
+            let at = sheet.tags_at() + i * TAG_LEN;
+            sheet.check_string(dword(bytes, at), false)?;
+            sheet.check_string(dword(bytes, at + 24), true)?;
+            if bytes[at + 12] > 3 {
+                return Err(Error::format(format!(
+                    "tag {} has unknown direction {}",
+                    i,
+                    bytes[at + 12]
+                )));
+            }
+            let index = dword(bytes, sheet.tag_index_at() + i * 4) as usize;
+            if index >= sheet.tags {
+                return Err(Error::format(format!(
+                    "tag index names missing tag {}",
+                    index
+                )));
+            }
+            let name = sheet.string(dword(bytes, sheet.tags_at() + index * TAG_LEN));
+            if last_name.is_some_and(|last| last > name) {
+                return Err(Error::format("tag index isn't sorted by name"));
+            }
+            last_name = Some(name);
+        }
+        for i in 0..sheet.layers {
+            let at = sheet.layers_at() + i * LAYER_LEN;
+            sheet.check_string(dword(bytes, at), false)?;
+            sheet.check_string(dword(bytes, at + 4), true)?;
+            sheet.check_string(dword(bytes, at + 20), true)?;
+            if bytes[at + 12] as usize >= BLEND_MODES.len() {
+                return Err(Error::format(format!(
+                    "layer {} has unknown blend mode {}",
+                    i,
+                    bytes[at + 12]
+                )));
+            }
+        }
+        for i in 0..sheet.slices {
+            let at = sheet.slices_at() + i * SLICE_LEN;
+            sheet.check_string(dword(bytes, at), false)?;
+            sheet.check_string(dword(bytes, at + 16), true)?;
+            let first = dword(bytes, at + 8) as u64;
+            if first + dword(bytes, at + 12) as u64 > sheet.keys as u64 {
+                return Err(Error::format(format!("slice {} has keys out of bounds", i)));
+            }
+        }
+        Ok(sheet)
+    }
+
+    fn offsets_at(&self) -> usize {
+        HEADER_LEN
+    }
+
+    fn frames_at(&self) -> usize {
+        self.offsets_at() + (self.strings + 1) * 4
+    }
+
+    fn tags_at(&self) -> usize {
+        self.frames_at() + self.frames * FRAME_LEN
+    }
+
+    fn tag_index_at(&self) -> usize {
+        self.tags_at() + self.tags * TAG_LEN
+    }
+
+    fn layers_at(&self) -> usize {
+        self.tag_index_at() + self.tags * 4
+    }
+
+    fn slices_at(&self) -> usize {
+        self.layers_at() + self.layers * LAYER_LEN
+    }
+
+    fn keys_at(&self) -> usize {
+        self.slices_at() + self.slices * SLICE_LEN
+    }
+
+    fn strings_at(&self) -> usize {
+        self.keys_at() + self.keys * KEY_LEN
+    }
+
+    fn check_string(&self, index: u32, optional: bool) -> Result<(), Error> {
+        if index == NONE && optional {
+            return Ok(());
+        }
+        if index as usize >= self.strings {
+            return Err(Error::format(format!("string {} doesn't exist", index)));
+        }
+        let (start, end) = self.string_range(index as usize);
+        std::str::from_utf8(&self.bytes[start..end])
+            .map_err(|_| Error::format(format!("string {} is not utf-8", index)))?;
+        Ok(())
+    }
+
+    fn string_range(&self, index: usize) -> (usize, usize) {
+        let at = self.offsets_at() + index * 4;
+        let start = self.strings_at() + dword(self.bytes, at) as usize;
+        (
+            start,
+            self.strings_at() + dword(self.bytes, at + 4) as usize,
+        )
+    }
+
+    fn string(&self, index: u32) -> &'a str {
+        let (start, end) = self.string_range(index as usize);
+        std::str::from_utf8(&self.bytes[start..end]).expect("checked when parsed")
+    }
+
+    fn optional_string(&self, index: u32) -> Option<&'a str> {
+        (index != NONE).then(|| self.string(index))
+    }
+
+    /// Pixel size of the sheet's image.
+    pub fn size(&self) -> Dimensions {
+        Dimensions {
+            w: dword(self.bytes, 52),
+            h: dword(self.bytes, 56),
+        }
+    }
+
+    /// Relative path to the sheet's image.
+    pub fn image(&self) -> Option<&'a str> {
+        self.optional_string(dword(self.bytes, 48))
+    }
+
+    /// Number of frames.
+    pub fn frame_count(&self) -> usize {
+        self.frames
+    }
+
+    /// Frame at `index`.
+    pub fn frame(&self, index: usize) -> Option<BakedFrame<'a>> {
+        if index >= self.frames {
+            return None;
+        }
+        let (bytes, at) = (self.bytes, self.frames_at() + index * FRAME_LEN);
+        let flags = dword(bytes, at + 48);
+        Some(BakedFrame {
+            filename: self.string(dword(bytes, at)),
+            data: FrameData {
+                frame: rect(bytes, at + 4),
+                rotated: flags & FRAME_ROTATED != 0,
+                trimmed: flags & FRAME_TRIMMED != 0,
+                sprite_source_size: rect(bytes, at + 20),
+                source_size: Dimensions {
+                    w: dword(bytes, at + 36),
+                    h: dword(bytes, at + 40),
+                },
+                duration: dword(bytes, at + 44),
+            },
+        })
+    }
+
+    /// Every frame, in order.
+    pub fn frames(&self) -> impl Iterator<Item = BakedFrame<'a>> + '_ {
+        (0..self.frames).filter_map(|i| self.frame(i))
+    }
+
+    /// Number of tags.
+    pub fn tag_count(&self) -> usize {
+        self.tags
+    }
+
+    /// Tag at `index`, in the order of the sheet.
+    pub fn tag_at(&self, index: usize) -> Option<BakedTag<'a>> {
+        if index >= self.tags {
+            return None;
+        }
+        let (bytes, at) = (self.bytes, self.tags_at() + index * TAG_LEN);
+        let flags = bytes[at + 13];
+        Some(BakedTag {
+            name: self.string(dword(bytes, at)),
+            from: dword(bytes, at + 4),
+            to: dword(bytes, at + 8),
+            direction: match bytes[at + 12] {
+                0 => Direction::Forward,
+                1 => Direction::Reverse,
+                2 => Direction::Pingpong,
+                _ => Direction::PingpongReverse,
+            },
+            repeat: (flags & TAG_REPEAT != 0).then(|| dword(bytes, at + 16)),
+            color: (flags & TAG_COLOR != 0).then(|| color(bytes, at + 20)),
+            data: self.optional_string(dword(bytes, at + 24)),
+        })
+    }
+
+    /// Finds a tag by name, the first one if several share it.
+    pub fn tag(&self, name: &str) -> Option<BakedTag<'a>> {
+        let index_at = |i: usize| dword(self.bytes, self.tag_index_at() + i * 4) as usize;
+        let name_at = |i: usize| self.string(dword(self.bytes, self.tags_at() + i * TAG_LEN));
+        // Lower bound of `name` in the index.
+        let (mut low, mut high) = (0, self.tags);
+        while low < high {
+            let mid = (low + high) / 2;
+            if name_at(index_at(mid)) < name {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (low < self.tags && name_at(index_at(low)) == name)
+            .then(|| self.tag_at(index_at(low)))
+            .flatten()
+    }
+
+    /// Every tag, in the order of the sheet.
+    pub fn tags(&self) -> impl Iterator<Item = BakedTag<'a>> + '_ {
+        (0..self.tags).filter_map(|i| self.tag_at(i))
+    }
+
+    /// Every slice, in the order of the sheet.
+    pub fn slices(&self) -> impl Iterator<Item = BakedSlice<'a>> + '_ {
+        (0..self.slices).map(|i| {
+            let (bytes, at) = (self.bytes, self.slices_at() + i * SLICE_LEN);
+            BakedSlice {
+                name: self.string(dword(bytes, at)),
+                color: color(bytes, at + 4),
+                data: self.optional_string(dword(bytes, at + 16)),
+                sheet: *self,
+                first_key: dword(bytes, at + 8) as usize,
+                keys: dword(bytes, at + 12) as usize,
+            }
+        })
+    }
+
+    /// Finds a slice by name.
+    pub fn slice(&self, name: &str) -> Option<BakedSlice<'a>> {
+        self.slices().find(|slice| slice.name == name)
+    }
+
+    fn key(&self, index: usize) -> SliceKey {
+        let (bytes, at) = (self.bytes, self.keys_at() + index * KEY_LEN);
+        let flags = dword(bytes, at + 44);
+        SliceKey {
+            frame: dword(bytes, at),
+            bounds: rect(bytes, at + 4),
+            pivot: (flags & KEY_PIVOT != 0).then(|| Point {
+                x: dword(bytes, at + 20),
+                y: dword(bytes, at + 24),
+            }),
+            center: (flags & KEY_CENTER != 0).then(|| rect(bytes, at + 28)),
+        }
+    }
+
+    fn layer(&self, index: usize) -> Layer {
+        let (bytes, at) = (self.bytes, self.layers_at() + index * LAYER_LEN);
+        let flags = bytes[at + 13];
+        Layer {
+            name: self.string(dword(bytes, at)).to_string(),
+            group: self
+                .optional_string(dword(bytes, at + 4))
+                .map(str::to_string),
+            opacity: (flags & LAYER_OPACITY != 0).then(|| dword(bytes, at + 8)),
+            blend_mode: (flags & LAYER_BLEND_MODE != 0)
+                .then(|| BLEND_MODES[bytes[at + 12] as usize]),
+            color: (flags & LAYER_COLOR != 0).then(|| color(bytes, at + 16)),
+            data: self
+                .optional_string(dword(bytes, at + 20))
+                .map(str::to_string),
+        }
+    }
+
+    /// Copies everything into a [`SpritesheetData`].
+    pub fn to_sheet(&self) -> SpritesheetData {
+        let string = |at| self.string(dword(self.bytes, at)).to_string();
+        SpritesheetData {
+            frames: self
+                .frames()
+                .map(|frame| Frame {
+                    filename: frame.filename.to_string(),
+                    data: frame.data,
+                })
+                .collect(),
+            meta: Metadata {
+                app: string(32),
+                version: string(36),
+                format: string(40),
+                size: self.size(),
+                scale: string(44),
+                image: self.image().map(str::to_string),
+                frame_tags: self
+                    .tags()
+                    .map(|tag| Frametag {
+                        name: tag.name.to_string(),
+                        from: tag.from,
+                        to: tag.to,
+                        direction: tag.direction,
+                        repeat: tag.repeat,
+                        color: tag.color,
+                        data: tag.data.map(str::to_string),
+                    })
+                    .collect(),
+                layers: (0..self.layers).map(|i| self.layer(i)).collect(),
+                slices: self
+                    .slices()
+                    .map(|slice| Slice {
+                        name: slice.name.to_string(),
+                        color: slice.color,
+                        keys: slice.keys().collect(),
+                        data: slice.data.map(str::to_string),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheets() -> Vec<SpritesheetData> {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        [
+            file.hash_json,
+            file.array_json,
+            include_bytes!("../testdata/export/sheet.json"),
+            include_bytes!("../testdata/codegen/slime.json"),
+        ]
+        .iter()
+        .map(|json| serde_json::from_slice(json).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        for sheet in sheets() {
+            let bytes = bake(&sheet).unwrap();
+            assert_eq!(sheet, load(&bytes).unwrap());
+        }
+        let sheet: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let bytes = bake(&sheet).unwrap();
+        assert!(bytes.len() < file.array_json.len());
+
+        let baked = BakedSheet::parse(&bytes).unwrap();
+        assert_eq!(sheet.frames.len(), baked.frame_count());
+        assert_eq!(sheet.frames[4].filename, baked.frame(4).unwrap().filename);
+        assert_eq!(sheet.frames[4].data, baked.frame(4).unwrap().data);
+        assert_eq!(None, baked.frame(9));
+        for tag in &sheet.meta.frame_tags {
+            let baked = baked.tag(&tag.name).unwrap();
+            assert_eq!((tag.from, tag.to), (baked.from, baked.to));
+        }
+        assert!(baked.tag("missing").is_none());
+        let slices: Vec<_> = baked.slices().map(|slice| slice.name).collect();
+        let names: Vec<_> = sheet.meta.slices.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, slices);
+    }
+
+    #[test]
+    fn test_views() {
+        let sheets = sheets();
+        let bytes = bake(&sheets[3]).unwrap();
+        let baked = BakedSheet::parse(&bytes).unwrap();
+        assert_eq!(Some("slime.png"), baked.image());
+        assert_eq!(Some(5), baked.tag("2x").map(|tag| tag.from));
+        let hitbox = baked.slice("hitbox").unwrap();
+        assert_eq!(2, hitbox.keys().count());
+        assert_eq!(Some(4), hitbox.key_at(6).map(|key| key.frame));
+    }
+
+    #[test]
+    fn test_bad_data() {
+        let bytes = bake(&sheets()[2]).unwrap();
+        assert!(BakedSheet::parse(&bytes[..bytes.len() - 1]).is_err());
+        assert!(BakedSheet::parse(&bytes[..HEADER_LEN + 4]).is_err());
+        assert!(BakedSheet::parse(b"ASPB").is_err());
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert!(BakedSheet::parse(&newer).is_err());
+        let mut broken = bytes.clone();
+        let at = HEADER_LEN + 8;
+        broken[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(BakedSheet::parse(&broken).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! The `export` and `convert` commands.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aseprite::ase::AseFile;
+use aseprite::sprite_sheet::{SheetOptions, SheetType, SpriteSheet};
+use aseprite::{baked, export, import, SpritesheetData};
+use clap::{Args, ValueEnum};
+
+/// `--sheet-type` values.
+#[derive(Clone, Copy, ValueEnum)]
+enum SheetTypeArg {
+    Horizontal,
+    Vertical,
+    Rows,
+    Columns,
+    Packed,
+}
+
+/// Layouts of Aseprite's own JSON.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DataFormat {
+    JsonHash,
+    JsonArray,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// .ase file to export.
+    file: PathBuf,
+    /// Where the sheet image goes. `{title}` and `{tag}` are filled in.
+    #[arg(long)]
+    sheet: Option<PathBuf>,
+    /// Where the JSON data goes, printed if left out. `{title}` and
+    /// `{tag}` are filled in.
+    #[arg(long)]
+    data: Option<PathBuf>,
+    /// How frames are laid out.
+    #[arg(long, value_enum, default_value = "horizontal")]
+    sheet_type: SheetTypeArg,
+    /// Columns of a `rows` sheet.
+    #[arg(long)]
+    sheet_columns: Option<u32>,
+    /// Rows of a `columns` sheet.
+    #[arg(long)]
+    sheet_rows: Option<u32>,
+    /// Layout of the JSON data.
+    #[arg(long, value_enum, default_value = "json-hash")]
+    format: DataFormat,
+    /// Only export this layer, which can be given more than once.
+    #[arg(long)]
+    layer: Vec<String>,
+    /// Leave out this layer, which can be given more than once.
+    #[arg(long)]
+    ignore_layer: Vec<String>,
+    /// Only export the frames of this tag.
+    #[arg(long)]
+    tag: Option<String>,
+    /// Give every layer its own frames.
+    #[arg(long)]
+    split_layers: bool,
+    /// Give every tag its own frames, or its own sheet when `{tag}` is in
+    /// the file names.
+    #[arg(long)]
+    split_tags: bool,
+    /// Cut transparent borders off each frame.
+    #[arg(long)]
+    trim: bool,
+    /// Repeat the edges of each frame a pixel outwards.
+    #[arg(long)]
+    extrude: bool,
+    /// Pixels around the whole sheet.
+    #[arg(long, default_value_t = 0)]
+    border_padding: u32,
+    /// Pixels between frames.
+    #[arg(long, default_value_t = 0)]
+    shape_padding: u32,
+    /// Pixels around each frame, inside its rectangle.
+    #[arg(long, default_value_t = 0)]
+    inner_padding: u32,
+    /// Frame names, such as `{title} ({layer}) {frame}.{extension}`.
+    #[arg(long)]
+    filename_format: Option<String>,
+    /// Put the tags in the data.
+    #[arg(long)]
+    list_tags: bool,
+    /// Put the layers in the data.
+    #[arg(long)]
+    list_layers: bool,
+    /// Put the slices in the data.
+    #[arg(long)]
+    list_slices: bool,
+}
+
+fn fill_path(path: &Path, title: &str, tag: &str) -> PathBuf {
+    let path = path.to_string_lossy();
+    PathBuf::from(path.replace("{title}", title).replace("{tag}", tag))
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Tags to export a sheet for, `None` meaning the whole file. A file
+/// without tags is exported once even when splitting them.
+fn exported_tags(file: &AseFile, split: bool, tag: &Option<String>) -> Vec<Option<String>> {
+    match (split, tag) {
+        (true, None) if !file.tags.is_empty() => {
+            file.tags.iter().map(|tag| Some(tag.name.clone())).collect()
+        }
+        _ => vec![tag.clone()],
+    }
+}
+
+pub fn export(args: ExportArgs) -> Result<String, String> {
+    let bytes = fs::read(&args.file).map_err(|e| format!("{}: {}", args.file.display(), e))?;
+    let file = AseFile::from_bytes(&bytes).map_err(|e| e.to_string())?;
+    let name = args
+        .file
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let title = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(title, _)| title);
+
+    let mut options = SheetOptions::default();
+    options.sheet_type = match args.sheet_type {
+        SheetTypeArg::Horizontal => SheetType::Horizontal,
+        SheetTypeArg::Vertical => SheetType::Vertical,
+        SheetTypeArg::Rows => SheetType::Rows,
+        SheetTypeArg::Columns => SheetType::Columns,
+        SheetTypeArg::Packed => SheetType::Packed,
+    };
+    options.columns = args.sheet_columns;
+    options.rows = args.sheet_rows;
+    options.layers = args.layer;
+    options.ignore_layers = args.ignore_layer;
+    options.tag = args.tag;
+    options.split_layers = args.split_layers;
+    options.split_tags = args.split_tags;
+    options.trim = args.trim;
+    options.extrude = args.extrude;
+    options.border_padding = args.border_padding;
+    options.shape_padding = args.shape_padding;
+    options.inner_padding = args.inner_padding;
+    options.filename_format = args.filename_format;
+    options.list_tags = args.list_tags;
+    options.list_layers = args.list_layers;
+    options.list_slices = args.list_slices;
+
+    // A sheet for each tag if the file names tell them apart.
+    let per_tag = [&args.sheet, &args.data].iter().any(|path| {
+        path.as_ref()
+            .is_some_and(|p| p.to_string_lossy().contains("{tag}"))
+    });
+    let tags = exported_tags(&file, args.split_tags && per_tag, &options.tag);
+
+    let mut printed = String::new();
+    for tag in tags {
+        let mut options = options.clone();
+        options.tag = tag.clone();
+        let mut sheet = SpriteSheet::from_ase(&file, &name, &options).map_err(|e| e.to_string())?;
+        let tag = tag.as_deref().unwrap_or("");
+        if let Some(path) = &args.sheet {
+            let path = fill_path(path, title, tag);
+            let mut png = Vec::new();
+            sheet.image.write_png(&mut png).map_err(|e| e.to_string())?;
+            write(&path, &png)?;
+            sheet.data.meta.image = Some(path.display().to_string());
+        }
+        let json = match args.format {
+            DataFormat::JsonHash => export::aseprite_hash(&sheet.data),
+            DataFormat::JsonArray => export::aseprite_array(&sheet.data),
+        } + "\n";
+        match &args.data {
+            Some(path) => write(&fill_path(path, title, tag), json.as_bytes())?,
+            None => printed += &json,
+        }
+    }
+    Ok(printed)
+}
+
+/// Manifests `convert` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Aseprite's JSON, as a hash or array.
+    Aseprite,
+    TexturePacker,
+    Sparrow,
+    Libgdx,
+}
+
+/// Manifests `convert` writes.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    JsonHash,
+    JsonArray,
+    TexturePacker,
+    Phaser,
+    Libgdx,
+    Sparrow,
+    Godot,
+    Css,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Manifest to convert.
+    input: PathBuf,
+    /// Format of the input, guessed from its contents if left out.
+    #[arg(long, value_enum)]
+    from: Option<InputFormat>,
+    /// Format to write.
+    #[arg(long, value_enum)]
+    to: OutputFormat,
+    /// Where the output goes, printed if left out.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Texture path for Godot, `res://` and the sheet image by default.
+    #[arg(long)]
+    texture: Option<String>,
+    /// Start of the CSS class names.
+    #[arg(long, default_value = "sprite-")]
+    class_prefix: String,
+}
+
+fn guess_format(text: &str) -> InputFormat {
+    let text = text.trim_start();
+    if text.starts_with('<') {
+        InputFormat::Sparrow
+    } else if !text.starts_with('{') {
+        InputFormat::Libgdx
+    } else if serde_json::from_str::<SpritesheetData>(text).is_ok() {
+        InputFormat::Aseprite
+    } else {
+        InputFormat::TexturePacker
+    }
+}
+
+pub fn convert(args: ConvertArgs) -> Result<String, String> {
+    let text =
+        fs::read_to_string(&args.input).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let from = args.from.unwrap_or_else(|| guess_format(&text));
+    let sheets = match from {
+        InputFormat::Aseprite => {
+            vec![serde_json::from_str(&text).map_err(|e| e.to_string())?]
+        }
+        InputFormat::TexturePacker => {
+            vec![import::texture_packer(text.as_bytes()).map_err(|e| e.to_string())?]
+        }
+        InputFormat::Sparrow => vec![import::sparrow(&text).map_err(|e| e.to_string())?],
+        InputFormat::Libgdx => import::libgdx(&text).map_err(|e| e.to_string())?,
+    };
+
+    let single = || match &sheets[..] {
+        [sheet] => Ok(sheet),
+        sheets => Err(format!(
+            "{} has {} pages, which only phaser can hold",
+            args.input.display(),
+            sheets.len()
+        )),
+    };
+    let output = match args.to {
+        OutputFormat::Phaser => export::phaser_multiatlas(&sheets.iter().collect::<Vec<_>>()),
+        OutputFormat::JsonHash => export::aseprite_hash(single()?),
+        OutputFormat::JsonArray => export::aseprite_array(single()?),
+        OutputFormat::TexturePacker => export::texture_packer(single()?),
+        OutputFormat::Libgdx => export::libgdx(single()?),
+        OutputFormat::Sparrow => export::sparrow(single()?),
+        OutputFormat::Godot => {
+            let sheet = single()?;
+            let texture = args
+                .texture
+                .clone()
+                .unwrap_or_else(|| format!("res://{}", sheet.meta.image.as_deref().unwrap_or("")));
+            export::godot_sprite_frames(sheet, &texture)
+        }
+        OutputFormat::Css => export::css(single()?, &args.class_prefix),
+    };
+    let output = match output.ends_with('\n') {
+        true => output,
+        false => output + "\n",
+    };
+    match &args.output {
+        Some(path) => {
+            write(path, output.as_bytes())?;
+            Ok(String::new())
+        }
+        None => Ok(output),
+    }
+}
+
+#[derive(Args)]
+pub struct BakeArgs {
+    /// Aseprite JSON sheet to bake.
+    input: PathBuf,
+    /// Where the baked sheet goes.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn bake(args: BakeArgs) -> Result<String, String> {
+    let text =
+        fs::read_to_string(&args.input).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let sheet: SpritesheetData =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let bytes = baked::bake(&sheet).map_err(|e| e.to_string())?;
+    write(&args.output, &bytes)?;
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_format() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let sheet: SpritesheetData = serde_json::from_slice(file.hash_json).unwrap();
+        assert_eq!(
+            InputFormat::Aseprite,
+            guess_format(std::str::from_utf8(file.hash_json).unwrap())
+        );
+        assert_eq!(
+            InputFormat::TexturePacker,
+            guess_format(&export::texture_packer(&sheet))
+        );
+        assert_eq!(InputFormat::Sparrow, guess_format(&export::sparrow(&sheet)));
+        assert_eq!(InputFormat::Libgdx, guess_format(&export::libgdx(&sheet)));
+    }
+
+    #[test]
+    fn test_exported_tags() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        assert_eq!(ase.tags.len(), exported_tags(&ase, true, &None).len());
+        let mut untagged = ase;
+        untagged.tags.clear();
+        assert_eq!(vec![None], exported_tags(&untagged, true, &None));
+    }
+
+    #[test]
+    fn test_convert_pages() {
+        let input =
+            std::env::temp_dir().join(format!("aseprite-pages-{}.atlas", std::process::id()));
+        fs::write(
+            &input,
+            "a.png\nsize:8,8\nrun\nbounds:0,0,8,8\n\nb.png\nsize:8,8\nidle\nbounds:0,0,8,8\n",
+        )
+        .unwrap();
+        let args = |to| ConvertArgs {
+            input: input.clone(),
+            from: None,
+            to,
+            output: None,
+            texture: None,
+            class_prefix: "sprite-".to_string(),
+        };
+        let phaser = convert(args(OutputFormat::Phaser));
+        let css = convert(args(OutputFormat::Css));
+        fs::remove_file(&input).unwrap();
+        assert!(phaser.unwrap().contains("b.png"));
+        assert!(css.unwrap_err().contains("2 pages"));
+    }
+}
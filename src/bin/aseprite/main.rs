@@ -0,0 +1,390 @@
+//! Command-line tool for looking at Aseprite's JSON exports and .ase files.
+
+mod export;
+mod preview;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use aseprite::ase::{AseFile, ColorMode};
+use aseprite::validate::{self, Diagnostic, Severity};
+use aseprite::{Dimensions, Direction, Frametag, Slice, SpritesheetData};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(name = "aseprite", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show frames, tags, layers, slices and the color format.
+    Info {
+        /// JSON sheet or .ase file.
+        file: PathBuf,
+        /// Print JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check files for mistakes, failing if there are errors.
+    Validate {
+        /// JSON sheets or .ase files.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Print the diagnostics as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// List tags.
+    Tags {
+        /// JSON sheet or .ase file.
+        file: PathBuf,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export an .ase file as a sprite sheet, with Aseprite's options.
+    Export(export::ExportArgs),
+    /// Convert sheet data between Aseprite's JSON and other manifests.
+    Convert(export::ConvertArgs),
+    /// Bake a JSON sheet into the compact binary format.
+    Bake(export::BakeArgs),
+    /// Draw a frame in the terminal, or play a tag.
+    Preview(preview::PreviewArgs),
+    /// List slices, with a row for each key.
+    Slices {
+        /// JSON sheet or .ase file.
+        file: PathBuf,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// What the commands show of either kind of file.
+#[derive(Serialize)]
+struct Summary {
+    kind: &'static str,
+    app: Option<String>,
+    version: Option<String>,
+    format: String,
+    size: Dimensions,
+    frames: usize,
+    duration: u64,
+    tags: Vec<Frametag>,
+    layers: Vec<String>,
+    slices: Vec<Slice>,
+}
+
+/// Either kind of file, as loaded.
+enum Input {
+    Sheet(Box<SpritesheetData>),
+    Ase(Box<AseFile>),
+}
+
+impl Input {
+    fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        // .ase files have their magic number after the file size.
+        if bytes.get(4..6) == Some(&[0xe0, 0xa5]) {
+            let file = AseFile::from_bytes(&bytes).map_err(|e| e.to_string())?;
+            Ok(Input::Ase(Box::new(file)))
+        } else {
+            let sheet = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+            Ok(Input::Sheet(Box::new(sheet)))
+        }
+    }
+
+    fn summary(self) -> Summary {
+        match self {
+            Input::Sheet(sheet) => {
+                let sheet = *sheet;
+                Summary {
+                    kind: "sheet",
+                    app: Some(sheet.meta.app),
+                    version: Some(sheet.meta.version),
+                    format: sheet.meta.format,
+                    size: sheet.meta.size,
+                    frames: sheet.frames.len(),
+                    duration: sheet.frames.iter().map(|f| f.duration as u64).sum(),
+                    tags: sheet.meta.frame_tags,
+                    layers: sheet.meta.layers.into_iter().map(|l| l.name).collect(),
+                    slices: sheet.meta.slices,
+                }
+            }
+            Input::Ase(file) => {
+                let file = *file;
+                let format = match file.header.color_mode() {
+                    ColorMode::Rgba => "rgba",
+                    ColorMode::Grayscale => "grayscale",
+                    ColorMode::Indexed => "indexed",
+                    _ => "unknown",
+                };
+                Summary {
+                    kind: "ase",
+                    app: None,
+                    version: None,
+                    format: format!("{} ({} bpp)", format, file.header.color_depth),
+                    size: Dimensions {
+                        w: file.header.width as u32,
+                        h: file.header.height as u32,
+                    },
+                    frames: file.frames.len(),
+                    duration: file.frames.iter().map(|f| f.duration as u64).sum(),
+                    tags: file.tags,
+                    layers: file.layers.into_iter().map(|l| l.name).collect(),
+                    slices: file.slices,
+                }
+            }
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Input::Sheet(sheet) => validate::sheet(sheet),
+            Input::Ase(file) => validate::ase(file),
+        }
+    }
+}
+
+fn direction(direction: Direction) -> String {
+    serde_json::to_value(direction)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Lines up `rows` under `headers`, padding all but the last column.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut text = String::new();
+    let headers = headers.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+            line.push_str(cell);
+            let padding = width - cell.chars().count();
+            line.extend(std::iter::repeat_n(' ', padding));
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    let names: Vec<_> = names.map(String::as_str).collect();
+    match names.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", names.join(", ")),
+    }
+}
+
+fn info(summary: &Summary) -> String {
+    let mut text = String::new();
+    if let (Some(app), Some(version)) = (&summary.app, &summary.version) {
+        text += &format!("app: {} {}\n", app, version);
+    }
+    text += &format!("format: {}\n", summary.format);
+    text += &format!("size: {}x{}\n", summary.size.w, summary.size.h);
+    text += &format!(
+        "frames: {} lasting {} ms\n",
+        summary.frames, summary.duration
+    );
+    text += &format!(
+        "tags: {}{}\n",
+        summary.tags.len(),
+        names(summary.tags.iter().map(|t| &t.name))
+    );
+    text += &format!(
+        "layers: {}{}\n",
+        summary.layers.len(),
+        names(summary.layers.iter())
+    );
+    text += &format!(
+        "slices: {}{}\n",
+        summary.slices.len(),
+        names(summary.slices.iter().map(|s| &s.name))
+    );
+    text
+}
+
+fn tags(tags: &[Frametag]) -> String {
+    let rows: Vec<_> = tags
+        .iter()
+        .map(|tag| {
+            vec![
+                tag.name.clone(),
+                tag.from.to_string(),
+                tag.to.to_string(),
+                direction(tag.direction),
+                tag.repeat.map_or("forever".to_string(), |r| r.to_string()),
+            ]
+        })
+        .collect();
+    table(&["name", "from", "to", "direction", "repeat"], &rows)
+}
+
+fn slices(slices: &[Slice]) -> String {
+    let rows: Vec<_> = slices
+        .iter()
+        .flat_map(|slice| {
+            slice.keys.iter().map(|key| {
+                let b = key.bounds;
+                vec![
+                    slice.name.clone(),
+                    key.frame.to_string(),
+                    format!("{},{} {}x{}", b.x, b.y, b.w, b.h),
+                    key.pivot
+                        .map_or(String::new(), |p| format!("{},{}", p.x, p.y)),
+                    key.center.map_or(String::new(), |c| {
+                        format!("{},{} {}x{}", c.x, c.y, c.w, c.h)
+                    }),
+                ]
+            })
+        })
+        .collect();
+    table(&["name", "frame", "bounds", "pivot", "center"], &rows)
+}
+
+/// Diagnostic of a file, as `validate --json` prints it.
+#[derive(Serialize)]
+struct FileDiagnostic {
+    file: String,
+    severity: Severity,
+    code: &'static str,
+    message: String,
+}
+
+impl FileDiagnostic {
+    fn new(path: &Path, diagnostic: Diagnostic) -> Self {
+        Self {
+            file: path.display().to_string(),
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message: diagnostic.message,
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).expect("Serializing to a string can't fail") + "\n"
+}
+
+fn run(command: Command) -> Result<ExitCode, String> {
+    let (output, code) = match command {
+        Command::Info { file, json } => {
+            let summary = Input::load(&file)?.summary();
+            match json {
+                true => (to_json(&summary), ExitCode::SUCCESS),
+                false => (info(&summary), ExitCode::SUCCESS),
+            }
+        }
+        Command::Tags { file, json } => {
+            let summary = Input::load(&file)?.summary();
+            match json {
+                true => (to_json(&summary.tags), ExitCode::SUCCESS),
+                false => (tags(&summary.tags), ExitCode::SUCCESS),
+            }
+        }
+        Command::Slices { file, json } => {
+            let summary = Input::load(&file)?.summary();
+            match json {
+                true => (to_json(&summary.slices), ExitCode::SUCCESS),
+                false => (slices(&summary.slices), ExitCode::SUCCESS),
+            }
+        }
+        Command::Export(args) => (export::export(args)?, ExitCode::SUCCESS),
+        Command::Convert(args) => (export::convert(args)?, ExitCode::SUCCESS),
+        Command::Bake(args) => (export::bake(args)?, ExitCode::SUCCESS),
+        Command::Preview(args) => (preview::preview(args)?, ExitCode::SUCCESS),
+        Command::Validate { files, json } => {
+            let mut found = Vec::new();
+            for path in files {
+                match Input::load(&path) {
+                    Ok(input) => found.extend(
+                        input
+                            .diagnostics()
+                            .into_iter()
+                            .map(|d| FileDiagnostic::new(&path, d)),
+                    ),
+                    Err(message) => found.push(FileDiagnostic {
+                        file: path.display().to_string(),
+                        severity: Severity::Error,
+                        code: "unreadable",
+                        message,
+                    }),
+                }
+            }
+            let failed = found.iter().any(|d| d.severity == Severity::Error);
+            let output = match json {
+                true => to_json(&found),
+                false => found
+                    .iter()
+                    .map(|d| {
+                        let severity = match d.severity {
+                            Severity::Error => "error",
+                            _ => "warning",
+                        };
+                        format!("{}: {}[{}]: {}\n", d.file, severity, d.code, d.message)
+                    })
+                    .collect(),
+            };
+            match failed {
+                true => (output, ExitCode::FAILURE),
+                false => (output, ExitCode::SUCCESS),
+            }
+        }
+    };
+    print!("{}", output);
+    Ok(code)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let sheet: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        let summary = Input::Sheet(Box::new(sheet)).summary();
+        let ase = Input::Ase(Box::new(ase)).summary();
+        assert_eq!(tags(&summary.tags), tags(&ase.tags));
+        assert_eq!(
+            "name       from  to  direction  repeat
+start      0     2   forward    forever
+forward    0     1   forward    forever
+ping-pong  2     3   pingpong   forever
+reverse    4     5   reverse    forever
+end        6     8   forward    forever
+red        6     7   forward    forever
+",
+            tags(&summary.tags)
+        );
+        assert!(info(&summary).contains("frames: 9 lasting"));
+    }
+}
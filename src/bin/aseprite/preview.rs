@@ -0,0 +1,202 @@
+//! The `preview` command.
+
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use aseprite::animation::{tag_playback, tag_sequence};
+use aseprite::image::RgbaImage;
+use aseprite::terminal::{self, TerminalOptions};
+use aseprite::{Frametag, Slice};
+use clap::Args;
+
+use crate::Input;
+
+#[derive(Args)]
+pub struct PreviewArgs {
+    /// JSON sheet or .ase file. A sheet's image is found through its
+    /// `meta.image`.
+    file: PathBuf,
+    /// Play this tag with its frame durations, instead of showing one
+    /// frame.
+    #[arg(long, conflicts_with = "frame")]
+    tag: Option<String>,
+    /// Frame to show.
+    #[arg(long, default_value_t = 0)]
+    frame: u32,
+    /// How many characters wide each pixel is.
+    #[arg(long, default_value_t = 1)]
+    scale: u32,
+    /// Draw a line around the bounds of every slice.
+    #[arg(long)]
+    slices: bool,
+    /// How many times a looping tag plays, forever if left out. Tags
+    /// with a repeat count play that many times.
+    #[arg(long)]
+    loops: Option<u32>,
+}
+
+/// Frames of either kind of file, drawn as images.
+struct Frames {
+    images: Vec<RgbaImage>,
+    durations: Vec<u32>,
+    tags: Vec<Frametag>,
+    slices: Vec<Slice>,
+}
+
+impl Frames {
+    /// Frames of `input`, with a sheet's image looked for in `dir`.
+    fn new(input: Input, dir: &Path) -> Result<Self, String> {
+        match input {
+            Input::Ase(file) => Ok(Self {
+                images: (0..file.frames.len())
+                    .map(|f| file.render_frame(f))
+                    .collect(),
+                durations: file.frames.iter().map(|f| f.duration as u32).collect(),
+                tags: file.tags,
+                slices: file.slices,
+            }),
+            Input::Sheet(sheet) => {
+                let image = sheet
+                    .meta
+                    .image
+                    .as_deref()
+                    .ok_or("the sheet doesn't name its image")?;
+                let image = dir.join(image);
+                let file =
+                    fs::File::open(&image).map_err(|e| format!("{}: {}", image.display(), e))?;
+                let image = RgbaImage::read_png(file).map_err(|e| e.to_string())?;
+                let images = (0..sheet.frames.len())
+                    .map(|f| sheet.extract_frame(&image, f))
+                    .collect::<Option<_>>()
+                    .ok_or("a frame is outside the sheet image")?;
+                let sheet = *sheet;
+                Ok(Self {
+                    images,
+                    durations: sheet.frames.iter().map(|f| f.duration).collect(),
+                    tags: sheet.meta.frame_tags,
+                    slices: sheet.meta.slices,
+                })
+            }
+        }
+    }
+
+    /// Frame `frame` for the terminal, with slice bounds if `slices`.
+    fn render(&self, frame: u32, scale: u32, slices: bool) -> Result<String, String> {
+        let image = self.images.get(frame as usize).ok_or_else(|| {
+            format!(
+                "frame {} is out of range, there are {}",
+                frame,
+                self.images.len()
+            )
+        })?;
+        let mut options = TerminalOptions::default();
+        options.scale = scale;
+        if slices {
+            options.outlines = self
+                .slices
+                .iter()
+                .filter_map(|slice| slice.key_at(frame))
+                .map(|key| key.bounds)
+                .collect();
+        }
+        Ok(terminal::render(image, &options))
+    }
+}
+
+/// Frames `tag` shows, in order, once through unless it repeats.
+fn playback(tag: &Frametag, loops: Option<u32>, frames: usize) -> Result<Vec<u32>, String> {
+    let last = tag.from.max(tag.to);
+    if last as usize >= frames {
+        return Err(format!(
+            "tag {:?} runs to frame {}, there are {}",
+            tag.name, last, frames
+        ));
+    }
+    Ok(match (tag_playback(tag), loops) {
+        (Some(sequence), _) => sequence,
+        (None, Some(loops)) => tag_sequence(tag).repeat(loops as usize),
+        (None, None) => tag_sequence(tag),
+    })
+}
+
+pub fn preview(args: PreviewArgs) -> Result<String, String> {
+    let dir = args.file.parent().unwrap_or(Path::new(""));
+    let frames = Frames::new(Input::load(&args.file)?, dir)?;
+    let Some(name) = &args.tag else {
+        return frames.render(args.frame, args.scale, args.slices);
+    };
+    let tag = frames
+        .tags
+        .iter()
+        .find(|tag| &tag.name == name)
+        .ok_or_else(|| format!("no tag called {:?}", name))?;
+    let sequence = playback(tag, args.loops, frames.images.len())?;
+    if sequence.is_empty() {
+        return Ok(String::new());
+    }
+    let forever = tag.repeat.is_none() && args.loops.is_none();
+
+    // Draws over the last frame on a terminal, and one after the other
+    // in a log, where it plays only once.
+    let terminal = std::io::stdout().is_terminal();
+    let mut rendered = vec![None; frames.images.len()];
+    let mut stdout = std::io::stdout().lock();
+    let mut lines = 0;
+    loop {
+        for &frame in &sequence {
+            let text = match &mut rendered[frame as usize] {
+                Some(text) => text,
+                slot => slot.insert(frames.render(frame, args.scale, args.slices)?),
+            };
+            if terminal && lines > 0 {
+                let _ = write!(stdout, "\x1b[{}A", lines);
+            }
+            let _ = write!(stdout, "{}", text);
+            let _ = stdout.flush();
+            lines = text.lines().count();
+            let duration = frames.durations[frame as usize];
+            thread::sleep(Duration::from_millis(duration as u64));
+        }
+        if !(forever && terminal) {
+            return Ok(String::new());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aseprite::ase::AseFile;
+
+    #[test]
+    fn test_frames() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        let frames = Frames::new(Input::Ase(Box::new(ase)), Path::new("")).unwrap();
+        assert_eq!(9, frames.images.len());
+        let text = frames.render(1, 2, true).unwrap();
+        assert_eq!(frames.images[1].height as usize, text.lines().count());
+        assert!(frames.render(9, 1, false).is_err());
+
+        let sheet = serde_json::from_slice(file.array_json).unwrap();
+        let missing = Frames::new(Input::Sheet(Box::new(sheet)), Path::new("missing"));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_playback() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        let mut tag = ase.tags[0].clone();
+        tag.from = 1;
+        tag.to = 2;
+        assert_eq!(vec![1, 2, 1, 2], playback(&tag, Some(2), 3).unwrap());
+        assert!(playback(&tag, None, 2).is_err());
+        assert!(playback(&tag, Some(0), 3).unwrap().is_empty());
+        tag.from = 5;
+        assert!(playback(&tag, None, 3).is_err());
+    }
+}
@@ -0,0 +1,593 @@
+//! Compiling sprites into `OUT_DIR` from a build script.
+//!
+//! Needs the `png` and `json` features. A `build.rs` like this exports
+//! every `.ase` file under `assets` and copies the sheets already
+//! exported there:
+//!
+//! ```no_run
+//! let options = aseprite::build::BuildOptions::default();
+//! aseprite::build::compile("assets", &options).unwrap();
+//! ```
+//!
+//! `assets/hero/walk.ase` becomes `walk.json` and `walk.png` in
+//! `$OUT_DIR/hero`, ready for `include_bytes!`. Sheets whose inputs and
+//! options haven't changed since the last build are left as they are.
+//!
+//! Setting [`BuildOptions::rust_module`] also writes an enum for the
+//! sprites and their tags, slices and layers, see [`codegen`](crate::codegen).
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ase::AseFile;
+use crate::atlas::{self, AtlasOptions};
+use crate::image::RgbaImage;
+use crate::sprite_sheet::{SheetOptions, SheetType, SpriteSheet};
+use crate::{codegen, export};
+use crate::{Error, SpritesheetData};
+
+/// What turns `.ase` files into sheets.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub enum Exporter {
+    /// [`SpriteSheet::from_ase`].
+    #[default]
+    Native,
+    /// Aseprite itself, run as `program -b`, with `args` before the file.
+    ///
+    /// The sheet options are passed on as Aseprite's own.
+    Command {
+        /// Path to the Aseprite executable.
+        program: PathBuf,
+        /// More arguments, such as `--scale 2`.
+        args: Vec<String>,
+    },
+}
+
+/// How assets are compiled.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct BuildOptions {
+    /// Where the sheets go, `OUT_DIR` when `None`.
+    pub out_dir: Option<PathBuf>,
+    /// What exports `.ase` files.
+    pub exporter: Exporter,
+    /// How `.ase` files are exported.
+    pub sheet: SheetOptions,
+    /// Packs every sheet into `atlas-0.png`, `atlas-1.png` and so on at the
+    /// top of the output, rather than giving each its own image.
+    pub atlas: Option<AtlasOptions>,
+    /// File in the output directory for [`codegen::rust_module`] of every
+    /// sheet, such as `sprites.rs`. Tags, layers and slices of `.ase` files
+    /// are only there if `sheet` lists them.
+    pub rust_module: Option<PathBuf>,
+}
+
+/// What a build did.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct BuildReport {
+    /// Sources that were exported or copied again.
+    pub built: Vec<PathBuf>,
+    /// Sources left alone because they hadn't changed.
+    pub cached: Vec<PathBuf>,
+    /// Every file written or kept, JSON, images and Rust.
+    pub outputs: Vec<PathBuf>,
+}
+
+/// Stable 64-bit FNV-1a hash, so caches outlive toolchain updates.
+struct Fingerprint(u64);
+
+impl Fingerprint {
+    fn new(options: &BuildOptions) -> Self {
+        let mut fingerprint = Fingerprint(0xcbf2_9ce4_8422_2325);
+        fingerprint.add(env!("CARGO_PKG_VERSION").as_bytes());
+        let options = BuildOptions {
+            out_dir: None,
+            ..options.clone()
+        };
+        fingerprint.add(format!("{:?}", options).as_bytes());
+        fingerprint
+    }
+
+    fn add(&mut self, bytes: &[u8]) {
+        for &byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("{:016x}\n", self.0)
+    }
+}
+
+/// `.ase` or JSON file to compile.
+struct Source {
+    path: PathBuf,
+    /// Path below the asset directory, without the extension.
+    stem: PathBuf,
+    ase: bool,
+}
+
+fn find_sources(dir: &Path, root: &Path, sources: &mut Vec<Source>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_sources(&path, root, sources)?;
+            continue;
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let ase = match extension.to_ascii_lowercase().as_str() {
+            "ase" | "aseprite" => true,
+            "json" => false,
+            _ => continue,
+        };
+        let stem = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+        sources.push(Source { path, stem, ase });
+    }
+    Ok(())
+}
+
+/// Drops JSON sheets with the same stem as an `.ase` file, which are
+/// taken to be its export, and fails on other sources that would be
+/// written to the same place.
+fn dedupe_sources(sources: Vec<Source>) -> Result<Vec<Source>, Error> {
+    let ase_stems: HashSet<PathBuf> = sources
+        .iter()
+        .filter(|source| source.ase)
+        .map(|source| source.stem.clone())
+        .collect();
+    let mut stems = HashMap::new();
+    let mut kept = Vec::new();
+    for source in sources {
+        if !source.ase && ase_stems.contains(&source.stem) {
+            continue;
+        }
+        if let Some(other) = stems.insert(source.stem.clone(), source.path.clone()) {
+            return Err(Error::format(format!(
+                "{} and {} would both be compiled to {}",
+                other.display(),
+                source.path.display(),
+                source.stem.display()
+            )));
+        }
+        kept.push(source);
+    }
+    Ok(kept)
+}
+
+/// Aseprite's command-line options for `options`.
+pub fn aseprite_args(options: &SheetOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut flag = |on: bool, name: &str| {
+        if on {
+            args.push(name.to_string());
+        }
+    };
+    flag(options.split_layers, "--split-layers");
+    flag(options.split_tags, "--split-tags");
+    flag(options.trim, "--trim");
+    flag(options.extrude, "--extrude");
+    flag(options.list_tags, "--list-tags");
+    flag(options.list_layers, "--list-layers");
+    flag(options.list_slices, "--list-slices");
+    let mut value = |name: &str, value: String| {
+        args.push(name.to_string());
+        args.push(value);
+    };
+    let sheet_type = match options.sheet_type {
+        SheetType::Horizontal => "horizontal",
+        SheetType::Vertical => "vertical",
+        SheetType::Rows => "rows",
+        SheetType::Columns => "columns",
+        SheetType::Packed => "packed",
+    };
+    value("--sheet-type", sheet_type.to_string());
+    if let Some(columns) = options.columns {
+        value("--sheet-columns", columns.to_string());
+    }
+    if let Some(rows) = options.rows {
+        value("--sheet-rows", rows.to_string());
+    }
+    for layer in &options.layers {
+        value("--layer", layer.clone());
+    }
+    for layer in &options.ignore_layers {
+        value("--ignore-layer", layer.clone());
+    }
+    if let Some(tag) = &options.tag {
+        value("--tag", tag.clone());
+    }
+    for (name, padding) in [
+        ("--border-padding", options.border_padding),
+        ("--shape-padding", options.shape_padding),
+        ("--inner-padding", options.inner_padding),
+    ] {
+        if padding > 0 {
+            value(name, padding.to_string());
+        }
+    }
+    if let Some(format) = &options.filename_format {
+        value("--filename-format", format.clone());
+    }
+    args
+}
+
+fn read_png(path: &Path) -> Result<RgbaImage, Error> {
+    RgbaImage::read_png(fs::File::open(path)?)
+}
+
+fn write_png(path: &Path, image: &RgbaImage) -> Result<(), Error> {
+    let mut png = Vec::new();
+    image.write_png(&mut png)?;
+    Ok(fs::write(path, png)?)
+}
+
+/// Sheet of `source`, along with its image.
+fn load(
+    source: &Source,
+    options: &BuildOptions,
+    scratch: &Path,
+) -> Result<(SpritesheetData, RgbaImage), Error> {
+    if !source.ase {
+        let data: SpritesheetData = serde_json::from_slice(&fs::read(&source.path)?)
+            .map_err(|e| Error::format(format!("{}: {}", source.path.display(), e)))?;
+        let image = sheet_image(&source.path, &data)?;
+        return Ok((data, read_png(&image)?));
+    }
+    let name = source
+        .path
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    match &options.exporter {
+        Exporter::Native => {
+            let file = AseFile::from_bytes(&fs::read(&source.path)?)?;
+            let sheet = SpriteSheet::from_ase(&file, &name, &options.sheet)?;
+            Ok((sheet.data, sheet.image))
+        }
+        Exporter::Command { program, args } => {
+            fs::create_dir_all(scratch)?;
+            let (png, json) = (scratch.join("sheet.png"), scratch.join("sheet.json"));
+            let status = Command::new(program)
+                .arg("-b")
+                .args(args)
+                .args(aseprite_args(&options.sheet))
+                .arg(&source.path)
+                .arg("--format")
+                .arg("json-array")
+                .arg("--sheet")
+                .arg(&png)
+                .arg("--data")
+                .arg(&json)
+                .status()?;
+            if !status.success() {
+                return Err(Error::format(format!(
+                    "{} failed to export {}: {}",
+                    program.display(),
+                    source.path.display(),
+                    status
+                )));
+            }
+            let data = serde_json::from_slice(&fs::read(&json)?)
+                .map_err(|e| Error::format(format!("{}: {}", json.display(), e)))?;
+            Ok((data, read_png(&png)?))
+        }
+    }
+}
+
+/// Image a JSON sheet refers to, relative to the JSON.
+fn sheet_image(json: &Path, data: &SpritesheetData) -> Result<PathBuf, Error> {
+    let image = data
+        .meta
+        .image
+        .as_deref()
+        .ok_or_else(|| Error::format(format!("{} names no image", json.display())))?;
+    Ok(json.parent().unwrap_or(Path::new("")).join(image))
+}
+
+/// Hashes what `source` is built from.
+fn add_source(fingerprint: &mut Fingerprint, source: &Source) -> Result<(), Error> {
+    let bytes = fs::read(&source.path)?;
+    fingerprint.add(source.stem.to_string_lossy().as_bytes());
+    fingerprint.add(&bytes);
+    if !source.ase {
+        // The image is an input too; broken JSON fails when it's loaded.
+        if let Ok(data) = serde_json::from_slice::<SpritesheetData>(&bytes) {
+            fingerprint.add(&fs::read(sheet_image(&source.path, &data)?)?);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `outputs` were written from the same inputs as `fingerprint`.
+fn is_cached(stamp: &Path, fingerprint: &Fingerprint, outputs: &[PathBuf]) -> bool {
+    outputs.iter().all(|output| output.is_file())
+        && fs::read_to_string(stamp).is_ok_and(|text| text == fingerprint.text())
+}
+
+/// Writes the Rust module for the sheets of `sources`, unless it's the
+/// same as before.
+fn write_rust_module(
+    path: &Path,
+    sources: &[Source],
+    json_of: impl Fn(&Source) -> PathBuf,
+    report: &mut BuildReport,
+) -> Result<(), Error> {
+    let mut sheets = Vec::new();
+    for source in sources {
+        let json = json_of(source);
+        let data: SpritesheetData = serde_json::from_slice(&fs::read(&json)?)
+            .map_err(|e| Error::format(format!("{}: {}", json.display(), e)))?;
+        let name: Vec<_> = source
+            .stem
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        sheets.push((name.join("_"), data, json));
+    }
+    let sprites: Vec<_> = sheets
+        .iter()
+        .map(|(name, data, json)| (name.as_str(), data, json.as_path()))
+        .collect();
+    let code = codegen::rust_module(&sprites);
+    if fs::read_to_string(path).map_or(true, |old| old != code) {
+        fs::write(path, code)?;
+    }
+    report.outputs.push(path.to_path_buf());
+    Ok(())
+}
+
+/// Exports or copies every `.ase` and JSON sheet under `assets` into the
+/// output directory, keeping the directory structure.
+///
+/// Prints `cargo:rerun-if-changed` for the directory and every source.
+/// JSON sheets are expected to name their image relative to themselves.
+/// A JSON sheet next to an `.ase` file of the same name is taken to be
+/// its export and skipped, and other sources with the same name but
+/// different extensions are an error.
+pub fn compile(assets: impl AsRef<Path>, options: &BuildOptions) -> Result<BuildReport, Error> {
+    let assets = assets.as_ref();
+    let out_dir = match &options.out_dir {
+        Some(dir) => dir.clone(),
+        None => std::env::var_os("OUT_DIR")
+            .map(PathBuf::from)
+            .ok_or_else(|| Error::format("OUT_DIR isn't set, so out_dir has to be"))?,
+    };
+    let mut sources = Vec::new();
+    find_sources(assets, assets, &mut sources)?;
+    let sources = dedupe_sources(sources)?;
+    println!("cargo:rerun-if-changed={}", assets.display());
+    for source in &sources {
+        println!("cargo:rerun-if-changed={}", source.path.display());
+    }
+
+    let scratch = out_dir.join(".aseprite-export");
+    let mut report = BuildReport::default();
+    let json_of = |source: &Source| out_dir.join(&source.stem).with_extension("json");
+    match &options.atlas {
+        None => {
+            for source in &sources {
+                let (json, png) = (
+                    json_of(source),
+                    out_dir.join(&source.stem).with_extension("png"),
+                );
+                let stamp = json.with_extension("json.hash");
+                let mut fingerprint = Fingerprint::new(options);
+                add_source(&mut fingerprint, source)?;
+                let outputs = vec![json.clone(), png.clone()];
+                report.outputs.extend(outputs.iter().cloned());
+                if is_cached(&stamp, &fingerprint, &outputs) {
+                    report.cached.push(source.path.clone());
+                    continue;
+                }
+                let (mut data, image) = load(source, options, &scratch)?;
+                if let Some(dir) = json.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                write_png(&png, &image)?;
+                data.meta.image = png.file_name().map(|n| n.to_string_lossy().into_owned());
+                fs::write(&json, export::aseprite_array(&data))?;
+                fs::write(&stamp, fingerprint.text())?;
+                report.built.push(source.path.clone());
+            }
+        }
+        Some(atlas_options) => {
+            let mut fingerprint = Fingerprint::new(options);
+            for source in &sources {
+                add_source(&mut fingerprint, source)?;
+            }
+            let stamp = out_dir.join("atlas.hash");
+            // The page count is only known after packing.
+            let pages = fs::read_to_string(out_dir.join("atlas.pages"))
+                .ok()
+                .and_then(|text| text.trim().parse::<usize>().ok());
+            let page_path = |page: usize| out_dir.join(format!("atlas-{}.png", page));
+            let mut outputs: Vec<PathBuf> = sources.iter().map(json_of).collect();
+            if let Some(pages) = pages {
+                outputs.extend((0..pages).map(page_path));
+                if is_cached(&stamp, &fingerprint, &outputs) {
+                    report.cached = sources.iter().map(|s| s.path.clone()).collect();
+                    report.outputs = outputs;
+                    if let Some(module) = &options.rust_module {
+                        write_rust_module(&out_dir.join(module), &sources, json_of, &mut report)?;
+                    }
+                    return Ok(report);
+                }
+            }
+
+            let sheets = sources
+                .iter()
+                .map(|source| load(source, options, &scratch))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let refs: Vec<_> = sheets.iter().map(|(data, image)| (data, image)).collect();
+            let atlas = atlas::pack(&refs, atlas_options)?;
+            fs::create_dir_all(&out_dir)?;
+            let mut outputs = Vec::new();
+            for (source, packed) in sources.iter().zip(&atlas.sheets) {
+                let json = json_of(source);
+                let mut data = packed.data.clone();
+                // The page, relative to the JSON.
+                let depth = source.stem.components().count() - 1;
+                let page = format!("{}atlas-{}.png", "../".repeat(depth), packed.page);
+                data.meta.image = Some(page);
+                if let Some(dir) = json.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&json, export::aseprite_array(&data))?;
+                outputs.push(json);
+            }
+            for (i, page) in atlas.pages.iter().enumerate() {
+                write_png(&page_path(i), page)?;
+                outputs.push(page_path(i));
+            }
+            fs::write(out_dir.join("atlas.pages"), atlas.pages.len().to_string())?;
+            fs::write(&stamp, fingerprint.text())?;
+            report.built = sources.iter().map(|s| s.path.clone()).collect();
+            report.outputs = outputs;
+        }
+    }
+    if let Some(module) = &options.rust_module {
+        fs::create_dir_all(&out_dir)?;
+        write_rust_module(&out_dir.join(module), &sources, json_of, &mut report)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh directory with an .ase file and an exported sheet in it.
+    fn assets(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("aseprite-build-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        fs::create_dir_all(dir.join("assets/ui")).unwrap();
+        fs::write(dir.join("assets/complex.aseprite"), file.src_aseprite).unwrap();
+        fs::write(dir.join("assets/ui/sheet.json"), file.array_json).unwrap();
+        let data: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let image = data.meta.image.unwrap();
+        fs::write(dir.join("assets/ui").join(image), file.array_png).unwrap();
+        fs::write(dir.join("assets/notes.txt"), "not a sprite").unwrap();
+        dir
+    }
+
+    fn sheet(path: &Path) -> SpritesheetData {
+        serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_compile() {
+        let dir = assets("compile");
+        let mut options = BuildOptions {
+            out_dir: Some(dir.join("out")),
+            rust_module: Some(PathBuf::from("sprites.rs")),
+            ..BuildOptions::default()
+        };
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!(2, report.built.len());
+        assert_eq!(5, report.outputs.len());
+        let code = fs::read_to_string(dir.join("out/sprites.rs")).unwrap();
+        assert!(code.contains("    Complex,\n    /// `ui_sheet`\n    UiSheet,\n"));
+        assert!(code.contains("UiSheetTag::PingPong => 2..=3,"));
+        let exported = sheet(&dir.join("out/complex.json"));
+        assert_eq!(Some("complex.png"), exported.meta.image.as_deref());
+        assert_eq!(
+            sheet(&dir.join("assets/ui/sheet.json")).frames,
+            sheet(&dir.join("out/ui/sheet.json")).frames
+        );
+
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!((0, 2), (report.built.len(), report.cached.len()));
+        fs::write(dir.join("assets/ui/sheet.json"), b"{").unwrap();
+        assert!(compile(dir.join("assets"), &options).is_err());
+
+        fs::remove_file(dir.join("assets/ui/sheet.json")).unwrap();
+        options.sheet.trim = true;
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!(1, report.built.len());
+
+        // A sheet exported next to its .ase file is left to the .ase file.
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        fs::write(dir.join("assets/complex.json"), file.array_json).unwrap();
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!((0, 1), (report.built.len(), report.cached.len()));
+        let code = fs::read_to_string(dir.join("out/sprites.rs")).unwrap();
+        assert!(!code.contains("Complex2"));
+        fs::write(dir.join("assets/complex.ase"), b"").unwrap();
+        let error = compile(dir.join("assets"), &options).unwrap_err();
+        assert!(error.to_string().contains("would both be compiled to"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_atlas() {
+        let dir = assets("atlas");
+        let options = BuildOptions {
+            out_dir: Some(dir.join("out")),
+            atlas: Some(AtlasOptions::default()),
+            ..BuildOptions::default()
+        };
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!(2, report.built.len());
+        let ui = sheet(&dir.join("out/ui/sheet.json"));
+        assert_eq!(Some("../atlas-0.png"), ui.meta.image.as_deref());
+        let page = read_png(&dir.join("out/atlas-0.png")).unwrap();
+        let expected = sheet(&dir.join("assets/ui/sheet.json"));
+        let image =
+            RgbaImage::read_png(aseprite_test_data::FileSet::complex_1_2_25().array_png).unwrap();
+        for i in 0..ui.frames.len() {
+            assert_eq!(
+                expected.extract_frame(&image, i),
+                ui.extract_frame(&page, i)
+            );
+        }
+        let report = compile(dir.join("assets"), &options).unwrap();
+        assert_eq!(2, report.cached.len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_aseprite_args() {
+        let options = SheetOptions {
+            sheet_type: SheetType::Packed,
+            trim: true,
+            layers: vec!["body".to_string()],
+            shape_padding: 2,
+            ..SheetOptions::default()
+        };
+        assert_eq!(
+            vec![
+                "--trim",
+                "--sheet-type",
+                "packed",
+                "--layer",
+                "body",
+                "--shape-padding",
+                "2"
+            ],
+            aseprite_args(&options)
+        );
+        let dir = assets("command");
+        let options = BuildOptions {
+            out_dir: Some(dir.join("out")),
+            exporter: Exporter::Command {
+                program: dir.join("no-aseprite-here"),
+                args: Vec::new(),
+            },
+            ..BuildOptions::default()
+        };
+        assert!(matches!(
+            compile(dir.join("assets"), &options),
+            Err(Error::Io(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
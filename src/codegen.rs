@@ -0,0 +1,297 @@
+//! Generating Rust constants for the tags, slices and layers of sprites.
+//!
+//! Names in the generated code come from the sheets, so renaming a tag in
+//! Aseprite stops code using the old name from compiling. Write the code
+//! from a build script, or let the `build` module do it, and
+//! `include!` it:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/sprites.rs"));
+//!
+//! let walk = HeroTag::Walk.frames();
+//! let sheet = SpriteId::Hero.sheet();
+//! ```
+//!
+//! `.ase` files need exporting with their tags, slices and layers listed
+//! first, see [`SheetOptions`](crate::sprite_sheet::SheetOptions). The
+//! generated code reads the sheets as JSON, so it needs the `json`
+//! feature.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::SpritesheetData;
+
+/// `name` as a PascalCase identifier.
+fn pascal_case(name: &str) -> String {
+    let mut ident = String::new();
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            ident.push(first.to_ascii_uppercase());
+            ident.extend(chars);
+        }
+    }
+    match ident.chars().next() {
+        None => "Unnamed".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("N{}", ident),
+        Some(_) if ident == "Self" => "Self_".to_string(),
+        Some(_) => ident,
+    }
+}
+
+/// Identifiers for `names`, made unique, along with the first of each name.
+fn variants<'a>(names: impl Iterator<Item = &'a str>) -> Vec<(String, &'a str)> {
+    let mut seen_names = HashSet::new();
+    let mut seen_idents = HashSet::new();
+    let mut variants = Vec::new();
+    for name in names {
+        if !seen_names.insert(name) {
+            continue;
+        }
+        let base = pascal_case(name);
+        let mut ident = base.clone();
+        let mut n = 2;
+        while !seen_idents.insert(ident.clone()) {
+            ident = format!("{}{}", base, n);
+            n += 1;
+        }
+        variants.push((ident, name));
+    }
+    variants
+}
+
+/// `name` for a doc comment, on one line.
+fn doc(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// An enum of `variants` with `ALL` and `name()`, and the extra methods
+/// `methods` writes.
+fn write_enum(
+    out: &mut String,
+    doc_comment: &str,
+    ident: &str,
+    variants: &[(String, &str)],
+    methods: impl FnOnce(&mut String),
+) {
+    let _ = writeln!(out, "/// {}", doc_comment);
+    let _ = writeln!(out, "#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]");
+    let _ = writeln!(out, "pub enum {} {{", ident);
+    for (variant, name) in variants {
+        let _ = writeln!(out, "    /// `{}`", doc(name));
+        let _ = writeln!(out, "    {},", variant);
+    }
+    let _ = writeln!(out, "}}\n");
+    let _ = writeln!(out, "impl {} {{", ident);
+    let _ = writeln!(out, "    /// Every variant, in the order of the sheet.");
+    let _ = write!(
+        out,
+        "    pub const ALL: [{}; {}] = [",
+        ident,
+        variants.len()
+    );
+    for (i, (variant, _)) in variants.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        let _ = write!(out, "{}{}::{}", separator, ident, variant);
+    }
+    let _ = writeln!(out, "];\n");
+    let _ = writeln!(out, "    /// Name in the sheet.");
+    let _ = writeln!(out, "    pub const fn name(self) -> &'static str {{");
+    let _ = writeln!(out, "        match self {{");
+    for (variant, name) in variants {
+        let _ = writeln!(out, "            {}::{} => {:?},", ident, variant, name);
+    }
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "    }}");
+    methods(out);
+    let _ = writeln!(out, "}}\n");
+}
+
+/// Rust code for `sprites`, each a name, its sheet and the path of the
+/// sheet's JSON for `include_bytes!`.
+///
+/// Gives a `SpriteId` enum with a variant for each sprite, and for a
+/// sprite called `hero`, `HeroTag`, `HeroSlice` and `HeroLayer` enums for
+/// whichever of those the sheet has. Tags know their frames. Names that
+/// aren't identifiers are turned into PascalCase, with a number added to
+/// tell apart ones that end up the same. The code uses the `aseprite`
+/// crate and nothing else.
+pub fn rust_module(sprites: &[(&str, &SpritesheetData, &Path)]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated by aseprite::codegen, don't edit.\n");
+    let ids = variants(sprites.iter().map(|(name, _, _)| *name));
+    write_enum(&mut out, "Sprites.", "SpriteId", &ids, |out| {
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "    /// Sheet of the sprite, parsed the first time it's asked for."
+        );
+        let _ = writeln!(
+            out,
+            "    pub fn sheet(self) -> &'static aseprite::SpritesheetData {{"
+        );
+        let _ = writeln!(
+            out,
+            "        fn parse(json: &[u8]) -> aseprite::SpritesheetData {{"
+        );
+        let _ = writeln!(
+            out,
+            "            aseprite::SpritesheetData::from_json_with_limits(json, &aseprite::Limits::default())"
+        );
+        let _ = writeln!(
+            out,
+            "                .expect(\"generated from a valid sheet\")"
+        );
+        let _ = writeln!(out, "        }}");
+        let _ = writeln!(out, "        match self {{");
+        for ((variant, _), (_, _, path)) in ids.iter().zip(sprites) {
+            let _ = writeln!(out, "            SpriteId::{} => {{", variant);
+            let _ = writeln!(
+                out,
+                "                static SHEET: std::sync::OnceLock<aseprite::SpritesheetData> ="
+            );
+            let _ = writeln!(out, "                    std::sync::OnceLock::new();");
+            let _ = writeln!(
+                out,
+                "                SHEET.get_or_init(|| parse(include_bytes!({:?})))",
+                path.to_string_lossy()
+            );
+            let _ = writeln!(out, "            }}");
+        }
+        let _ = writeln!(out, "        }}");
+        let _ = writeln!(out, "    }}");
+    });
+
+    for ((id, name), (_, sheet, _)) in ids.iter().zip(sprites) {
+        let meta = &sheet.meta;
+        let tags = variants(meta.frame_tags.iter().map(|tag| tag.name.as_str()));
+        if !tags.is_empty() {
+            let ident = format!("{}Tag", id);
+            let comment = format!("Tags of `{}`.", doc(name));
+            write_enum(&mut out, &comment, &ident, &tags, |out| {
+                let _ = writeln!(out);
+                let _ = writeln!(out, "    /// Frames of the tag, first to last.");
+                let _ = writeln!(
+                    out,
+                    "    pub const fn frames(self) -> std::ops::RangeInclusive<u32> {{"
+                );
+                let _ = writeln!(out, "        match self {{");
+                for (variant, name) in &tags {
+                    let tag = sheet.frame_tag(name).expect("variants come from tags");
+                    let _ = writeln!(
+                        out,
+                        "            {}::{} => {}..={},",
+                        ident, variant, tag.from, tag.to
+                    );
+                }
+                let _ = writeln!(out, "        }}");
+                let _ = writeln!(out, "    }}\n");
+                let _ = writeln!(out, "    /// The tag in the sheet.");
+                let _ = writeln!(
+                    out,
+                    "    pub fn tag(self) -> &'static aseprite::Frametag {{"
+                );
+                let _ = writeln!(out, "        SpriteId::{}", id);
+                let _ = writeln!(out, "            .sheet()");
+                let _ = writeln!(out, "            .frame_tag(self.name())");
+                let _ = writeln!(out, "            .expect(\"generated from this sheet\")");
+                let _ = writeln!(out, "    }}");
+            });
+        }
+
+        let slices = variants(meta.slices.iter().map(|slice| slice.name.as_str()));
+        if !slices.is_empty() {
+            let ident = format!("{}Slice", id);
+            let comment = format!("Slices of `{}`.", doc(name));
+            write_enum(&mut out, &comment, &ident, &slices, |out| {
+                let _ = writeln!(out);
+                let _ = writeln!(out, "    /// The slice in the sheet.");
+                let _ = writeln!(out, "    pub fn slice(self) -> &'static aseprite::Slice {{");
+                let _ = writeln!(out, "        SpriteId::{}", id);
+                let _ = writeln!(out, "            .sheet()");
+                let _ = writeln!(out, "            .meta");
+                let _ = writeln!(out, "            .slices");
+                let _ = writeln!(out, "            .iter()");
+                let _ = writeln!(out, "            .find(|slice| slice.name == self.name())");
+                let _ = writeln!(out, "            .expect(\"generated from this sheet\")");
+                let _ = writeln!(out, "    }}");
+            });
+        }
+
+        let layers = variants(meta.layers.iter().map(|layer| layer.name.as_str()));
+        if !layers.is_empty() {
+            let ident = format!("{}Layer", id);
+            let comment = format!("Layers of `{}`.", doc(name));
+            write_enum(&mut out, &comment, &ident, &layers, |_| {});
+        }
+    }
+    out.truncate(out.trim_end().len() + 1);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Direction;
+
+    #[allow(dead_code)]
+    mod generated {
+        include!("../testdata/codegen/sprites.rs");
+    }
+
+    fn sprites() -> Vec<(&'static str, SpritesheetData, &'static Path)> {
+        let sheet = |json: &str| serde_json::from_str(json).unwrap();
+        vec![
+            (
+                "hero",
+                sheet(include_str!("../testdata/export/sheet.json")),
+                Path::new("../export/sheet.json"),
+            ),
+            (
+                "slime",
+                sheet(include_str!("../testdata/codegen/slime.json")),
+                Path::new("slime.json"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!("WalkLeft", pascal_case("walk-left"));
+        assert_eq!("N2x", pascal_case("2x"));
+        assert_eq!("Unnamed", pascal_case("!!"));
+        assert_eq!(
+            vec![
+                ("Run".to_string(), "run"),
+                ("Run2".to_string(), "Run"),
+                ("Run3".to_string(), "run!")
+            ],
+            variants(["run", "Run", "run", "run!"].into_iter())
+        );
+    }
+
+    #[test]
+    fn test_rust_module() {
+        let sprites = sprites();
+        let sprites: Vec<_> = sprites
+            .iter()
+            .map(|(name, sheet, path)| (*name, sheet, *path))
+            .collect();
+        let code = rust_module(&sprites);
+        let expected = include_str!("../testdata/codegen/sprites.rs");
+        assert_eq!(expected, code);
+
+        use generated::*;
+        assert_eq!(1..=3, HeroTag::Run.frames());
+        assert_eq!(sprites[0].1, SpriteId::Hero.sheet());
+        assert_eq!(Direction::Pingpong, HeroTag::Run.tag().direction);
+        assert_eq!(6..=7, SlimeTag::WalkLeft2.frames());
+        assert_eq!("walk-left", SlimeTag::WalkLeft.name());
+        assert_eq!(4, SlimeSlice::Hitbox.slice().keys[1].frame);
+        assert_eq!([SlimeLayer::Body, SlimeLayer::Eyes], SlimeLayer::ALL);
+    }
+}
@@ -0,0 +1,231 @@
+//! Hitboxes and hurtboxes authored as slices.
+//!
+//! Slices are matched by the prefix of their name: `hit:punch` becomes a
+//! [`BoxKind::Hit`] box named `punch`, `hurt:body` a [`BoxKind::Hurt`]
+//! box and any other `prefix:name` a [`BoxKind::Other`] box. Slices
+//! without a prefix are left alone.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{Point, SpritesheetData};
+
+/// Name of the slice that marks the pivot of each frame.
+pub const PIVOT_SLICE: &str = "pivot";
+
+/// Kind of collision box, taken from the slice name prefix.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum BoxKind {
+    /// `hit:` slices, areas that deal damage.
+    Hit,
+    /// `hurt:` slices, areas that receive damage.
+    Hurt,
+    /// Any other prefix.
+    Other(String),
+}
+
+impl BoxKind {
+    fn from_prefix(prefix: &str) -> Self {
+        match prefix {
+            "hit" => BoxKind::Hit,
+            "hurt" => BoxKind::Hurt,
+            other => BoxKind::Other(other.to_owned()),
+        }
+    }
+}
+
+/// Collision box of a single frame.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct CollisionBox {
+    /// Kind of the box.
+    pub kind: BoxKind,
+    /// Slice name without the kind prefix.
+    pub name: String,
+    /// Left edge relative to the pivot.
+    pub x: i32,
+    /// Top edge relative to the pivot.
+    pub y: i32,
+    /// Width.
+    pub w: u32,
+    /// Height.
+    pub h: u32,
+    /// Properties parsed from the slice's custom data.
+    ///
+    /// See [`parse_properties`].
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Pivot of a frame in sprite coordinates.
+///
+/// This is the pivot of the [`PIVOT_SLICE`] slice, or the center of its
+/// bounds when it has no pivot. Without such a slice it is the center of
+/// the frame.
+pub fn frame_pivot(sheet: &SpritesheetData, frame: u32) -> Point {
+    let key = sheet
+        .meta
+        .slices
+        .iter()
+        .find(|slice| slice.name == PIVOT_SLICE)
+        .and_then(|slice| slice.key_at(frame));
+
+    match key {
+        Some(key) => key.anchor(),
+        None => {
+            let size = sheet
+                .frames
+                .get(frame as usize)
+                .or_else(|| sheet.frames.first())
+                .map(|frame| frame.source_size);
+            size.map_or(Point { x: 0, y: 0 }, |size| Point {
+                x: size.w / 2,
+                y: size.h / 2,
+            })
+        }
+    }
+}
+
+/// Collision boxes of a frame, positioned relative to `pivot`.
+///
+/// When `flip_x` is true the boxes are mirrored around the pivot, the
+/// same way the sprite is when drawn flipped horizontally.
+pub fn collision_boxes(
+    sheet: &SpritesheetData,
+    frame: u32,
+    pivot: Point,
+    flip_x: bool,
+) -> Vec<CollisionBox> {
+    sheet
+        .meta
+        .slices
+        .iter()
+        .filter_map(|slice| {
+            let (prefix, name) = slice.name.split_once(':')?;
+            let key = slice.key_at(frame)?;
+            let bounds = key.bounds;
+
+            let left = bounds.x as i32 - pivot.x as i32;
+            let x = if flip_x {
+                -(left + bounds.w as i32)
+            } else {
+                left
+            };
+
+            Some(CollisionBox {
+                kind: BoxKind::from_prefix(prefix),
+                name: name.to_owned(),
+                x,
+                y: bounds.y as i32 - pivot.y as i32,
+                w: bounds.w,
+                h: bounds.h,
+                properties: slice
+                    .data
+                    .as_deref()
+                    .map(parse_properties)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Parses slice custom data into properties.
+///
+/// Entries are separated by commas, semicolons or newlines and written
+/// as `key=value`. A bare `key` gets an empty value.
+pub fn parse_properties(data: &str) -> BTreeMap<String, String> {
+    data.split([',', ';', '\n'])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) => (key.trim().to_owned(), value.trim().to_owned()),
+            None => (entry.to_owned(), String::new()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S: &str = r##"{ "frames": [
+   {
+    "filename": "fighter 0.ase",
+    "frame": { "x": 0, "y": 0, "w": 32, "h": 32 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 32, "h": 32 },
+    "sourceSize": { "w": 32, "h": 32 },
+    "duration": 100
+   },
+   {
+    "filename": "fighter 1.ase",
+    "frame": { "x": 32, "y": 0, "w": 32, "h": 32 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 32, "h": 32 },
+    "sourceSize": { "w": 32, "h": 32 },
+    "duration": 100
+   }
+ ],
+ "meta": {
+  "app": "http://www.aseprite.org/",
+  "version": "1.2.25-x64",
+  "image": "fighter.png",
+  "format": "RGBA8888",
+  "size": { "w": 64, "h": 32 },
+  "scale": "1",
+  "slices": [
+   { "name": "pivot", "color": "#0000ffff", "keys": [
+     { "frame": 0, "bounds": {"x": 14, "y": 30, "w": 4, "h": 2 }, "pivot": {"x": 2, "y": 2 } }
+   ] },
+   { "name": "hurt:body", "color": "#00ff00ff", "keys": [
+     { "frame": 0, "bounds": {"x": 10, "y": 8, "w": 12, "h": 24 } }
+   ] },
+   { "name": "hit:punch", "color": "#ff0000ff", "data": "damage=5, stun", "keys": [
+     { "frame": 1, "bounds": {"x": 22, "y": 12, "w": 8, "h": 4 } }
+   ] },
+   { "name": "decoration", "color": "#ffffffff", "keys": [
+     { "frame": 0, "bounds": {"x": 0, "y": 0, "w": 1, "h": 1 } }
+   ] }
+  ]
+ }
+}
+"##;
+
+    #[test]
+    fn test_collision_boxes() {
+        let sheet: SpritesheetData = serde_json::from_str(S).unwrap();
+        let pivot = frame_pivot(&sheet, 1);
+        assert_eq!(Point { x: 16, y: 32 }, pivot);
+
+        let boxes = collision_boxes(&sheet, 0, pivot, false);
+        assert_eq!(1, boxes.len());
+        assert_eq!(BoxKind::Hurt, boxes[0].kind);
+        assert_eq!("body", boxes[0].name);
+
+        let boxes = collision_boxes(&sheet, 1, pivot, false);
+        assert_eq!(2, boxes.len());
+        let punch = &boxes[1];
+        assert_eq!(BoxKind::Hit, punch.kind);
+        assert_eq!((6, -20, 8, 4), (punch.x, punch.y, punch.w, punch.h));
+        assert_eq!(
+            Some("5"),
+            punch.properties.get("damage").map(|s| s.as_str())
+        );
+        assert_eq!(Some(""), punch.properties.get("stun").map(|s| s.as_str()));
+
+        let flipped = collision_boxes(&sheet, 1, pivot, true);
+        assert_eq!((-14, -20), (flipped[1].x, flipped[1].y));
+        assert_eq!((-6, -24), (flipped[0].x, flipped[0].y));
+    }
+
+    #[test]
+    fn test_frame_pivot_fallback() {
+        let mut sheet: SpritesheetData = serde_json::from_str(S).unwrap();
+        sheet.meta.slices.clear();
+        assert_eq!(Point { x: 16, y: 16 }, frame_pivot(&sheet, 0));
+    }
+}
@@ -0,0 +1,47 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Errors from reading or writing files other than the JSON export.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The data isn't in the expected format.
+    Format(String),
+}
+
+impl Error {
+    #[cfg(feature = "std")]
+    pub(crate) fn format(message: impl Into<String>) -> Self {
+        Error::Format(message.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(e) => write!(fmt, "i/o error: {}", e),
+            Error::Format(message) => write!(fmt, "invalid data: {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Format(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
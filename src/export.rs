@@ -0,0 +1,465 @@
+//! Writing sprite sheets as the atlas manifests of other engines and tools.
+//!
+//! Every writer gives the same text for the same sheet. The image is the
+//! one named by `meta.image`. Engines that can't turn rotated frames
+//! upright get them as they are, so pack sheets with rotated frames into
+//! an [`atlas`](crate::atlas) first.
+
+use std::fmt::Write as _;
+
+#[cfg(feature = "json")]
+use serde::ser::{Serialize, SerializeMap, Serializer};
+#[cfg(feature = "json")]
+use serde_json::{json, Value};
+
+use crate::animation::{tag_playback, tag_sequence};
+use crate::SpritesheetData;
+#[cfg(feature = "json")]
+use crate::{Frame, FrameData, Metadata, Rect};
+
+fn image(sheet: &SpritesheetData) -> &str {
+    sheet.meta.image.as_deref().unwrap_or("")
+}
+
+#[cfg(feature = "json")]
+fn rect_json(rect: Rect) -> Value {
+    json!({ "x": rect.x, "y": rect.y, "w": rect.w, "h": rect.h })
+}
+
+#[cfg(feature = "json")]
+/// Frame fields shared by the JSON formats, which come from TexturePacker.
+fn frame_json(frame: &FrameData) -> Value {
+    json!({
+        "frame": rect_json(frame.frame),
+        "rotated": frame.rotated,
+        "trimmed": frame.trimmed,
+        "spriteSourceSize": rect_json(frame.sprite_source_size),
+        "sourceSize": { "w": frame.source_size.w, "h": frame.source_size.h },
+    })
+}
+
+#[cfg(feature = "json")]
+/// Aseprite's own JSON, with frames in an array.
+pub fn aseprite_array(sheet: &SpritesheetData) -> String {
+    serde_json::to_string_pretty(sheet).expect("sheets are valid JSON")
+}
+
+#[cfg(feature = "json")]
+/// Frames keyed by filename, in the order of the sheet.
+struct FrameMap<'a>(&'a [Frame]);
+
+#[cfg(feature = "json")]
+impl Serialize for FrameMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for frame in self.0 {
+            map.serialize_entry(&frame.filename, &frame.data)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "json")]
+/// Aseprite's own JSON, with frames keyed by filename.
+///
+/// This is what Aseprite writes by default. Frames sharing a filename
+/// collapse into one when read back.
+pub fn aseprite_hash(sheet: &SpritesheetData) -> String {
+    #[derive(serde::Serialize)]
+    struct Hash<'a> {
+        frames: FrameMap<'a>,
+        meta: &'a Metadata,
+    }
+    let hash = Hash {
+        frames: FrameMap(&sheet.frames),
+        meta: &sheet.meta,
+    };
+    serde_json::to_string_pretty(&hash).expect("sheets are valid JSON")
+}
+
+#[cfg(feature = "json")]
+/// TexturePacker's generic JSON (Hash) format.
+///
+/// Frames are keyed by filename, so frames sharing a filename collapse
+/// into one.
+pub fn texture_packer(sheet: &SpritesheetData) -> String {
+    let frames: serde_json::Map<String, Value> = sheet
+        .frames
+        .iter()
+        .map(|frame| {
+            let mut value = frame_json(frame);
+            value["pivot"] = json!({ "x": 0.5, "y": 0.5 });
+            (frame.filename.clone(), value)
+        })
+        .collect();
+    let json = json!({
+        "frames": frames,
+        "meta": {
+            "app": "https://www.codeandweb.com/texturepacker",
+            "version": "1.0",
+            "image": image(sheet),
+            "format": sheet.meta.format,
+            "size": { "w": sheet.meta.size.w, "h": sheet.meta.size.h },
+            "scale": sheet.meta.scale,
+        },
+    });
+    serde_json::to_string_pretty(&json).expect("sheets are valid JSON")
+}
+
+#[cfg(feature = "json")]
+/// Phaser 3 multi-atlas JSON, with a texture for each sheet.
+///
+/// Pass the pages of an [`atlas`](crate::atlas) to load them together.
+pub fn phaser_multiatlas(sheets: &[&SpritesheetData]) -> String {
+    let textures: Vec<Value> = sheets
+        .iter()
+        .map(|sheet| {
+            let frames: Vec<Value> = sheet
+                .frames
+                .iter()
+                .map(|frame| {
+                    let mut value = frame_json(frame);
+                    value["filename"] = json!(frame.filename);
+                    value
+                })
+                .collect();
+            json!({
+                "image": image(sheet),
+                "format": sheet.meta.format,
+                "size": { "w": sheet.meta.size.w, "h": sheet.meta.size.h },
+                "scale": sheet.meta.scale.parse::<f64>().unwrap_or(1.0),
+                "frames": frames,
+            })
+        })
+        .collect();
+    let json = json!({
+        "textures": textures,
+        "meta": {
+            "app": "https://www.aseprite.org/",
+            "version": "3.0",
+        },
+    });
+    serde_json::to_string_pretty(&json).expect("sheets are valid JSON")
+}
+
+/// libGDX `.atlas` text, in the format every libGDX version reads.
+pub fn libgdx(sheet: &SpritesheetData) -> String {
+    let mut out = String::new();
+    let size = sheet.meta.size;
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{}", image(sheet));
+    let _ = writeln!(out, "size: {}, {}", size.w, size.h);
+    let _ = writeln!(out, "format: {}", sheet.meta.format);
+    let _ = writeln!(out, "filter: Nearest, Nearest");
+    let _ = writeln!(out, "repeat: none");
+    for frame in &sheet.frames {
+        let (rect, trimmed, source) = (frame.frame, frame.sprite_source_size, frame.source_size);
+        let _ = writeln!(out, "{}", frame.filename);
+        let _ = writeln!(out, "  rotate: {}", frame.rotated);
+        let _ = writeln!(out, "  xy: {}, {}", rect.x, rect.y);
+        let _ = writeln!(out, "  size: {}, {}", trimmed.w, trimmed.h);
+        let _ = writeln!(out, "  orig: {}, {}", source.w, source.h);
+        // libGDX measures offsets from the bottom.
+        let bottom = source.h.saturating_sub(trimmed.y + trimmed.h);
+        let _ = writeln!(out, "  offset: {}, {}", trimmed.x, bottom);
+        let _ = writeln!(out, "  index: -1");
+    }
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Starling and Sparrow texture atlas XML.
+pub fn sparrow(sheet: &SpritesheetData) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        out,
+        r#"<TextureAtlas imagePath="{}">"#,
+        xml_escape(image(sheet))
+    );
+    for frame in &sheet.frames {
+        let (rect, trimmed, source) = (frame.frame, frame.sprite_source_size, frame.source_size);
+        // The size is the one in the texture, turned for rotated frames.
+        let (w, h) = match frame.rotated {
+            false => (trimmed.w, trimmed.h),
+            true => (trimmed.h, trimmed.w),
+        };
+        let _ = write!(
+            out,
+            r#"  <SubTexture name="{}" x="{}" y="{}" width="{}" height="{}""#,
+            xml_escape(&frame.filename),
+            rect.x,
+            rect.y,
+            w,
+            h
+        );
+        if frame.rotated {
+            let _ = write!(out, r#" rotated="true""#);
+        }
+        if (trimmed.x, trimmed.y, trimmed.w, trimmed.h) != (0, 0, source.w, source.h) {
+            let _ = write!(
+                out,
+                r#" frameX="{}" frameY="{}" frameWidth="{}" frameHeight="{}""#,
+                -(trimmed.x as i64),
+                -(trimmed.y as i64),
+                source.w,
+                source.h
+            );
+        }
+        let _ = writeln!(out, "/>");
+    }
+    let _ = writeln!(out, "</TextureAtlas>");
+    out
+}
+
+fn godot_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Godot 4 `SpriteFrames` resource, with an animation for each tag.
+///
+/// Sheets without tags get a `default` animation playing every frame.
+/// Animations run at the frame rate that makes each frame's relative
+/// duration a whole number, and tags that repeat a number of times are
+/// played out in full without looping. `texture_path` is the path of the
+/// sheet image, usually starting with `res://`.
+pub fn godot_sprite_frames(sheet: &SpritesheetData, texture_path: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "[gd_resource type=\"SpriteFrames\" load_steps={} format=3]\n",
+        sheet.frames.len() + 2
+    );
+    let _ = writeln!(
+        out,
+        "[ext_resource type=\"Texture2D\" path=\"{}\" id=\"1\"]\n",
+        godot_string(texture_path)
+    );
+    for (i, frame) in sheet.frames.iter().enumerate() {
+        let (rect, trimmed, source) = (frame.frame, frame.sprite_source_size, frame.source_size);
+        let _ = writeln!(
+            out,
+            "[sub_resource type=\"AtlasTexture\" id=\"AtlasTexture_{}\"]",
+            i
+        );
+        let _ = writeln!(out, "atlas = ExtResource(\"1\")");
+        let _ = writeln!(
+            out,
+            "region = Rect2({}, {}, {}, {})",
+            rect.x, rect.y, rect.w, rect.h
+        );
+        if (trimmed.x, trimmed.y, trimmed.w, trimmed.h) != (0, 0, source.w, source.h) {
+            let _ = writeln!(
+                out,
+                "margin = Rect2({}, {}, {}, {})",
+                trimmed.x,
+                trimmed.y,
+                source.w.saturating_sub(trimmed.w),
+                source.h.saturating_sub(trimmed.h)
+            );
+        }
+        let _ = writeln!(out);
+    }
+
+    let animations: Vec<(&str, Vec<u32>, bool)> = match sheet.meta.frame_tags.is_empty() {
+        true => vec![("default", (0..sheet.frames.len() as u32).collect(), true)],
+        false => sheet
+            .meta
+            .frame_tags
+            .iter()
+            .map(|tag| match tag_playback(tag) {
+                Some(sequence) => (tag.name.as_str(), sequence, false),
+                None => (tag.name.as_str(), tag_sequence(tag), true),
+            })
+            .collect(),
+    };
+    let duration = |frame: u32| sheet.frames.get(frame as usize).map_or(0, |f| f.duration);
+    let _ = writeln!(out, "[resource]");
+    let _ = write!(out, "animations = [");
+    for (i, (name, sequence, looping)) in animations.iter().enumerate() {
+        let step = sequence
+            .iter()
+            .fold(0, |step, &f| gcd(step, duration(f)))
+            .max(1);
+        let separator = if i == 0 { "" } else { ", " };
+        let _ = write!(out, "{}{{\n\"frames\": [", separator);
+        for (j, &frame) in sequence.iter().enumerate() {
+            let separator = if j == 0 { "" } else { ", " };
+            let _ = write!(
+                out,
+                "{}{{\n\"duration\": {:?},\n\"texture\": SubResource(\"AtlasTexture_{}\")\n}}",
+                separator,
+                (duration(frame) / step) as f64,
+                frame
+            );
+        }
+        let _ = write!(
+            out,
+            "],\n\"loop\": {},\n\"name\": &\"{}\",\n\"speed\": {:?}\n}}",
+            looping,
+            godot_string(name),
+            1000.0 / step as f64
+        );
+    }
+    let _ = writeln!(out, "]");
+    out
+}
+
+fn css_class(name: &str) -> String {
+    name.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect()
+}
+
+fn css_px(value: u32) -> String {
+    match value {
+        0 => "0".to_string(),
+        v => format!("{}px", v),
+    }
+}
+
+/// CSS sprite sheet with a class per frame, named `class_prefix`
+/// followed by the filename with anything but letters, digits, `-` and
+/// `_` replaced by `-`.
+///
+/// Trimmed frames get margins that make up their untrimmed size.
+pub fn css(sheet: &SpritesheetData, class_prefix: &str) -> String {
+    let mut out = String::new();
+    for (i, frame) in sheet.frames.iter().enumerate() {
+        let (rect, trimmed, source) = (frame.frame, frame.sprite_source_size, frame.source_size);
+        if i > 0 {
+            let _ = writeln!(out);
+        }
+        let _ = writeln!(out, ".{}{} {{", class_prefix, css_class(&frame.filename));
+        let _ = writeln!(out, "  width: {};", css_px(trimmed.w));
+        let _ = writeln!(out, "  height: {};", css_px(trimmed.h));
+        let _ = writeln!(
+            out,
+            "  background: url(\"{}\") {} {} no-repeat;",
+            image(sheet).replace('"', "\\\""),
+            match rect.x {
+                0 => "0".to_string(),
+                x => format!("-{}px", x),
+            },
+            match rect.y {
+                0 => "0".to_string(),
+                y => format!("-{}px", y),
+            }
+        );
+        if (trimmed.x, trimmed.y, trimmed.w, trimmed.h) != (0, 0, source.w, source.h) {
+            let _ = writeln!(
+                out,
+                "  margin: {} {} {} {};",
+                css_px(trimmed.y),
+                css_px(source.w.saturating_sub(trimmed.x + trimmed.w)),
+                css_px(source.h.saturating_sub(trimmed.y + trimmed.h)),
+                css_px(trimmed.x)
+            );
+        }
+        let _ = writeln!(out, "}}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet() -> SpritesheetData {
+        serde_json::from_str(include_str!("../testdata/export/sheet.json")).unwrap()
+    }
+
+    /// Compares with the expected output, printing what differs.
+    fn check(expected: &str, actual: &str) {
+        for (i, (e, a)) in expected.lines().zip(actual.lines()).enumerate() {
+            assert_eq!(e, a, "line {}", i + 1);
+        }
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json() {
+        let sheet = sheet();
+        check(
+            include_str!("../testdata/export/texture_packer.json"),
+            &texture_packer(&sheet),
+        );
+        check(
+            include_str!("../testdata/export/phaser.json"),
+            &phaser_multiatlas(&[&sheet, &sheet]),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_aseprite() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let array: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let hash: SpritesheetData = serde_json::from_slice(file.hash_json).unwrap();
+        for sheet in [&array, &hash] {
+            let written = aseprite_hash(sheet);
+            assert!(written.contains("\"complex 0.aseprite\": {"));
+            assert_eq!(hash, serde_json::from_str(&written).unwrap());
+            assert_eq!(array, serde_json::from_str(&aseprite_array(sheet)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        let sheet = sheet();
+        check(
+            include_str!("../testdata/export/sheet.atlas"),
+            &libgdx(&sheet),
+        );
+        check(
+            include_str!("../testdata/export/sheet.xml"),
+            &sparrow(&sheet),
+        );
+        check(
+            include_str!("../testdata/export/sheet.css"),
+            &css(&sheet, "hero-"),
+        );
+    }
+
+    #[test]
+    fn test_godot() {
+        let mut sheet = sheet();
+        check(
+            include_str!("../testdata/export/sheet.tres"),
+            &godot_sprite_frames(&sheet, "res://sprites/hero.png"),
+        );
+        sheet.meta.frame_tags.clear();
+        check(
+            include_str!("../testdata/export/untagged.tres"),
+            &godot_sprite_frames(&sheet, "res://hero.png"),
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Pixel buffers.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[cfg(feature = "png")]
+use crate::Error;
+use crate::{Color, Rect};
+
+/// Image with 8-bit RGBA pixels, stored row by row.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RgbaImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Pixel data, four bytes per pixel.
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Color of the pixel at `(x, y)`, `None` if out of bounds.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.data[i..i + 4];
+        Some(Color {
+            r: p[0],
+            g: p[1],
+            b: p[2],
+            a: p[3],
+        })
+    }
+
+    /// Sets the pixel at `(x, y)`, ignoring coordinates out of bounds.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if x < self.width && y < self.height {
+            let i = (y as usize * self.width as usize + x as usize) * 4;
+            self.data[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    /// Image filled with `color`.
+    pub fn filled(width: u32, height: u32, color: Color) -> Self {
+        let pixel = [color.r, color.g, color.b, color.a];
+        Self {
+            width,
+            height,
+            data: pixel.repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Enlarges the image `factor` times, repeating every pixel.
+    pub fn scale(&self, factor: u32) -> RgbaImage {
+        let factor = factor as usize;
+        let row = self.width as usize * 4;
+        let mut data = Vec::with_capacity(self.data.len() * factor * factor);
+        for line in self.data.chunks_exact(row.max(1)) {
+            let start = data.len();
+            for pixel in line.chunks_exact(4) {
+                for _ in 0..factor {
+                    data.extend_from_slice(pixel);
+                }
+            }
+            for _ in 1..factor {
+                data.extend_from_within(start..start + row * factor);
+            }
+        }
+        RgbaImage {
+            width: self.width * factor as u32,
+            height: self.height * factor as u32,
+            data,
+        }
+    }
+
+    /// Hash of the size and pixels, for finding equal images.
+    pub(crate) fn pixel_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.width, self.height, &self.data).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Smallest rectangle holding every pixel that isn't fully transparent,
+    /// empty at the origin if there's none.
+    pub fn opaque_bounds(&self) -> Rect {
+        let opaque = |x: u32, y: u32| self.pixel(x, y).is_some_and(|c| c.a > 0);
+        let rows: Vec<u32> = (0..self.height)
+            .filter(|&y| (0..self.width).any(|x| opaque(x, y)))
+            .collect();
+        let columns: Vec<u32> = (0..self.width)
+            .filter(|&x| rows.iter().any(|&y| opaque(x, y)))
+            .collect();
+        match (rows.first(), rows.last(), columns.first(), columns.last()) {
+            (Some(&top), Some(&bottom), Some(&left), Some(&right)) => Rect {
+                x: left,
+                y: top,
+                w: right - left + 1,
+                h: bottom - top + 1,
+            },
+            _ => Rect {
+                x: 0,
+                y: 0,
+                w: 0,
+                h: 0,
+            },
+        }
+    }
+
+    /// Copies the pixels inside `rect`, transparent where it leaves the image.
+    pub fn crop(&self, rect: Rect) -> RgbaImage {
+        let mut image = RgbaImage::new(rect.w, rect.h);
+        for y in 0..rect.h {
+            for x in 0..rect.w {
+                if let Some(color) = self.pixel(rect.x.saturating_add(x), rect.y.saturating_add(y))
+                {
+                    image.set_pixel(x, y, color);
+                }
+            }
+        }
+        image
+    }
+
+    /// Copy of the image turned a quarter turn counterclockwise.
+    pub fn rotate_counterclockwise(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.height, self.width);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(color) = self.pixel(x, y) {
+                    image.set_pixel(y, self.width - 1 - x, color);
+                }
+            }
+        }
+        image
+    }
+
+    /// Decodes a PNG image of any color type.
+    #[cfg(feature = "png")]
+    pub fn read_png<R: std::io::Read>(reader: R) -> Result<Self, Error> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| Error::format(format!("png: {}", e)))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| Error::format(format!("png: {}", e)))?;
+        let pixels = &buffer[..info.buffer_size()];
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => pixels.to_vec(),
+            png::ColorType::Rgb => pixels
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => pixels
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => pixels.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(Error::format("png: indexed image wasn't expanded"));
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    /// Encodes the image as an RGBA PNG.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| Error::format(format!("png: {}", e)))?;
+        writer
+            .write_image_data(&self.data)
+            .map_err(|e| Error::format(format!("png: {}", e)))
+    }
+
+    /// Draws `src` over this image with its top left corner at `(x, y)`.
+    ///
+    /// Pixels are blended with normal alpha blending, with the alpha of
+    /// `src` scaled by `opacity`.
+    pub fn draw(&mut self, src: &RgbaImage, x: i32, y: i32, opacity: u8) {
+        for sy in 0..src.height {
+            let dy = y + sy as i32;
+            if dy < 0 || dy >= self.height as i32 {
+                continue;
+            }
+            for sx in 0..src.width {
+                let dx = x + sx as i32;
+                if dx < 0 || dx >= self.width as i32 {
+                    continue;
+                }
+                let (dx, dy) = (dx as u32, dy as u32);
+                let (Some(top), Some(bottom)) = (src.pixel(sx, sy), self.pixel(dx, dy)) else {
+                    continue;
+                };
+                self.set_pixel(dx, dy, blend_normal(bottom, top, opacity));
+            }
+        }
+    }
+}
+
+/// Image of palette indices, one byte per pixel, stored row by row.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct IndexedImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Palette index of each pixel.
+    pub data: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// Image filled with `index`.
+    pub fn new(width: u32, height: u32, index: u8) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![index; width as usize * height as usize],
+        }
+    }
+}
+
+fn mul_un8(a: u32, b: u32) -> u32 {
+    let t = a * b + 0x80;
+    ((t >> 8) + t) >> 8
+}
+
+/// Source-over blending of `top` onto `bottom`, the way Aseprite's normal blend mode does it.
+pub(crate) fn blend_normal(bottom: Color, top: Color, opacity: u8) -> Color {
+    let top_a = mul_un8(top.a as u32, opacity as u32);
+    if bottom.a == 0 {
+        return Color {
+            a: top_a as u8,
+            ..top
+        };
+    }
+    if top_a == 0 {
+        return bottom;
+    }
+
+    let bottom_a = bottom.a as u32;
+    let a = top_a + bottom_a - mul_un8(bottom_a, top_a);
+    let mix = |b: u8, t: u8| {
+        let (b, t) = (b as i32, t as i32);
+        (b + (t - b) * top_a as i32 / a as i32) as u8
+    };
+    Color {
+        r: mix(bottom.r, top.r),
+        g: mix(bottom.g, top.g),
+        b: mix(bottom.b, top.b),
+        a: a as u8,
+    }
+}
@@ -0,0 +1,849 @@
+//! Reading the atlas manifests of other tools as sprite sheets.
+//!
+//! What the formats leave out is filled in: frames last
+//! [`DEFAULT_DURATION`] milliseconds, and without tags of their own,
+//! runs of frames numbered in order, such as `walk_01.png` and
+//! `walk_02.png`, become a tag named after what precedes the number.
+
+#[cfg(feature = "json")]
+use serde::Deserialize;
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+use crate::collision::PIVOT_SLICE;
+use crate::Error;
+#[cfg(feature = "json")]
+use crate::Layer;
+use crate::{
+    Color, Dimensions, Direction, Frame, FrameData, Frametag, Metadata, Point, Rect, Slice,
+    SliceKey, SpritesheetData,
+};
+
+/// Duration of frames whose format has none, Aseprite's default.
+pub const DEFAULT_DURATION: u32 = 100;
+
+/// Frame with only the fields every format has, which the rest are
+/// worked out from.
+#[derive(Debug, Clone)]
+struct Region {
+    name: String,
+    frame: Rect,
+    rotated: bool,
+    sprite_source_size: Option<Rect>,
+    source_size: Option<Dimensions>,
+    duration: Option<u32>,
+    pivot: Option<Point>,
+}
+
+impl Region {
+    fn new(name: String, frame: Rect) -> Self {
+        Self {
+            name,
+            frame,
+            rotated: false,
+            sprite_source_size: None,
+            source_size: None,
+            duration: None,
+            pivot: None,
+        }
+    }
+
+    fn into_frame(self) -> Result<Frame, Error> {
+        let sprite_source_size = self.sprite_source_size.unwrap_or(Rect {
+            x: 0,
+            y: 0,
+            ..self.frame
+        });
+        let source_size = match self.source_size {
+            Some(size) => size,
+            None => Dimensions {
+                w: end(sprite_source_size.x, sprite_source_size.w, "frame offset")?,
+                h: end(sprite_source_size.y, sprite_source_size.h, "frame offset")?,
+            },
+        };
+        let trimmed = (sprite_source_size.x, sprite_source_size.y) != (0, 0)
+            || (sprite_source_size.w, sprite_source_size.h) != (source_size.w, source_size.h);
+        Ok(Frame {
+            filename: self.name,
+            data: FrameData {
+                frame: self.frame,
+                rotated: self.rotated,
+                trimmed,
+                sprite_source_size,
+                source_size,
+                duration: self.duration.unwrap_or(DEFAULT_DURATION),
+            },
+        })
+    }
+}
+
+/// `start + len`, the end of a span of `what`, if it fits.
+fn end(start: u32, len: u32, what: &str) -> Result<u32, Error> {
+    start
+        .checked_add(len)
+        .ok_or_else(|| Error::format(format!("{} is out of range", what)))
+}
+
+/// Splits a frame name such as `walk_01.png` into `walk` and 1.
+fn numbered(name: &str) -> Option<(&str, u32)> {
+    let stem = match name.rsplit_once('.') {
+        Some((stem, extension))
+            if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphabetic()) =>
+        {
+            stem
+        }
+        _ => name,
+    };
+    let base = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = stem[base.len()..].parse().ok()?;
+    let base = base.trim_end_matches([' ', '_', '-', '.', '/']);
+    (!base.is_empty()).then_some((base, number))
+}
+
+/// Tags for runs of frames whose names count up from one to the next.
+fn infer_tags(frames: &[Frame]) -> Vec<Frametag> {
+    let mut tags: Vec<Frametag> = Vec::new();
+    let mut previous: Option<(&str, u32)> = None;
+    for (i, frame) in frames.iter().enumerate() {
+        let current = numbered(&frame.filename);
+        match (previous, current) {
+            (Some((base, number)), Some((next_base, next)))
+                if base == next_base && number.checked_add(1) == Some(next) =>
+            {
+                if let Some(tag) = tags.last_mut() {
+                    tag.to = i as u32;
+                }
+            }
+            (_, Some((base, _))) => tags.push(Frametag {
+                name: base.to_string(),
+                from: i as u32,
+                to: i as u32,
+                direction: Direction::Forward,
+                repeat: None,
+                color: None,
+                data: None,
+            }),
+            _ => {}
+        }
+        previous = current;
+    }
+    tags
+}
+
+/// Slice holding the pivot of each frame that has one.
+fn pivot_slice(regions: &[Region], frames: &[Frame]) -> Option<Slice> {
+    let mut keys: Vec<SliceKey> = Vec::new();
+    for (i, (region, frame)) in regions.iter().zip(frames).enumerate() {
+        let Some(pivot) = region.pivot else {
+            continue;
+        };
+        let size = frame.source_size;
+        let bounds = Rect {
+            x: 0,
+            y: 0,
+            w: size.w,
+            h: size.h,
+        };
+        if keys
+            .last()
+            .is_some_and(|key| key.bounds == bounds && key.pivot == Some(pivot))
+        {
+            continue;
+        }
+        keys.push(SliceKey {
+            frame: i as u32,
+            bounds,
+            pivot: Some(pivot),
+            center: None,
+        });
+    }
+    (!keys.is_empty()).then(|| Slice {
+        name: PIVOT_SLICE.to_string(),
+        color: Color {
+            r: 0,
+            g: 0,
+            b: 255,
+            a: 255,
+        },
+        keys,
+        data: None,
+    })
+}
+
+/// Builds the sheet, taking its size from the frames if it isn't known.
+fn build_sheet(
+    regions: Vec<Region>,
+    meta: Metadata,
+    size: Option<Dimensions>,
+) -> Result<SpritesheetData, Error> {
+    let frames = regions
+        .iter()
+        .cloned()
+        .map(Region::into_frame)
+        .collect::<Result<Vec<_>, _>>()?;
+    let slice = pivot_slice(&regions, &frames);
+    let size = match size {
+        Some(size) => size,
+        None => {
+            let mut size = Dimensions { w: 0, h: 0 };
+            for f in &frames {
+                let (w, h) = match f.rotated {
+                    false => (f.frame.w, f.frame.h),
+                    true => (f.frame.h, f.frame.w),
+                };
+                size.w = size.w.max(end(f.frame.x, w, "frame")?);
+                size.h = size.h.max(end(f.frame.y, h, "frame")?);
+            }
+            size
+        }
+    };
+    let mut meta = Metadata { size, ..meta };
+    if meta.frame_tags.is_empty() {
+        meta.frame_tags = infer_tags(&frames);
+    }
+    if let Some(slice) = slice {
+        if !meta.slices.iter().any(|s| s.name == PIVOT_SLICE) {
+            meta.slices.push(slice);
+        }
+    }
+    for tag in &meta.frame_tags {
+        if tag.from > tag.to || tag.to as usize >= frames.len() {
+            return Err(Error::format(format!(
+                "tag {} is out of the frames",
+                tag.name
+            )));
+        }
+    }
+    Ok(SpritesheetData { frames, meta })
+}
+
+fn metadata(app: &str, image: Option<String>) -> Metadata {
+    Metadata {
+        app: app.to_string(),
+        version: String::new(),
+        format: "RGBA8888".to_string(),
+        size: Dimensions { w: 0, h: 0 },
+        scale: "1".to_string(),
+        image,
+        frame_tags: Vec::new(),
+        layers: Vec::new(),
+        slices: Vec::new(),
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TpFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    sprite_source_size: Option<Rect>,
+    #[serde(default)]
+    source_size: Option<Dimensions>,
+    #[serde(default)]
+    duration: Option<u32>,
+    #[serde(default)]
+    pivot: Option<TpPivot>,
+}
+
+#[cfg(feature = "json")]
+/// Pivot as a fraction of the untrimmed size.
+#[derive(Deserialize)]
+struct TpPivot {
+    x: f64,
+    y: f64,
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct TpMeta {
+    app: Option<String>,
+    version: Option<String>,
+    image: Option<String>,
+    format: Option<String>,
+    size: Option<Dimensions>,
+    scale: Option<Value>,
+    #[serde(default)]
+    frame_tags: Vec<Frametag>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+    #[serde(default)]
+    slices: Vec<Slice>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+struct TpSheet {
+    frames: Value,
+    #[serde(default)]
+    meta: TpMeta,
+}
+
+#[cfg(feature = "json")]
+/// Reads TexturePacker's JSON, in its Hash or Array form.
+///
+/// Shoebox and most other packers write the same format, with some
+/// fields left out. Frames of the Hash form, which has no order, are
+/// sorted by name, counting numbers up in order. Pivots become keys of
+/// the [`PIVOT_SLICE`] slice.
+pub fn texture_packer(json: &[u8]) -> Result<SpritesheetData, Error> {
+    let json_error = |e: serde_json::Error| Error::format(e.to_string());
+    let sheet: TpSheet = serde_json::from_slice(json).map_err(json_error)?;
+    let mut frames: Vec<(String, TpFrame)> = match sheet.frames {
+        Value::Object(map) => {
+            let mut frames = map
+                .into_iter()
+                .map(|(name, frame)| Ok((name, serde_json::from_value(frame)?)))
+                .collect::<Result<Vec<_>, serde_json::Error>>()
+                .map_err(json_error)?;
+            frames.sort_by(|(a, _), (b, _)| {
+                let key = |name: &str| numbered(name).map(|(base, n)| (base.to_string(), n));
+                key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+            });
+            frames
+        }
+        frames => serde_json::from_value::<Vec<TpFrame>>(frames)
+            .map_err(json_error)?
+            .into_iter()
+            .map(|frame| (frame.filename.clone().unwrap_or_default(), frame))
+            .collect(),
+    };
+
+    let regions = frames
+        .drain(..)
+        .map(|(name, frame)| {
+            let source_size = match (frame.source_size, frame.sprite_source_size) {
+                (Some(size), _) => Some(size),
+                (None, Some(s)) => Some(Dimensions {
+                    w: end(s.x, s.w, "spriteSourceSize")?,
+                    h: end(s.y, s.h, "spriteSourceSize")?,
+                }),
+                (None, None) => None,
+            };
+            let pivot = frame.pivot.map(|pivot| {
+                let size = source_size.unwrap_or(Dimensions {
+                    w: frame.frame.w,
+                    h: frame.frame.h,
+                });
+                Point {
+                    x: (pivot.x * size.w as f64).round().max(0.0) as u32,
+                    y: (pivot.y * size.h as f64).round().max(0.0) as u32,
+                }
+            });
+            Ok(Region {
+                rotated: frame.rotated,
+                sprite_source_size: frame.sprite_source_size,
+                source_size,
+                duration: frame.duration,
+                pivot,
+                ..Region::new(name, frame.frame)
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    let meta = sheet.meta;
+    let scale = match meta.scale {
+        Some(Value::String(scale)) => scale,
+        Some(Value::Number(scale)) => scale.to_string(),
+        _ => "1".to_string(),
+    };
+    let defaults = metadata(meta.app.as_deref().unwrap_or(""), meta.image);
+    let metadata = Metadata {
+        version: meta.version.unwrap_or_default(),
+        format: meta.format.unwrap_or(defaults.format.clone()),
+        scale,
+        frame_tags: meta.frame_tags,
+        layers: meta.layers,
+        slices: meta.slices,
+        ..defaults
+    };
+    build_sheet(regions, metadata, meta.size)
+}
+
+/// Name and attributes of an XML element.
+type XmlElement<'a> = (&'a str, Vec<(&'a str, String)>);
+
+/// Elements of an XML document with their attributes, in order.
+///
+/// Only as much XML as atlas files use is understood: no text content,
+/// comments or CDATA matter.
+fn xml_elements(xml: &str) -> Result<Vec<XmlElement<'_>>, Error> {
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if rest.starts_with("!--") {
+            let end = rest
+                .find("-->")
+                .ok_or_else(|| Error::format("unclosed xml comment"))?;
+            rest = &rest[end + 3..];
+            continue;
+        }
+        let end = rest
+            .find('>')
+            .ok_or_else(|| Error::format("unclosed xml tag"))?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with(['?', '!', '/']) {
+            continue;
+        }
+        let tag = tag.trim_end_matches('/');
+        let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+        let (name, mut attributes_text) = tag.split_at(name_end);
+        let mut attributes = Vec::new();
+        loop {
+            attributes_text = attributes_text.trim_start();
+            if attributes_text.is_empty() {
+                break;
+            }
+            let (key, value) = attributes_text
+                .split_once('=')
+                .ok_or_else(|| Error::format(format!("bad xml attribute in <{}>", name)))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or_else(|| Error::format(format!("unquoted xml attribute in <{}>", name)))?;
+            let end = value[1..]
+                .find(quote)
+                .ok_or_else(|| Error::format(format!("unclosed xml attribute in <{}>", name)))?;
+            attributes.push((key.trim(), xml_unescape(&value[1..end + 1])));
+            attributes_text = &value[end + 2..];
+        }
+        elements.push((name, attributes));
+    }
+    Ok(elements)
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|n| n.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Reads Starling or Sparrow texture atlas XML, which Shoebox writes too.
+pub fn sparrow(xml: &str) -> Result<SpritesheetData, Error> {
+    let mut image = None;
+    let mut regions = Vec::new();
+    for (name, attributes) in xml_elements(xml)? {
+        let attribute = |key: &str| {
+            attributes
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        let number = |key: &str| -> Result<Option<i64>, Error> {
+            attribute(key)
+                .map(|v| {
+                    // Some tools write fractions, which round to pixels.
+                    v.trim()
+                        .parse::<f64>()
+                        .map(|v| v.round() as i64)
+                        .map_err(|_| Error::format(format!("{} isn't a number: {}", key, v)))
+                })
+                .transpose()
+        };
+        let pixels = |key: &str| -> Result<u32, Error> {
+            number(key)?
+                .ok_or_else(|| Error::format(format!("SubTexture without {}", key)))?
+                .try_into()
+                .map_err(|_| Error::format(format!("{} is out of range", key)))
+        };
+        match name {
+            "TextureAtlas" => image = attribute("imagePath").map(str::to_string),
+            "SubTexture" => {
+                let rotated = attribute("rotated") == Some("true");
+                let (w, h) = (pixels("width")?, pixels("height")?);
+                // The size is the one in the texture, turned for rotated
+                // frames, where `frame` has the upright size.
+                let (w, h) = if rotated { (h, w) } else { (w, h) };
+                let mut region = Region::new(
+                    attribute("name").unwrap_or_default().to_string(),
+                    Rect {
+                        x: pixels("x")?,
+                        y: pixels("y")?,
+                        w,
+                        h,
+                    },
+                );
+                region.rotated = rotated;
+                if attribute("frameWidth").is_some() {
+                    let x = u32::try_from(-number("frameX")?.unwrap_or(0));
+                    let y = u32::try_from(-number("frameY")?.unwrap_or(0));
+                    let (Ok(x), Ok(y)) = (x, y) else {
+                        return Err(Error::format("frame offsets must not be positive"));
+                    };
+                    region.sprite_source_size = Some(Rect { x, y, w, h });
+                    region.source_size = Some(Dimensions {
+                        w: pixels("frameWidth")?,
+                        h: pixels("frameHeight")?,
+                    });
+                }
+                regions.push(region);
+            }
+            _ => {}
+        }
+    }
+    build_sheet(regions, metadata("", image), None)
+}
+
+/// Numbers separated by commas, as libGDX writes them.
+fn libgdx_numbers(key: &str, value: &str) -> Result<Vec<i64>, Error> {
+    value
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse()
+                .map_err(|_| Error::format(format!("bad libGDX {}: {}", key, value)))
+        })
+        .collect()
+}
+
+fn libgdx_pair(key: &str, value: &str) -> Result<(u32, u32), Error> {
+    match libgdx_numbers(key, value)?[..] {
+        [a, b, ..] => match (u32::try_from(a), u32::try_from(b)) {
+            (Ok(a), Ok(b)) => Ok((a, b)),
+            _ => Err(Error::format(format!("bad libGDX {}: {}", key, value))),
+        },
+        _ => Err(Error::format(format!("bad libGDX {}: {}", key, value))),
+    }
+}
+
+/// Region of a libGDX atlas as written, before working out the frame.
+#[derive(Default)]
+struct LibgdxRegion {
+    name: String,
+    xy: (u32, u32),
+    size: (u32, u32),
+    orig: Option<(u32, u32)>,
+    offset: (u32, u32),
+    rotate: bool,
+    index: i64,
+}
+
+impl LibgdxRegion {
+    fn field(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key {
+            "xy" => self.xy = libgdx_pair(key, value)?,
+            "size" => self.size = libgdx_pair(key, value)?,
+            "orig" => self.orig = Some(libgdx_pair(key, value)?),
+            "offset" => self.offset = libgdx_pair(key, value)?,
+            "bounds" => {
+                self.xy = libgdx_pair(key, value)?;
+                self.size = libgdx_pair(key, value.splitn(3, ',').nth(2).unwrap_or(""))?;
+            }
+            "offsets" => {
+                self.offset = libgdx_pair(key, value)?;
+                self.orig = Some(libgdx_pair(key, value.splitn(3, ',').nth(2).unwrap_or(""))?);
+            }
+            "rotate" => self.rotate = value == "true" || value == "90",
+            "index" => self.index = libgdx_numbers(key, value)?[0],
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn into_region(self) -> Region {
+        let (w, h) = self.size;
+        let orig = self.orig.unwrap_or(self.size);
+        let name = match self.index {
+            index if index >= 0 => format!("{} {}", self.name, index),
+            _ => self.name,
+        };
+        Region {
+            rotated: self.rotate,
+            // libGDX measures offsets from the bottom.
+            sprite_source_size: Some(Rect {
+                x: self.offset.0,
+                y: orig.1.saturating_sub(self.offset.1.saturating_add(h)),
+                w,
+                h,
+            }),
+            source_size: Some(Dimensions {
+                w: orig.0,
+                h: orig.1,
+            }),
+            ..Region::new(
+                name,
+                Rect {
+                    x: self.xy.0,
+                    y: self.xy.1,
+                    w,
+                    h,
+                },
+            )
+        }
+    }
+}
+
+/// Reads a libGDX `.atlas`, in the format before or since libGDX 1.9.13,
+/// giving a sheet for each page.
+///
+/// Regions with an index are named after it, such as `walk 3`.
+pub fn libgdx(atlas: &str) -> Result<Vec<SpritesheetData>, Error> {
+    let mut pages = Vec::new();
+    let mut page: Option<(String, Option<Dimensions>, Vec<LibgdxRegion>)> = None;
+    let mut finish = |page: Option<(String, Option<Dimensions>, Vec<LibgdxRegion>)>| {
+        if let Some((image, size, regions)) = page {
+            let regions = regions.into_iter().map(LibgdxRegion::into_region).collect();
+            pages.push(build_sheet(regions, metadata("", Some(image)), size)?);
+        }
+        Ok::<(), Error>(())
+    };
+    for line in atlas.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            finish(page.take())?;
+            continue;
+        }
+        let field = trimmed
+            .split_once(':')
+            .map(|(key, value)| (key.trim(), value.trim()));
+        match (&mut page, field) {
+            (None, _) => page = Some((trimmed.to_string(), None, Vec::new())),
+            (Some((_, size, regions)), Some((key, value))) => match regions.last_mut() {
+                Some(region) => region.field(key, value)?,
+                None if key == "size" => {
+                    let (w, h) = libgdx_pair(key, value)?;
+                    *size = Some(Dimensions { w, h });
+                }
+                None => {}
+            },
+            (Some((_, _, regions)), None) => regions.push(LibgdxRegion {
+                name: trimmed.to_string(),
+                index: -1,
+                ..LibgdxRegion::default()
+            }),
+        }
+    }
+    finish(page.take())?;
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export;
+
+    fn sheet() -> SpritesheetData {
+        serde_json::from_str(include_str!("../testdata/export/sheet.json")).unwrap()
+    }
+
+    /// What the formats keep of a sheet.
+    fn frames(sheet: &SpritesheetData) -> Vec<(Rect, bool, Rect, Dimensions)> {
+        sheet
+            .frames
+            .iter()
+            .map(|f| (f.frame, f.rotated, f.sprite_source_size, f.source_size))
+            .collect()
+    }
+
+    #[test]
+    fn test_numbered() {
+        assert_eq!(Some(("walk", 1)), numbered("walk_01.png"));
+        assert_eq!(Some(("hero run", 12)), numbered("hero run 12.ase"));
+        assert_eq!(Some(("walk", 3)), numbered("walk/3"));
+        assert_eq!(None, numbered("idle.png"));
+        assert_eq!(None, numbered("7.png"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sheet = sheet();
+        for imported in [
+            sparrow(&export::sparrow(&sheet)).unwrap(),
+            libgdx(&export::libgdx(&sheet)).unwrap().remove(0),
+        ] {
+            assert_eq!(frames(&sheet), frames(&imported));
+            assert_eq!(sheet.meta.image, imported.meta.image);
+            assert!(imported
+                .frames
+                .iter()
+                .all(|f| f.duration == DEFAULT_DURATION));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_texture_packer_round_trip() {
+        let sheet = sheet();
+        let imported = texture_packer(export::texture_packer(&sheet).as_bytes()).unwrap();
+        let pivots = imported.meta.slices.iter().find(|s| s.name == PIVOT_SLICE);
+        assert_eq!(1, pivots.unwrap().keys.len());
+        assert_eq!(Some(Point { x: 8, y: 8 }), pivots.unwrap().keys[0].pivot);
+        // The Hash form is sorted by name.
+        let mut expected = sheet.frames.clone();
+        expected.sort_by(|a, b| a.filename.cmp(&b.filename));
+        let names: Vec<_> = imported.frames.iter().map(|f| &f.filename).collect();
+        assert_eq!(
+            expected.iter().map(|f| &f.filename).collect::<Vec<_>>(),
+            names
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_texture_packer() {
+        let json = br#"{
+          "frames": {
+            "walk_10.png": { "frame": { "x": 0, "y": 0, "w": 4, "h": 4 }, "pivot": { "x": 0.5, "y": 1 } },
+            "walk_9.png": { "frame": { "x": 4, "y": 0, "w": 4, "h": 4 }, "pivot": { "x": 0.5, "y": 1 } },
+            "walk_8.png": {
+              "frame": { "x": 8, "y": 0, "w": 2, "h": 3 }, "rotated": true, "trimmed": true,
+              "spriteSourceSize": { "x": 1, "y": 0, "w": 2, "h": 3 }, "sourceSize": { "w": 4, "h": 4 },
+              "pivot": { "x": 0.25, "y": 0.25 }
+            },
+            "idle.png": { "frame": { "x": 0, "y": 4, "w": 4, "h": 4 } }
+          },
+          "meta": { "app": "https://www.codeandweb.com/texturepacker", "scale": 1 }
+        }"#;
+        let sheet = texture_packer(json).unwrap();
+        let names: Vec<_> = sheet.frames.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(
+            vec!["idle.png", "walk_8.png", "walk_9.png", "walk_10.png"],
+            names
+        );
+        assert_eq!(1, sheet.meta.frame_tags.len());
+        let tag = &sheet.meta.frame_tags[0];
+        assert_eq!(("walk", 1, 3), (tag.name.as_str(), tag.from, tag.to));
+        assert_eq!(Dimensions { w: 11, h: 8 }, sheet.meta.size);
+        assert!(sheet.frames[1].trimmed && sheet.frames[1].rotated);
+        assert!(!sheet.frames[2].trimmed);
+
+        let pivots = &sheet.meta.slices[0];
+        let keys: Vec<_> = pivots.keys.iter().map(|k| (k.frame, k.pivot)).collect();
+        assert_eq!(
+            vec![
+                (1, Some(Point { x: 1, y: 1 })),
+                (2, Some(Point { x: 2, y: 4 }))
+            ],
+            keys
+        );
+        assert_eq!(
+            Point { x: 2, y: 4 },
+            crate::collision::frame_pivot(&sheet, 3)
+        );
+
+        assert!(texture_packer(br#"{ "frames": [{ "frame": 3 }] }"#).is_err());
+        let far = br#"{ "frames": [{ "frame": { "x": 4294967295, "y": 0, "w": 1, "h": 1 } }] }"#;
+        assert!(texture_packer(far).is_err());
+        let far = br#"{ "frames": [{
+          "frame": { "x": 0, "y": 0, "w": 1, "h": 1 },
+          "spriteSourceSize": { "x": 4294967295, "y": 0, "w": 1, "h": 1 }
+        }] }"#;
+        assert!(texture_packer(far).is_err());
+    }
+
+    #[test]
+    fn test_libgdx() {
+        // The format since libGDX 1.9.13, with two pages.
+        let atlas = "
+page1.png
+size:64,32
+filter:Nearest,Nearest
+repeat:none
+run
+bounds:0,0,8,6
+offsets:1,2,10,10
+rotate:90
+index:1
+run
+bounds:8,0,8,8
+index:0
+
+page2.png
+size:16,16
+idle
+bounds:0,0,16,16
+";
+        let pages = libgdx(atlas).unwrap();
+        assert_eq!(2, pages.len());
+        let run = &pages[0];
+        assert_eq!(Dimensions { w: 64, h: 32 }, run.meta.size);
+        assert_eq!("run 1", run.frames[0].filename);
+        assert!(run.frames[0].rotated);
+        assert_eq!(
+            Rect {
+                x: 1,
+                y: 2,
+                w: 8,
+                h: 6
+            },
+            run.frames[0].sprite_source_size
+        );
+        assert_eq!(Dimensions { w: 10, h: 10 }, run.frames[0].source_size);
+        // Counting down isn't a tag.
+        assert_eq!(2, run.meta.frame_tags.len());
+        assert_eq!(Some("page2.png"), pages[1].meta.image.as_deref());
+        assert!(pages[1].meta.frame_tags.is_empty());
+        assert!(libgdx("page.png\nsize: 1, 1\nx\nxy: a, b\n").is_err());
+
+        // Numbers and offsets at the edge of `u32` don't overflow.
+        let atlas = "page.png\nsize:8,8\nwalk_4294967295\nbounds:0,0,1,1\n\
+                     offsets:0,4294967295,1,1\nwalk_0\nbounds:1,0,1,1\n";
+        let page = libgdx(atlas).unwrap().remove(0);
+        assert_eq!(0, page.frames[0].sprite_source_size.y);
+        assert!(page.meta.frame_tags.iter().all(|tag| tag.from == tag.to));
+    }
+
+    #[test]
+    fn test_sparrow() {
+        let xml = r#"<?xml version="1.0"?>
+<!-- made with <Shoebox> -->
+<TextureAtlas imagePath='hero.png'>
+  <SubTexture name="fly&#32;0" x="0" y="0" width="4.0" height="3" frameX="-1" frameY="0" frameWidth="6" frameHeight="3"/>
+  <SubTexture name="fly 1" x="4" y="0" width="4" height="3"></SubTexture>
+</TextureAtlas>"#;
+        let sheet = sparrow(xml).unwrap();
+        assert_eq!(Some("hero.png"), sheet.meta.image.as_deref());
+        assert_eq!("fly 0", sheet.frames[0].filename);
+        assert!(sheet.frames[0].trimmed);
+        assert_eq!(Dimensions { w: 6, h: 3 }, sheet.frames[0].source_size);
+        assert_eq!(1, sheet.frames[0].sprite_source_size.x);
+        assert_eq!(
+            (0, 1),
+            (sheet.meta.frame_tags[0].from, sheet.meta.frame_tags[0].to)
+        );
+        assert!(sparrow(r#"<SubTexture name="a" x="0" y="0" width="-1" height="1"/>"#).is_err());
+        assert!(
+            sparrow(r#"<SubTexture name="a" x="4294967295" y="0" width="1" height="1"/>"#).is_err()
+        );
+    }
+}
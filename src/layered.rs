@@ -0,0 +1,343 @@
+//! Playback of sprites exported with `--split-layers`.
+//!
+//! Splitting layers gives every layer its own frames in the sheet. These
+//! are told apart by their file names, which have to contain the layer
+//! name in parentheses followed by the frame number, as the default
+//! `{title} ({layer}) {frame}.{extension}` format does.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::animation::{tag_sequence, Animation};
+use crate::{BlendMode, Frame, Frametag, Layer, Limits, SpritesheetData};
+
+/// Frames of a split-layer sheet, indexed by layer and frame number.
+#[derive(Debug, Clone)]
+pub struct SplitLayers<'a> {
+    sheet: &'a SpritesheetData,
+    /// Sheet frame index per frame number, for each layer in `meta.layers`.
+    frames: Vec<Vec<Option<usize>>>,
+    frame_count: u32,
+}
+
+impl<'a> SplitLayers<'a> {
+    /// Indexes the frames of `sheet`.
+    ///
+    /// Frames numbered past [`Limits::max_frames`] are left out.
+    pub fn new(sheet: &'a SpritesheetData) -> Self {
+        let max_frames = Limits::default().max_frames;
+        let frames: Vec<Vec<Option<usize>>> = sheet
+            .meta
+            .layers
+            .iter()
+            .map(|layer| {
+                let marker = format!("({})", layer.name);
+                let mut frames = Vec::new();
+                for (index, frame) in sheet.frames.iter().enumerate() {
+                    let Some(start) = frame.filename.find(&marker) else {
+                        continue;
+                    };
+                    let rest = frame.filename[start + marker.len()..].trim_start();
+                    let digits = rest
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(rest.len());
+                    let number = match &rest[..digits] {
+                        "" => frames.len(),
+                        digits => match digits.parse::<u32>() {
+                            Ok(number) if number < max_frames => number as usize,
+                            // Not a frame Aseprite could have written.
+                            _ => continue,
+                        },
+                    };
+                    if frames.len() <= number {
+                        frames.resize(number + 1, None);
+                    }
+                    frames[number] = Some(index);
+                }
+                frames
+            })
+            .collect();
+        let frame_count = frames.iter().map(Vec::len).max().unwrap_or(0) as u32;
+        Self {
+            sheet,
+            frames,
+            frame_count,
+        }
+    }
+
+    /// The indexed sheet.
+    pub fn sheet(&self) -> &'a SpritesheetData {
+        self.sheet
+    }
+
+    /// Number of frames in the sprite.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Frame of the named layer at `frame`.
+    ///
+    /// `None` for group layers and for cels left out of the sheet.
+    pub fn layer_frame(&self, layer: &str, frame: u32) -> Option<&'a Frame> {
+        let index = self.layer_index(layer)?;
+        self.frame_of(index, frame)
+    }
+
+    /// Duration of each frame number in milliseconds.
+    ///
+    /// Taken from the first layer that has the frame.
+    pub fn durations(&self) -> Vec<u32> {
+        (0..self.frame_count)
+            .map(|frame| {
+                (0..self.frames.len())
+                    .find_map(|layer| self.frame_of(layer, frame))
+                    .map_or(0, |frame| frame.duration)
+            })
+            .collect()
+    }
+
+    fn layer_index(&self, layer: &str) -> Option<usize> {
+        self.sheet.meta.layers.iter().position(|l| l.name == layer)
+    }
+
+    fn frame_of(&self, layer: usize, frame: u32) -> Option<&'a Frame> {
+        let index = (*self.frames.get(layer)?.get(frame as usize)?)?;
+        self.sheet.frames.get(index)
+    }
+}
+
+/// One layer to draw for the current frame.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DrawItem<'a> {
+    /// Layer whose position in the stack is drawn.
+    pub slot: &'a Layer,
+    /// Layer whose pixels are drawn.
+    ///
+    /// Same as `slot` unless another layer was swapped in.
+    pub layer: &'a Layer,
+    /// Frame on the sprite sheet.
+    pub frame: &'a Frame,
+    /// Opacity of `layer`.
+    pub opacity: u32,
+    /// Blend mode of `layer`.
+    pub blend_mode: BlendMode,
+}
+
+/// Plays all layers of a split-layer sheet in lockstep.
+#[derive(Debug, Clone)]
+pub struct LayeredPlayer<'a> {
+    layers: SplitLayers<'a>,
+    durations: Vec<u32>,
+    animation: Animation,
+    hidden: BTreeSet<String>,
+    swaps: BTreeMap<String, String>,
+}
+
+impl<'a> LayeredPlayer<'a> {
+    /// Plays the whole timeline of `sheet`.
+    pub fn new(sheet: &'a SpritesheetData) -> Self {
+        let layers = SplitLayers::new(sheet);
+        let durations = layers.durations();
+        let animation = Animation::from_durations((0..layers.frame_count()).collect(), &durations);
+        Self {
+            layers,
+            durations,
+            animation,
+            hidden: BTreeSet::new(),
+            swaps: BTreeMap::new(),
+        }
+    }
+
+    /// Frames of the sheet, indexed by layer.
+    pub fn layers(&self) -> &SplitLayers<'a> {
+        &self.layers
+    }
+
+    /// Starts playing `tag` from its first frame.
+    pub fn play(&mut self, tag: &Frametag) {
+        self.animation = Animation::from_durations(tag_sequence(tag), &self.durations);
+    }
+
+    /// Shared playback state.
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Current frame number.
+    pub fn frame(&self) -> u32 {
+        self.animation.frame()
+    }
+
+    /// Advances all layers by `elapsed` milliseconds.
+    ///
+    /// Returns how many frames were stepped over.
+    pub fn update(&mut self, elapsed: u32) -> u32 {
+        self.animation.update(elapsed)
+    }
+
+    /// Shows or hides a layer.
+    ///
+    /// Hiding a group hides every layer within it.
+    pub fn set_visible(&mut self, layer: &str, visible: bool) {
+        if visible {
+            self.hidden.remove(layer);
+        } else {
+            self.hidden.insert(layer.to_owned());
+        }
+    }
+
+    /// Whether a layer and all groups containing it are visible.
+    pub fn is_visible(&self, layer: &str) -> bool {
+        let layers = &self.layers.sheet.meta.layers;
+        let mut current = Some(layer);
+        // Bounded by the number of layers in case groups are cyclic.
+        for _ in 0..=layers.len() {
+            let Some(name) = current else {
+                return true;
+            };
+            if self.hidden.contains(name) {
+                return false;
+            }
+            current = layers
+                .iter()
+                .find(|l| l.name == name)
+                .and_then(|l| l.group.as_deref());
+        }
+        true
+    }
+
+    /// Draws the frames of `with` in place of `layer`.
+    ///
+    /// `with` is still drawn in its own place as well, so usually it
+    /// should be hidden.
+    pub fn swap(&mut self, layer: &str, with: &str) {
+        self.swaps.insert(layer.to_owned(), with.to_owned());
+    }
+
+    /// Undoes [`LayeredPlayer::swap`].
+    pub fn unswap(&mut self, layer: &str) {
+        self.swaps.remove(layer);
+    }
+
+    /// Layers to draw for the current frame, bottom to top.
+    pub fn draw_list(&self) -> Vec<DrawItem<'a>> {
+        let sheet = self.layers.sheet;
+        let frame = self.frame();
+        sheet
+            .meta
+            .layers
+            .iter()
+            .filter(|slot| self.is_visible(&slot.name))
+            .filter_map(|slot| {
+                let layer = match self.swaps.get(&slot.name) {
+                    Some(with) => sheet.meta.layers.iter().find(|l| &l.name == with)?,
+                    None => slot,
+                };
+                Some(DrawItem {
+                    slot,
+                    layer,
+                    frame: self.layers.layer_frame(&layer.name, frame)?,
+                    opacity: layer.opacity.unwrap_or(255),
+                    blend_mode: layer.blend_mode.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet() -> SpritesheetData {
+        let mut frames = Vec::new();
+        for (layer, x) in [("body", 0), ("helmet", 64), ("hat", 128)] {
+            for frame in 0..2 {
+                frames.push(format!(
+                    r#"{{ "filename": "knight ({}) {}.ase", "frame": {{ "x": {}, "y": 0, "w": 32, "h": 32 }},
+                    "rotated": false, "trimmed": false, "spriteSourceSize": {{ "x": 0, "y": 0, "w": 32, "h": 32 }},
+                    "sourceSize": {{ "w": 32, "h": 32 }}, "duration": {} }}"#,
+                    layer,
+                    frame,
+                    x + frame * 32,
+                    100 * (frame + 1)
+                ));
+            }
+        }
+        let json = format!(
+            r##"{{ "frames": [{}], "meta": {{
+              "app": "http://www.aseprite.org/", "version": "1.2.25-x64", "format": "RGBA8888",
+              "size": {{ "w": 192, "h": 32 }}, "scale": "1",
+              "frameTags": [ {{ "name": "second", "from": 1, "to": 1, "direction": "forward" }} ],
+              "layers": [
+               {{ "name": "body", "opacity": 255, "blendMode": "normal" }},
+               {{ "name": "head" }},
+               {{ "name": "helmet", "group": "head", "opacity": 255, "blendMode": "normal" }},
+               {{ "name": "hat", "group": "head", "opacity": 127, "blendMode": "multiply" }}
+              ] }} }}"##,
+            frames.join(",")
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_split_layers() {
+        let sheet = sheet();
+        let layers = SplitLayers::new(&sheet);
+        assert_eq!(2, layers.frame_count());
+        assert_eq!(vec![100, 200], layers.durations());
+        assert_eq!(
+            "knight (hat) 1.ase",
+            layers.layer_frame("hat", 1).unwrap().filename
+        );
+        assert!(layers.layer_frame("head", 0).is_none());
+
+        let mut sheet = sheet;
+        sheet.frames[1].filename = "knight (body) 4294967295.ase".to_owned();
+        sheet.frames[2].filename = "knight (helmet) 99999999999999999999.ase".to_owned();
+        let layers = SplitLayers::new(&sheet);
+        assert_eq!(2, layers.frame_count());
+        assert!(layers.layer_frame("body", 1).is_none());
+    }
+
+    #[test]
+    fn test_draw_list() {
+        let sheet = sheet();
+        let mut player = LayeredPlayer::new(&sheet);
+        player.set_visible("hat", false);
+
+        let names = |player: &LayeredPlayer| {
+            player
+                .draw_list()
+                .iter()
+                .map(|item| item.frame.filename.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["knight (body) 0.ase", "knight (helmet) 0.ase"],
+            names(&player)
+        );
+
+        player.update(100);
+        assert_eq!(1, player.frame());
+        player.swap("helmet", "hat");
+        let list = player.draw_list();
+        assert_eq!("helmet", list[1].slot.name);
+        assert_eq!("knight (hat) 1.ase", list[1].frame.filename);
+        assert_eq!(
+            (127, BlendMode::Multiply),
+            (list[1].opacity, list[1].blend_mode)
+        );
+
+        player.set_visible("head", false);
+        assert_eq!(vec!["knight (body) 1.ase"], names(&player));
+
+        player.play(sheet.frame_tag("second").unwrap());
+        assert_eq!(1, player.frame());
+        player.update(1000);
+        assert_eq!(1, player.frame());
+    }
+}
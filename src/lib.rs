@@ -1,9 +1,14 @@
 //! A crate for loading data from the aseprite sprite editor. Should
-//! go along well with the tiled crate, I hope!
+//! go along well with the tiled crate, I hope! The [`tiled`] module
+//! exports tilemaps drawn in Aseprite to Tiled's own formats.
 //!
-//! It does not load any actual images, just the metadata. Currently
-//! it only loads aseprite's JSON export format.  I've yet to find a use case
-//! that won't cover though.
+//! It does not load any actual images, just the metadata. Mostly it
+//! loads aseprite's JSON export format.  I've yet to find a use case
+//! that won't cover though.  The [`ase`] module reads the `.ase` files
+//! themselves, for when you need things the export leaves out, such as
+//! the palette. [`animated`] turns tags into GIF and APNG previews,
+//! [`terminal`] draws frames in a terminal, and [`sprite_sheet`] exports
+//! sheets like Aseprite does.
 //!
 //! Automatically exporting a sprite to a given format is documented
 //! here: https://www.aseprite.org/docs/cli/ The easy way to export in
@@ -15,13 +20,68 @@
 //!
 //! This has been tested to work with aseprite 1.1.6 and 1.2.25; other
 //! versions have not been tested.
+//!
+//! Without the default `std` feature the crate is `no_std` and needs
+//! only `alloc`. The data types, [`animation`] playback, [`collision`]
+//! and slice queries stay; the loaders and exporters go.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
-use std::ops::{Deref, DerefMut};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Add, AddAssign, Deref, DerefMut, Sub};
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "std")]
+pub mod animated;
+pub mod animation;
+#[cfg(feature = "std")]
+pub mod ase;
+#[cfg(feature = "std")]
+pub mod atlas;
+#[cfg(feature = "std")]
+pub mod baked;
+#[cfg(all(feature = "png", feature = "json"))]
+pub mod build;
+#[cfg(feature = "json")]
+pub mod codegen;
+pub mod collision;
+mod error;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod image;
+#[cfg(feature = "std")]
+pub mod import;
+pub mod layered;
+mod limits;
+#[cfg(feature = "std")]
+pub mod palette;
+pub mod root_motion;
+#[cfg(feature = "std")]
+mod sheet;
+#[cfg(feature = "std")]
+pub mod sprite_sheet;
+#[cfg(feature = "std")]
+pub mod terminal;
+#[cfg(feature = "json")]
+pub mod tiled;
+#[cfg(feature = "std")]
+pub mod validate;
+
+// Lets tests use the code `codegen` generates, which names the crate.
+#[cfg(test)]
+extern crate self as aseprite;
+
+pub use error::Error;
+pub use limits::Limits;
+
 /// 2D Rectangle with a position and a size.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Rect {
     /// X coordinate.
     pub x: u32,
@@ -42,6 +102,52 @@ pub struct Point {
     pub y: u32,
 }
 
+/// 2D offset that may be negative.
+///
+/// Adding and subtracting saturate rather than overflow.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Offset {
+    /// Horizontal offset.
+    pub x: i32,
+    /// Vertical offset.
+    pub y: i32,
+}
+
+impl Add for Offset {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x.saturating_add(other.x),
+            y: self.y.saturating_add(other.y),
+        }
+    }
+}
+
+impl AddAssign for Offset {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Offset {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self {
+            x: self.x.saturating_sub(other.x),
+            y: self.y.saturating_sub(other.y),
+        }
+    }
+}
+
+impl From<Point> for Offset {
+    fn from(point: Point) -> Self {
+        Self {
+            x: i32::try_from(point.x).unwrap_or(i32::MAX),
+            y: i32::try_from(point.y).unwrap_or(i32::MAX),
+        }
+    }
+}
+
 /// 2D size.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Dimensions {
@@ -64,8 +170,8 @@ pub struct Color {
     pub a: u8,
 }
 
-impl std::fmt::Debug for Color {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Debug for Color {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
         let Self { r, g, b, a } = self;
         write!(fmt, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
     }
@@ -82,7 +188,7 @@ impl<'de> Deserialize<'de> for Color {
         let s: String = Deserialize::deserialize(deserializer)?;
         if !s.starts_with('#') {
             Err(serde::de::Error::custom("color doesn't start with '#'"))
-        } else if !s.len() == 7 {
+        } else if s.len() != 9 || !s.is_ascii() {
             Err(serde::de::Error::custom("color has wrong length"))
         } else {
             let r = u8::from_str_radix(&s[1..3], 16)
@@ -101,15 +207,63 @@ impl<'de> Deserialize<'de> for Color {
 /// A single frame as part of an animation.
 ///
 /// Contains timing and position information.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Frame {
     /// Local path on disk.
     pub filename: String,
     /// Data for this frame.
-    #[serde(flatten)]
     pub data: FrameData,
 }
 
+/// Frame as Aseprite writes it, with the data beside the file name.
+///
+/// Spelled out rather than flattened, since flattening only works with
+/// self-describing formats.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Frame", rename_all = "camelCase")]
+struct FlatFrame<N> {
+    filename: N,
+    frame: Rect,
+    rotated: bool,
+    trimmed: bool,
+    sprite_source_size: Rect,
+    source_size: Dimensions,
+    duration: u32,
+}
+
+impl Serialize for Frame {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = &self.data;
+        FlatFrame {
+            filename: self.filename.as_str(),
+            frame: data.frame,
+            rotated: data.rotated,
+            trimmed: data.trimmed,
+            sprite_source_size: data.sprite_source_size,
+            source_size: data.source_size,
+            duration: data.duration,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Frame {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let frame = FlatFrame::<String>::deserialize(deserializer)?;
+        Ok(Frame {
+            filename: frame.filename,
+            data: FrameData {
+                frame: frame.frame,
+                rotated: frame.rotated,
+                trimmed: frame.trimmed,
+                sprite_source_size: frame.sprite_source_size,
+                source_size: frame.source_size,
+                duration: frame.duration,
+            },
+        })
+    }
+}
+
 impl Deref for Frame {
     type Target = FrameData;
     fn deref(&self) -> &Self::Target {
@@ -150,7 +304,7 @@ fn deserialize_frames<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Vec<Fra
     struct FramesVisitor;
     impl<'de> serde::de::Visitor<'de> for FramesVisitor {
         type Value = Vec<Frame>;
-        fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
             fmt.write_str("a json array or map")
         }
 
@@ -180,7 +334,14 @@ fn deserialize_frames<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Vec<Fra
         }
     }
 
-    de.deserialize_any(FramesVisitor)
+    // Only self-describing formats can tell a hash from an array, and
+    // frames are always written as an array. Binary formats are asked for
+    // an array, which some of them, like MessagePack, also read a hash as.
+    if de.is_human_readable() {
+        de.deserialize_any(FramesVisitor)
+    } else {
+        de.deserialize_seq(FramesVisitor)
+    }
 }
 
 /// Frame animation direction.
@@ -194,6 +355,9 @@ pub enum Direction {
     Reverse,
     /// Animation is played forward and then backward and so on.
     Pingpong,
+    /// Animation is played backward and then forward and so on.
+    #[serde(rename = "pingpong_reverse")]
+    PingpongReverse,
 }
 
 /// Tagged frame group.
@@ -211,6 +375,45 @@ pub struct Frametag {
     pub to: u32,
     /// Animation direction.
     pub direction: Direction,
+    /// How many times the animation plays.
+    ///
+    /// `None` when it loops forever. Only exported by Aseprite 1.3 and later.
+    #[serde(default, with = "repeat")]
+    pub repeat: Option<u32>,
+    /// Color with which the tag is displayed in Aseprite.
+    ///
+    /// Only exported by Aseprite 1.3 and later.
+    pub color: Option<Color>,
+    /// Custom data.
+    pub data: Option<String>,
+}
+
+/// Aseprite writes the repeat count of tags as a string.
+mod repeat {
+    use alloc::string::{String, ToString};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        repeat: &Option<u32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        repeat
+            .map(|repeat| repeat.to_string())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u32>, D::Error> {
+        let repeat: Option<String> = Deserialize::deserialize(deserializer)?;
+        match repeat.as_deref() {
+            None | Some("0") => Ok(None),
+            Some(repeat) => repeat
+                .parse()
+                .map(Some)
+                .map_err(|_| serde::de::Error::custom("tag repeat count isn't a number")),
+        }
+    }
 }
 
 // These are listed at:
@@ -290,6 +493,19 @@ pub struct Slice {
     pub data: Option<String>,
 }
 
+impl Slice {
+    /// Key in effect at the given frame.
+    ///
+    /// A key applies from its frame until the next key, so this is the last key that doesn't start after `frame`.
+    /// `None` if the slice only appears in later frames.
+    pub fn key_at(&self, frame: u32) -> Option<&SliceKey> {
+        self.keys
+            .iter()
+            .filter(|key| key.frame <= frame)
+            .max_by_key(|key| key.frame)
+    }
+}
+
 /// Define the slice rectangle in a specific frame.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -305,6 +521,24 @@ pub struct SliceKey {
     pub center: Option<Rect>,
 }
 
+impl SliceKey {
+    /// Pivot in sprite coordinates.
+    ///
+    /// The pivot is stored relative to the bounds; keys without one use the center of the bounds.
+    pub fn anchor(&self) -> Point {
+        match self.pivot {
+            Some(pivot) => Point {
+                x: self.bounds.x.saturating_add(pivot.x),
+                y: self.bounds.y.saturating_add(pivot.y),
+            },
+            None => Point {
+                x: self.bounds.x.saturating_add(self.bounds.w / 2),
+                y: self.bounds.y.saturating_add(self.bounds.h / 2),
+            },
+        }
+    }
+}
+
 /// Sprite sheet metadata.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -351,12 +585,86 @@ pub struct Metadata {
 #[non_exhaustive]
 pub struct SpritesheetData {
     /// List with sprite frame definitions.
+    ///
+    /// Read from either Aseprite's array or hash form in human readable
+    /// formats, and always written as an array. Binary formats should
+    /// store the array form.
     #[serde(deserialize_with = "deserialize_frames")]
     pub frames: Vec<Frame>,
     /// Meta data.
     pub meta: Metadata,
 }
 
+impl SpritesheetData {
+    /// Finds a tag by name.
+    pub fn frame_tag(&self, name: &str) -> Option<&Frametag> {
+        self.meta.frame_tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Parses JSON from an untrusted source and checks it against `limits`.
+    ///
+    /// Besides the limits, tags must stay within the frames.
+    #[cfg(feature = "json")]
+    pub fn from_json_with_limits(json: &[u8], limits: &Limits) -> Result<Self, Error> {
+        let sheet: Self = serde_json::from_slice(json).map_err(|e| Error::format(e.to_string()))?;
+        sheet.check_limits(limits)?;
+        Ok(sheet)
+    }
+
+    #[cfg(feature = "json")]
+    fn check_limits(&self, limits: &Limits) -> Result<(), Error> {
+        if self.frames.len() > limits.max_frames as usize {
+            return Err(Error::format(format!(
+                "{} frames are more than the limit of {}",
+                self.frames.len(),
+                limits.max_frames
+            )));
+        }
+        for frame in &self.frames {
+            let size = frame.source_size;
+            if size.w > limits.max_width || size.h > limits.max_height {
+                return Err(Error::format(format!(
+                    "frame {} of {}x{} pixels is larger than the limit of {}x{}",
+                    frame.filename, size.w, size.h, limits.max_width, limits.max_height
+                )));
+            }
+        }
+        for tag in &self.meta.frame_tags {
+            if tag.from > tag.to || tag.to as usize >= self.frames.len() {
+                return Err(Error::format(format!(
+                    "tag {} covers frames {} to {} of {}",
+                    tag.name,
+                    tag.from,
+                    tag.to,
+                    self.frames.len()
+                )));
+            }
+        }
+
+        let meta = &self.meta;
+        let strings = self
+            .frames
+            .iter()
+            .map(|frame| &frame.filename)
+            .chain(meta.frame_tags.iter().map(|tag| &tag.name))
+            .chain(meta.frame_tags.iter().filter_map(|tag| tag.data.as_ref()))
+            .chain(meta.layers.iter().map(|layer| &layer.name))
+            .chain(meta.layers.iter().filter_map(|layer| layer.data.as_ref()))
+            .chain(meta.slices.iter().map(|slice| &slice.name))
+            .chain(meta.slices.iter().filter_map(|slice| slice.data.as_ref()));
+        for string in strings {
+            if string.len() > limits.max_string_len {
+                return Err(Error::format(format!(
+                    "string of {} bytes is longer than the limit of {}",
+                    string.len(),
+                    limits.max_string_len
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate serde_json;
@@ -429,6 +737,29 @@ mod tests {
 }
 "##;
 
+    #[test]
+    fn test_saturating_geometry() {
+        use super::{Offset, Point, Rect, SliceKey};
+
+        let key = SliceKey {
+            frame: 0,
+            bounds: Rect {
+                x: u32::MAX - 1,
+                y: 3,
+                w: 10,
+                h: 4,
+            },
+            pivot: None,
+            center: None,
+        };
+        assert_eq!(Point { x: u32::MAX, y: 5 }, key.anchor());
+
+        let max = Offset::from(Point { x: u32::MAX, y: 7 });
+        assert_eq!(Offset { x: i32::MAX, y: 7 }, max);
+        assert_eq!(i32::MAX, (max + Offset { x: 1, y: 0 }).x);
+        assert_eq!(i32::MIN, (Offset { x: i32::MIN, y: 0 } - max).x);
+    }
+
     #[test]
     fn test_sprite_load_save() {
         let deserialized: super::SpritesheetData = serde_json::from_str(S).unwrap();
@@ -449,6 +780,63 @@ mod tests {
         assert_eq!(deserialized, deserialized_again);
     }
 
+    #[test]
+    fn test_other_formats() {
+        use super::SpritesheetData;
+
+        let complex = aseprite_test_data::FileSet::complex_1_2_25();
+        let sheets: Vec<SpritesheetData> = [
+            complex.hash_json,
+            include_bytes!("../testdata/export/sheet.json"),
+            include_bytes!("../testdata/codegen/slime.json"),
+        ]
+        .iter()
+        .map(|json| serde_json::from_slice(json).unwrap())
+        .collect();
+
+        for sheet in &sheets {
+            let text = ron::to_string(sheet).unwrap();
+            assert_eq!(*sheet, ron::from_str(&text).unwrap());
+            let bytes = rmp_serde::to_vec(sheet).unwrap();
+            assert_eq!(*sheet, rmp_serde::from_slice(&bytes).unwrap());
+            let bytes = rmp_serde::to_vec_named(sheet).unwrap();
+            assert_eq!(*sheet, rmp_serde::from_slice(&bytes).unwrap());
+            let bytes = bincode::serialize(sheet).unwrap();
+            assert_eq!(*sheet, bincode::deserialize(&bytes).unwrap());
+            let bytes = postcard::to_allocvec(sheet).unwrap();
+            assert_eq!(*sheet, postcard::from_bytes(&bytes).unwrap());
+        }
+
+        // MessagePack reads the hash form too, as its arrays and maps
+        // describe themselves.
+        let hash: serde_json::Value = serde_json::from_slice(complex.hash_json).unwrap();
+        let bytes = rmp_serde::to_vec_named(&hash).unwrap();
+        let sheet: SpritesheetData = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(sheets[0], sheet);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_limits() {
+        use super::{Error, Limits, SpritesheetData};
+
+        let mut limits = Limits::default();
+        assert!(SpritesheetData::from_json_with_limits(S.as_bytes(), &limits).is_ok());
+        limits.max_frames = 1;
+        assert!(SpritesheetData::from_json_with_limits(S.as_bytes(), &limits).is_err());
+        limits.max_frames = 2;
+        limits.max_string_len = 4;
+        assert!(SpritesheetData::from_json_with_limits(S.as_bytes(), &limits).is_err());
+
+        let limits = Limits::default();
+        let past_end = S.replace(r#""to": 1"#, r#""to": 7"#);
+        let bad_color = S.replace(r#""blendMode": "normal""#, r##""color": "#1""##);
+        for json in [past_end, bad_color] {
+            let result = SpritesheetData::from_json_with_limits(json.as_bytes(), &limits);
+            assert!(matches!(result, Err(Error::Format(_))));
+        }
+    }
+
     #[test]
     fn test_aseprite_test_data() {
         use super::SpritesheetData;
@@ -504,11 +892,13 @@ mod tests {
             {
                 let path = format!("data/{}/{}/{}.png", file.version, png_name, file.name);
 
-                let (png_info, mut reader) = png::Decoder::new(std::io::Cursor::new(png))
+                let mut decoder = png::Decoder::new(std::io::Cursor::new(png));
+                decoder.set_transformations(png::Transformations::EXPAND);
+                let mut reader = decoder
                     .read_info()
                     .unwrap_or_else(|e| panic!("{}: error decoding info: {}", path, e));
-                let mut frame = vec![0; png_info.buffer_size()];
-                reader
+                let mut frame = vec![0; reader.output_buffer_size()];
+                let png_info = reader
                     .next_frame(&mut frame)
                     .unwrap_or_else(|e| panic!("{}: error decoding frame: {}", path, e));
 
@@ -541,7 +931,7 @@ mod tests {
                 );
                 assert_eq!(
                     png_info.color_type,
-                    png::ColorType::RGBA,
+                    png::ColorType::Rgba,
                     "{}: expected RGBA",
                     path
                 );
@@ -581,23 +971,10 @@ mod tests {
     }
 
     fn png_color_profile(png: &[u8]) -> aseprite_test_data::PngColorProfile {
-        let mut discard = Vec::new();
-        let mut decoder = png::StreamingDecoder::new();
-        let mut has_srgb = false;
-        let mut has_iccp = false;
-        let mut rest = png;
-        while !rest.is_empty() {
-            let (next, decoded) = decoder.update(rest, &mut discard).unwrap();
-            rest = &rest[next..];
-
-            const SRGB: png::chunk::ChunkType = *b"sRGB"; // https://en.wikipedia.org/wiki/SRGB
-            const ICCP: png::chunk::ChunkType = *b"iCCP"; // International Color Consortium Profile
-            match decoded {
-                png::Decoded::PartialChunk(SRGB) => has_srgb = true,
-                png::Decoded::PartialChunk(ICCP) => has_iccp = true,
-                _ => {}
-            }
-        }
+        let reader = png::Decoder::new(png).read_info().unwrap();
+        let info = reader.info();
+        let has_srgb = info.srgb.is_some(); // https://en.wikipedia.org/wiki/SRGB
+        let has_iccp = info.icc_profile.is_some(); // International Color Consortium Profile
 
         match (has_srgb, has_iccp) {
             (true, _) => aseprite_test_data::PngColorProfile::SRGB,
@@ -0,0 +1,40 @@
+/// Bounds on what a loader accepts, for files from untrusted sources.
+///
+/// The defaults allow anything Aseprite itself can produce while keeping
+/// single allocations bounded; lower them for user-made content:
+///
+/// ```
+/// let mut limits = aseprite::Limits::default();
+/// limits.max_width = 512;
+/// limits.max_height = 512;
+/// limits.max_frames = 256;
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub struct Limits {
+    /// Largest canvas width in pixels.
+    pub max_width: u32,
+    /// Largest canvas height in pixels.
+    pub max_height: u32,
+    /// Largest number of frames.
+    pub max_frames: u32,
+    /// Largest chunk of an .ase file in bytes.
+    pub max_chunk_size: u64,
+    /// Largest decompressed cel, tileset or rendered tilemap in bytes.
+    pub max_cel_size: usize,
+    /// Longest string in bytes.
+    pub max_string_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_width: u16::MAX as u32,
+            max_height: u16::MAX as u32,
+            max_frames: u16::MAX as u32,
+            max_chunk_size: 256 << 20,
+            max_cel_size: 256 << 20,
+            max_string_len: u16::MAX as usize,
+        }
+    }
+}
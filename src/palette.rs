@@ -0,0 +1,324 @@
+//! Color palettes and the palette file formats other tools use.
+
+use std::fmt::Write as _;
+
+use crate::{Color, Error};
+
+/// Single palette color.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct PaletteEntry {
+    /// Color.
+    pub color: Color,
+    /// Name of the color, if it has one.
+    pub name: Option<String>,
+}
+
+impl PaletteEntry {
+    /// Unnamed entry.
+    pub fn new(color: Color) -> Self {
+        Self { color, name: None }
+    }
+}
+
+/// List of colors, looked up by index.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Palette {
+    /// Palette entries.
+    pub entries: Vec<PaletteEntry>,
+}
+
+const BLACK: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
+impl Palette {
+    /// Palette of unnamed colors.
+    pub fn from_colors(colors: impl IntoIterator<Item = Color>) -> Self {
+        Self {
+            entries: colors.into_iter().map(PaletteEntry::new).collect(),
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the palette has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Color at `index`.
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.entries.get(index).map(|entry| entry.color)
+    }
+
+    /// Colors in order.
+    pub fn colors(&self) -> impl Iterator<Item = Color> + '_ {
+        self.entries.iter().map(|entry| entry.color)
+    }
+
+    /// Grows or shrinks the palette, filling new entries with black.
+    pub fn resize(&mut self, len: usize) {
+        self.entries.resize(len, PaletteEntry::new(BLACK));
+    }
+
+    pub(crate) fn set(&mut self, index: usize, entry: PaletteEntry) {
+        if index >= self.entries.len() {
+            self.resize(index + 1);
+        }
+        self.entries[index] = entry;
+    }
+
+    /// Writes the palette as a GIMP `.gpl` file.
+    ///
+    /// The format has no alpha channel, so colors are written opaque.
+    pub fn to_gpl(&self, name: &str) -> String {
+        let mut gpl = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", name);
+        for entry in &self.entries {
+            let Color { r, g, b, .. } = entry.color;
+            let name = entry.name.as_deref().unwrap_or("Untitled");
+            let _ = writeln!(gpl, "{:3} {:3} {:3}\t{}", r, g, b, name);
+        }
+        gpl
+    }
+
+    /// Reads a GIMP `.gpl` file.
+    pub fn from_gpl(gpl: &str) -> Result<Self, Error> {
+        let mut lines = gpl.lines();
+        if lines.next().map(str::trim) != Some("GIMP Palette") {
+            return Err(Error::format(
+                "gpl palette doesn't start with 'GIMP Palette'",
+            ));
+        }
+
+        let mut palette = Palette::default();
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("Name:")
+                || line.starts_with("Columns:")
+            {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let mut component = || -> Result<u8, Error> {
+                fields
+                    .next()
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(|| Error::format(format!("invalid gpl palette line '{}'", line)))
+            };
+            let color = Color {
+                r: component()?,
+                g: component()?,
+                b: component()?,
+                a: 255,
+            };
+            let name = fields.collect::<Vec<_>>().join(" ");
+            palette.entries.push(PaletteEntry {
+                color,
+                name: Some(name).filter(|name| !name.is_empty() && name != "Untitled"),
+            });
+        }
+        Ok(palette)
+    }
+
+    /// Writes the palette as a JASC `.pal` file, as used by Paint Shop Pro.
+    ///
+    /// The format has no alpha channel, so colors are written opaque.
+    pub fn to_jasc(&self) -> String {
+        let mut pal = format!("JASC-PAL\r\n0100\r\n{}\r\n", self.len());
+        for Color { r, g, b, .. } in self.colors() {
+            let _ = write!(pal, "{} {} {}\r\n", r, g, b);
+        }
+        pal
+    }
+
+    /// Reads a JASC `.pal` file.
+    ///
+    /// A fourth component on a line is read as alpha.
+    pub fn from_jasc(pal: &str) -> Result<Self, Error> {
+        let mut lines = pal.lines().map(str::trim);
+        if lines.next() != Some("JASC-PAL") {
+            return Err(Error::format("jasc palette doesn't start with 'JASC-PAL'"));
+        }
+        let _version = lines.next();
+        let count: usize = lines
+            .next()
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| Error::format("jasc palette has no color count"))?;
+
+        let mut palette = Palette::default();
+        for line in lines.filter(|line| !line.is_empty()).take(count) {
+            let components = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<u8>, _>>()
+                .ok()
+                .filter(|c| c.len() == 3 || c.len() == 4)
+                .ok_or_else(|| Error::format(format!("invalid jasc palette line '{}'", line)))?;
+            palette.entries.push(PaletteEntry::new(Color {
+                r: components[0],
+                g: components[1],
+                b: components[2],
+                a: components.get(3).copied().unwrap_or(255),
+            }));
+        }
+        if palette.len() != count {
+            return Err(Error::format(format!(
+                "jasc palette should have {} colors but has {}",
+                count,
+                palette.len()
+            )));
+        }
+        Ok(palette)
+    }
+
+    /// Writes the palette as a `.hex` file with one `rrggbb` color per line.
+    ///
+    /// Colors that aren't opaque get their alpha appended as `rrggbbaa`.
+    pub fn to_hex(&self) -> String {
+        let mut hex = String::new();
+        for Color { r, g, b, a } in self.colors() {
+            let _ = write!(hex, "{:02x}{:02x}{:02x}", r, g, b);
+            if a != 255 {
+                let _ = write!(hex, "{:02x}", a);
+            }
+            hex.push('\n');
+        }
+        hex
+    }
+
+    /// Reads a `.hex` file.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let mut palette = Palette::default();
+        for line in hex.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let digits = line.trim_start_matches('#');
+            let invalid = || Error::format(format!("invalid hex palette line '{}'", line));
+            if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+                return Err(invalid());
+            }
+            let component = |i: usize| {
+                digits
+                    .get(i..i + 2)
+                    .map_or(Ok(255), |c| u8::from_str_radix(c, 16))
+                    .map_err(|_| invalid())
+            };
+            palette.entries.push(PaletteEntry::new(Color {
+                r: component(0)?,
+                g: component(2)?,
+                b: component(4)?,
+                a: component(6)?,
+            }));
+        }
+        Ok(palette)
+    }
+
+    /// Writes the palette as an RGBA PNG image one pixel high, with one pixel per color.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut encoder = png::Encoder::new(writer, self.len().max(1) as u32, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut pixels: Vec<u8> = self
+            .colors()
+            .flat_map(|Color { r, g, b, a }| [r, g, b, a])
+            .collect();
+        pixels.resize(pixels.len().max(4), 0);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| Error::format(format!("png: {}", e)))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| Error::format(format!("png: {}", e)))
+    }
+
+    /// Reads every pixel of a PNG image, row by row, as a palette.
+    #[cfg(feature = "png")]
+    pub fn read_png<R: std::io::Read>(reader: R) -> Result<Self, Error> {
+        let image = crate::image::RgbaImage::read_png(reader)?;
+        Ok(Self::from_colors(image.data.chunks_exact(4).map(|p| {
+            Color {
+                r: p[0],
+                g: p[1],
+                b: p[2],
+                a: p[3],
+            }
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let mut palette = Palette::from_colors([
+            Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            Color {
+                r: 163,
+                g: 206,
+                b: 39,
+                a: 255,
+            },
+            Color {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255,
+            },
+        ]);
+        palette.entries[1].name = Some("Lime Green".to_owned());
+        palette
+    }
+
+    #[test]
+    fn test_text_formats() {
+        let palette = palette();
+        assert_eq!(palette, Palette::from_gpl(&palette.to_gpl("Test")).unwrap());
+        assert_eq!(
+            palette.colors().collect::<Vec<_>>(),
+            Palette::from_jasc(&palette.to_jasc())
+                .unwrap()
+                .colors()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("000000\na3ce27\nffffff\n", palette.to_hex());
+        assert_eq!(
+            Some(Color {
+                r: 0xa3,
+                g: 0xce,
+                b: 0x27,
+                a: 0x80
+            }),
+            Palette::from_hex("#000000\n\na3ce2780\n").unwrap().get(1)
+        );
+        assert!(Palette::from_gpl("JASC-PAL\n").is_err());
+        assert!(Palette::from_jasc("JASC-PAL\r\n0100\r\n2\r\n1 2 3\r\n").is_err());
+        assert!(Palette::from_hex("abc\n").is_err());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png_strip() {
+        let palette = palette();
+        let mut png = Vec::new();
+        palette.write_png(&mut png).unwrap();
+        let read = Palette::read_png(png.as_slice()).unwrap();
+        assert_eq!(
+            palette.colors().collect::<Vec<_>>(),
+            read.colors().collect::<Vec<_>>()
+        );
+    }
+}
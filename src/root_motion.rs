@@ -0,0 +1,127 @@
+//! Root motion taken from a marker slice.
+//!
+//! Walk cycles are often drawn with the character moving across the
+//! canvas. Moving the entity along with a marker slice, and drawing each
+//! frame offset by the marker, keeps the feet planted on the ground.
+
+use alloc::vec::Vec;
+
+use crate::animation::tag_sequence;
+use crate::{Frametag, Offset, SpritesheetData};
+
+/// Displacement of a marker slice over one cycle of a tag.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RootMotion {
+    deltas: Vec<Offset>,
+}
+
+impl RootMotion {
+    /// Extracts the motion of the slice named `slice` over `tag`.
+    ///
+    /// The marker is the pivot of each slice key, or the center of its
+    /// bounds. Frames before the first key use the first key and frames
+    /// between keys keep the previous one.
+    ///
+    /// Returns `None` if there is no such slice or it has no key within the tag.
+    pub fn new(sheet: &SpritesheetData, tag: &Frametag, slice: &str) -> Option<Self> {
+        let slice = sheet.meta.slices.iter().find(|s| s.name == slice)?;
+        let markers: Vec<Option<Offset>> = tag_sequence(tag)
+            .into_iter()
+            .map(|frame| slice.key_at(frame).map(|key| key.anchor().into()))
+            .collect();
+        let mut previous = markers.iter().flatten().copied().next()?;
+
+        let mut deltas = Vec::with_capacity(markers.len());
+        for (position, marker) in markers.into_iter().enumerate() {
+            let marker = marker.unwrap_or(previous);
+            deltas.push(if position == 0 {
+                Offset::default()
+            } else {
+                marker - previous
+            });
+            previous = marker;
+        }
+        Some(Self { deltas })
+    }
+
+    /// Displacement when playback moves onto `position` within the cycle.
+    ///
+    /// Wrapping around to the start of the cycle doesn't move, since the
+    /// marker jumps back together with the drawing.
+    pub fn delta(&self, position: usize) -> Offset {
+        self.deltas.get(position).copied().unwrap_or_default()
+    }
+
+    /// Displacements of every position within the cycle.
+    pub fn deltas(&self) -> &[Offset] {
+        &self.deltas
+    }
+
+    /// Displacement over one whole cycle.
+    pub fn cycle_total(&self) -> Offset {
+        self.deltas
+            .iter()
+            .fold(Offset::default(), |total, &delta| total + delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Animation;
+
+    const S: &str = r##"{ "frames": [
+   { "filename": "walk 0.ase", "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false,
+     "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 100 },
+   { "filename": "walk 1.ase", "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false,
+     "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 100 },
+   { "filename": "walk 2.ase", "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false,
+     "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 }, "sourceSize": { "w": 16, "h": 16 }, "duration": 100 }
+ ],
+ "meta": {
+  "app": "http://www.aseprite.org/",
+  "version": "1.2.25-x64",
+  "format": "RGBA8888",
+  "size": { "w": 48, "h": 16 },
+  "scale": "1",
+  "frameTags": [
+   { "name": "walk", "from": 0, "to": 2, "direction": "forward" }
+  ],
+  "slices": [
+   { "name": "root", "color": "#0000ffff", "keys": [
+     { "frame": 0, "bounds": {"x": 2, "y": 14, "w": 2, "h": 2 }, "pivot": {"x": 1, "y": 2 } },
+     { "frame": 1, "bounds": {"x": 5, "y": 14, "w": 2, "h": 2 }, "pivot": {"x": 1, "y": 2 } },
+     { "frame": 2, "bounds": {"x": 9, "y": 13, "w": 2, "h": 2 } }
+   ] }
+  ]
+ }
+}
+"##;
+
+    #[test]
+    fn test_root_motion() {
+        let sheet: SpritesheetData = serde_json::from_str(S).unwrap();
+        let tag = sheet.frame_tag("walk").unwrap();
+        let motion = RootMotion::new(&sheet, tag, "root").unwrap();
+        assert_eq!(
+            &[
+                Offset { x: 0, y: 0 },
+                Offset { x: 3, y: 0 },
+                Offset { x: 4, y: -2 }
+            ],
+            motion.deltas()
+        );
+        assert!(RootMotion::new(&sheet, tag, "missing").is_none());
+
+        let mut animation = Animation::new(&sheet, tag).with_root_motion(motion);
+        animation.update(150);
+        assert_eq!(Offset { x: 3, y: 0 }, animation.take_root_motion());
+        animation.update(100);
+        assert_eq!(Offset { x: 4, y: -2 }, animation.take_root_motion());
+        animation.update(700);
+        assert_eq!(Offset { x: 14, y: -4 }, animation.take_root_motion());
+        animation.update(u32::MAX);
+        let motion = animation.take_root_motion();
+        assert!(motion.x > 100_000_000 && motion.y < -20_000_000);
+    }
+}
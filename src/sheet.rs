@@ -0,0 +1,198 @@
+//! Cutting frames and slices out of sprite sheet images.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::atlas::DedupeReport;
+use crate::image::RgbaImage;
+use crate::{Rect, SpritesheetData};
+
+impl SpritesheetData {
+    /// Cuts frame `index` out of `sheet`, the image named by `meta.image`.
+    ///
+    /// The frame gets its untrimmed `source_size`, with the trimmed pixels
+    /// put back at `sprite_source_size`. Rotated frames are turned back
+    /// upright. `None` if there's no such frame.
+    pub fn extract_frame(&self, sheet: &RgbaImage, index: usize) -> Option<RgbaImage> {
+        let pixels = self.trimmed_frame(sheet, index)?;
+        let data = &self.frames[index].data;
+        let mut image = RgbaImage::new(data.source_size.w, data.source_size.h);
+        let trimmed = data.sprite_source_size;
+        image.draw(&pixels, trimmed.x as i32, trimmed.y as i32, 255);
+        Some(image)
+    }
+
+    /// Points frames with the same pixels, after trimming, at the same
+    /// rectangle of `sheet`.
+    ///
+    /// Each frame keeps its own duration, filename and offset within the
+    /// sprite. The image is left as it is; packing the sheet into an atlas
+    /// leaves out the rectangles nothing points at anymore.
+    pub fn dedupe(&mut self, sheet: &RgbaImage) -> DedupeReport {
+        let before = self.stored_bytes();
+        let mut seen: HashMap<u64, Vec<(usize, RgbaImage)>> = HashMap::new();
+        for i in 0..self.frames.len() {
+            let Some(pixels) = self.trimmed_frame(sheet, i) else {
+                continue;
+            };
+            let same = seen.entry(pixels.pixel_hash()).or_default();
+            match same.iter().find(|(_, other)| *other == pixels) {
+                Some(&(first, _)) => {
+                    let first = self.frames[first].data.clone();
+                    let frame = &mut self.frames[i].data;
+                    frame.frame = first.frame;
+                    frame.rotated = first.rotated;
+                }
+                None => same.push((i, pixels)),
+            }
+        }
+        let rects: HashSet<Rect> = self.frames.iter().map(|f| f.frame).collect();
+        DedupeReport {
+            duplicate_frames: self.frames.len() - rects.len(),
+            bytes_saved: before - self.stored_bytes(),
+        }
+    }
+
+    /// Bytes of RGBA pixels in the rectangles the frames point at.
+    fn stored_bytes(&self) -> u64 {
+        let rects: HashSet<Rect> = self.frames.iter().map(|f| f.frame).collect();
+        rects.iter().map(|r| r.w as u64 * r.h as u64 * 4).sum()
+    }
+
+    /// Upright pixels of frame `index` within `sprite_source_size`.
+    pub(crate) fn trimmed_frame(&self, sheet: &RgbaImage, index: usize) -> Option<RgbaImage> {
+        let data = &self.frames.get(index)?.data;
+        let (frame, trimmed) = (data.frame, data.sprite_source_size);
+        // Rotated frames are stored a quarter turn clockwise, with `frame`
+        // still giving their upright size.
+        let (w, h) = match data.rotated {
+            false => (frame.w, frame.h),
+            true => (frame.h, frame.w),
+        };
+        let mut pixels = sheet.crop(Rect { w, h, ..frame });
+        if data.rotated {
+            pixels = pixels.rotate_counterclockwise();
+        }
+        // Inner padding surrounds the trimmed pixels within `frame`.
+        Some(pixels.crop(Rect {
+            x: frame.w.saturating_sub(trimmed.w) / 2,
+            y: frame.h.saturating_sub(trimmed.h) / 2,
+            w: trimmed.w,
+            h: trimmed.h,
+        }))
+    }
+
+    /// Cuts the bounds of the slice called `name` at frame `index` out of
+    /// `sheet`, `None` if the slice isn't there in that frame.
+    pub fn extract_slice(&self, sheet: &RgbaImage, name: &str, index: usize) -> Option<RgbaImage> {
+        let slice = self.meta.slices.iter().find(|slice| slice.name == name)?;
+        let key = slice.key_at(index as u32)?;
+        Some(self.extract_frame(sheet, index)?.crop(key.bounds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    const SHEET: &str = r##"{
+  "frames": [
+   { "filename": "upright", "frame": { "x": 0, "y": 0, "w": 3, "h": 2 }, "rotated": false, "trimmed": true,
+     "spriteSourceSize": { "x": 1, "y": 2, "w": 3, "h": 2 }, "sourceSize": { "w": 4, "h": 4 }, "duration": 100 },
+   { "filename": "rotated", "frame": { "x": 3, "y": 0, "w": 3, "h": 2 }, "rotated": true, "trimmed": true,
+     "spriteSourceSize": { "x": 1, "y": 2, "w": 3, "h": 2 }, "sourceSize": { "w": 4, "h": 4 }, "duration": 100 }
+  ],
+  "meta": {
+   "app": "http://www.aseprite.org/", "version": "1.3", "format": "RGBA8888",
+   "size": { "w": 5, "h": 3 }, "scale": "1", "frameTags": [], "layers": [],
+   "slices": [
+    { "name": "corner", "color": "#0000ffff", "keys": [{ "frame": 0, "bounds": { "x": 1, "y": 2, "w": 2, "h": 2 } }] }
+   ]
+  }
+}"##;
+
+    fn color(v: u8) -> Color {
+        Color {
+            r: v,
+            g: v,
+            b: v,
+            a: 255,
+        }
+    }
+
+    #[test]
+    fn test_extract_frame() {
+        let sheet: SpritesheetData = serde_json::from_str(SHEET).unwrap();
+        // The upright frame at the left, the same pixels turned clockwise
+        // at the right.
+        let mut image = RgbaImage::new(5, 3);
+        for (x, y, v) in [
+            (0, 0, 1),
+            (1, 0, 2),
+            (2, 0, 3),
+            (0, 1, 4),
+            (1, 1, 5),
+            (2, 1, 6),
+        ] {
+            image.set_pixel(x, y, color(v));
+            image.set_pixel(4 - y, x, color(v));
+        }
+
+        let upright = sheet.extract_frame(&image, 0).unwrap();
+        assert_eq!((4, 4), (upright.width, upright.height));
+        assert_eq!(Some(color(1)), upright.pixel(1, 2));
+        assert_eq!(Some(color(6)), upright.pixel(3, 3));
+        assert_eq!(Some(0), upright.pixel(0, 0).map(|c| c.a));
+        assert_eq!(Some(upright.clone()), sheet.extract_frame(&image, 1));
+        assert_eq!(None, sheet.extract_frame(&image, 2));
+
+        let slice = sheet.extract_slice(&image, "corner", 1).unwrap();
+        assert_eq!(
+            upright.crop(Rect {
+                x: 1,
+                y: 2,
+                w: 2,
+                h: 2
+            }),
+            slice
+        );
+        assert_eq!(None, sheet.extract_slice(&image, "nothing", 0));
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let mut sheet: SpritesheetData = serde_json::from_str(SHEET).unwrap();
+        let mut image = RgbaImage::new(5, 3);
+        for (x, y) in [(0, 0), (2, 1)] {
+            image.set_pixel(x, y, color(1));
+            image.set_pixel(4 - y, x, color(1));
+        }
+        let frames: Vec<_> = (0..2).map(|i| sheet.extract_frame(&image, i)).collect();
+
+        let report = sheet.dedupe(&image);
+        assert_eq!(1, report.duplicate_frames);
+        assert_eq!(3 * 2 * 4, report.bytes_saved);
+        assert_eq!(sheet.frames[0].frame, sheet.frames[1].frame);
+        assert!(!sheet.frames[1].rotated);
+        assert_eq!("rotated", sheet.frames[1].filename);
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(*frame, sheet.extract_frame(&image, i));
+        }
+        assert_eq!(
+            DedupeReport::default().bytes_saved,
+            sheet.dedupe(&image).bytes_saved
+        );
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_extract_exported_frames() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let ase = crate::ase::AseFile::from_bytes(file.src_aseprite).unwrap();
+        let sheet: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let image = RgbaImage::read_png(file.array_png).unwrap();
+        for i in 0..ase.frames.len() {
+            assert_eq!(ase.render_frame(i), sheet.extract_frame(&image, i).unwrap());
+        }
+    }
+}
@@ -0,0 +1,659 @@
+//! Exporting .ase files as sprite sheets, as Aseprite's `--sheet` does.
+//!
+//! The options follow Aseprite's command-line ones, so sheets can be
+//! built where Aseprite isn't installed.
+
+use crate::ase::{AseFile, LayerKind};
+use crate::atlas::MaxRects;
+use crate::image::RgbaImage;
+use crate::{
+    Dimensions, Error, Frame, FrameData, Frametag, Layer, Metadata, Rect, Slice, SliceKey,
+    SpritesheetData,
+};
+
+/// How frames are laid out on the sheet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum SheetType {
+    /// In a single row.
+    #[default]
+    Horizontal,
+    /// In a single column.
+    Vertical,
+    /// In a grid, filled row by row.
+    Rows,
+    /// In a grid, filled column by column.
+    Columns,
+    /// Packed close together, with MaxRects.
+    Packed,
+}
+
+/// What gets exported and how, named after Aseprite's options.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct SheetOptions {
+    /// `--sheet-type`.
+    pub sheet_type: SheetType,
+    /// `--sheet-columns`, for [`SheetType::Rows`]. Makes the sheet about
+    /// square when `None`.
+    pub columns: Option<u32>,
+    /// `--sheet-rows`, for [`SheetType::Columns`]. Makes the sheet about
+    /// square when `None`.
+    pub rows: Option<u32>,
+    /// `--layer`: only these layers, or the visible ones when empty.
+    pub layers: Vec<String>,
+    /// `--ignore-layer`: layers left out.
+    pub ignore_layers: Vec<String>,
+    /// `--tag`: only the frames of this tag.
+    pub tag: Option<String>,
+    /// `--split-layers`: every layer gets its own frames.
+    pub split_layers: bool,
+    /// `--split-tags`: every tag gets its own frames, and untagged frames
+    /// are left out.
+    pub split_tags: bool,
+    /// `--trim`: transparent borders are cut off each frame.
+    pub trim: bool,
+    /// `--extrude`: the edges of each frame are repeated a pixel outwards.
+    pub extrude: bool,
+    /// `--border-padding`: pixels around the whole sheet.
+    pub border_padding: u32,
+    /// `--shape-padding`: pixels between frames.
+    pub shape_padding: u32,
+    /// `--inner-padding`: pixels around each frame, inside its rectangle.
+    pub inner_padding: u32,
+    /// `--filename-format`, picked the way Aseprite does when `None`.
+    ///
+    /// `{title}`, `{extension}`, `{layer}`, `{tag}`, `{frame}`,
+    /// `{tagframe}` and `{duration}` are filled in. Digits after `frame`
+    /// or `tagframe` pad the number to as many digits and start counting
+    /// from their value, so `{frame01}` gives `01`, `02` and so on.
+    pub filename_format: Option<String>,
+    /// `--list-tags`: tags go in the metadata.
+    pub list_tags: bool,
+    /// `--list-layers`: layers go in the metadata.
+    pub list_layers: bool,
+    /// `--list-slices`: slices go in the metadata.
+    pub list_slices: bool,
+}
+
+/// A sheet image along with the data describing it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct SpriteSheet {
+    /// All the frames.
+    pub image: RgbaImage,
+    /// `meta.image` is `None` until the image is given a file name.
+    pub data: SpritesheetData,
+}
+
+/// Frame of the sprite, once for every tag it's exported as part of.
+struct Entry<'a> {
+    frame: usize,
+    tag: Option<&'a Frametag>,
+}
+
+impl Entry<'_> {
+    fn tag_frame(&self) -> usize {
+        self.frame - self.tag.map_or(0, |tag| tag.from as usize)
+    }
+}
+
+/// Group each layer is in.
+fn parents(file: &AseFile) -> Vec<Option<usize>> {
+    let mut groups: Vec<usize> = Vec::new();
+    file.layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer)| {
+            groups.truncate(layer.child_level as usize);
+            let parent = groups.last().copied();
+            groups.push(i);
+            parent
+        })
+        .collect()
+}
+
+/// Fills in the `{name}` parts of an Aseprite filename format.
+fn format_filename(format: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filename = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        filename.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        match value(&rest[1..end]) {
+            Some(value) => filename.push_str(&value),
+            None => filename.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    filename.push_str(rest);
+    filename
+}
+
+/// `{frame}` or `{tagframe}` with any padding and start of `token`.
+fn frame_number(token: &str, name: &str, number: usize) -> Option<String> {
+    let digits = token.strip_prefix(name)?;
+    if digits.is_empty() {
+        return Some(number.to_string());
+    }
+    let start: usize = digits.parse().ok()?;
+    Some(format!("{:0width$}", number + start, width = digits.len()))
+}
+
+/// Places of the boxes, and the size they take together.
+fn layout(options: &SheetOptions, boxes: &[(u32, u32)]) -> (Vec<(u32, u32)>, Dimensions) {
+    let n = boxes.len() as u32;
+    let padding = options.shape_padding;
+    let square = || ((n as f64).sqrt().ceil() as u32).max(1);
+    let cell = (
+        boxes.iter().map(|b| b.0).max().unwrap_or(0),
+        boxes.iter().map(|b| b.1).max().unwrap_or(0),
+    );
+    let mut places = Vec::with_capacity(boxes.len());
+    match options.sheet_type {
+        SheetType::Horizontal | SheetType::Vertical => {
+            let horizontal = options.sheet_type == SheetType::Horizontal;
+            let mut offset = 0;
+            for &(w, h) in boxes {
+                places.push(match horizontal {
+                    true => (offset, 0),
+                    false => (0, offset),
+                });
+                offset += match horizontal {
+                    true => w,
+                    false => h,
+                } + padding;
+            }
+        }
+        SheetType::Rows => {
+            let columns = options.columns.unwrap_or_else(square).max(1);
+            for i in 0..n {
+                places.push((
+                    i % columns * (cell.0 + padding),
+                    i / columns * (cell.1 + padding),
+                ));
+            }
+        }
+        SheetType::Columns => {
+            let rows = options.rows.unwrap_or_else(square).max(1);
+            for i in 0..n {
+                places.push((i / rows * (cell.0 + padding), i % rows * (cell.1 + padding)));
+            }
+        }
+        SheetType::Packed => {
+            let area: u64 = boxes.iter().map(|&(w, h)| w as u64 * h as u64).sum();
+            let mut side = ((area as f64).sqrt().ceil() as u32).max(cell.0).max(cell.1);
+            let mut order: Vec<usize> = (0..boxes.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(boxes[i].0.max(boxes[i].1)));
+            // Every box takes its padding to the right and bottom, which
+            // the space gets too so the last boxes fit.
+            places = loop {
+                let mut space = MaxRects::new(side + padding, side + padding);
+                let mut places = vec![(0, 0); boxes.len()];
+                let fits = order.iter().all(|&i| {
+                    let (w, h) = boxes[i];
+                    if w == 0 || h == 0 {
+                        return true;
+                    }
+                    space
+                        .insert(w + padding, h + padding)
+                        .map(|rect| places[i] = (rect.x, rect.y))
+                        .is_some()
+                });
+                if fits {
+                    break places;
+                }
+                side += side / 8 + 1;
+            };
+        }
+    }
+    let size = Dimensions {
+        w: places
+            .iter()
+            .zip(boxes)
+            .map(|(p, b)| p.0 + b.0)
+            .max()
+            .unwrap_or(0),
+        h: places
+            .iter()
+            .zip(boxes)
+            .map(|(p, b)| p.1 + b.1)
+            .max()
+            .unwrap_or(0),
+    };
+    (places, size)
+}
+
+/// Repeats the edge pixels of `rect` a pixel outwards.
+fn extrude(image: &mut RgbaImage, rect: Rect) {
+    if rect.w == 0 || rect.h == 0 || rect.x == 0 || rect.y == 0 {
+        return;
+    }
+    let (right, bottom) = (rect.x + rect.w - 1, rect.y + rect.h - 1);
+    for y in rect.y..=bottom {
+        for (from, to) in [(rect.x, rect.x - 1), (right, right + 1)] {
+            if let Some(color) = image.pixel(from, y) {
+                image.set_pixel(to, y, color);
+            }
+        }
+    }
+    for x in rect.x - 1..=right + 1 {
+        for (from, to) in [(rect.y, rect.y - 1), (bottom, bottom + 1)] {
+            if let Some(color) = image.pixel(x, from) {
+                image.set_pixel(x, to, color);
+            }
+        }
+    }
+}
+
+/// Keys of `slice` at each entry, keeping only the ones that change.
+fn remap_slice(slice: &Slice, entries: &[Entry]) -> Slice {
+    let mut keys: Vec<SliceKey> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let Some(key) = slice.key_at(entry.frame as u32) else {
+            continue;
+        };
+        let same = keys.last().is_some_and(|last| {
+            (last.bounds, last.pivot, last.center) == (key.bounds, key.pivot, key.center)
+        });
+        if !same {
+            keys.push(SliceKey {
+                frame: i as u32,
+                ..key.clone()
+            });
+        }
+    }
+    Slice {
+        keys,
+        ..slice.clone()
+    }
+}
+
+impl SpriteSheet {
+    /// Exports `file`, whose file name is `name`, as a sheet.
+    ///
+    /// Tags and slice keys count the exported frames, or with split
+    /// layers the frames of each layer. Fails if a layer or tag named in
+    /// the options isn't in the file.
+    pub fn from_ase(file: &AseFile, name: &str, options: &SheetOptions) -> Result<Self, Error> {
+        let (title, extension) = match name.rsplit_once('.') {
+            Some((title, extension)) => (title, extension),
+            None => (name, ""),
+        };
+        let frame_count = file.frames.len();
+        for tag in &file.tags {
+            if tag.from > tag.to || tag.to as usize >= frame_count {
+                return Err(Error::format(format!(
+                    "tag {} covers frames {} to {} of {}",
+                    tag.name, tag.from, tag.to, frame_count
+                )));
+            }
+        }
+
+        // Layers are shown along with the groups they're in.
+        let parents = parents(file);
+        let listed = |names: &[String], layer: usize| {
+            let mut layer = Some(layer);
+            while let Some(i) = layer {
+                if names.contains(&file.layers[i].name) {
+                    return true;
+                }
+                layer = parents[i];
+            }
+            false
+        };
+        for name in options.layers.iter().chain(&options.ignore_layers) {
+            if !file.layers.iter().any(|layer| layer.name == *name) {
+                return Err(Error::format(format!("there's no layer called {}", name)));
+            }
+        }
+        let shown: Vec<bool> = file
+            .visible_layers()
+            .into_iter()
+            .enumerate()
+            .map(|(i, visible)| {
+                let chosen = match options.layers.is_empty() {
+                    true => visible,
+                    false => listed(&options.layers, i),
+                };
+                chosen && !listed(&options.ignore_layers, i)
+            })
+            .collect();
+
+        let tags: Vec<&Frametag> = match &options.tag {
+            Some(name) => vec![file
+                .tags
+                .iter()
+                .find(|tag| tag.name == *name)
+                .ok_or_else(|| Error::format(format!("there's no tag called {}", name)))?],
+            None if options.split_tags => file.tags.iter().collect(),
+            None => Vec::new(),
+        };
+        let entries: Vec<Entry> = match tags.is_empty() {
+            true => (0..frame_count)
+                .map(|frame| Entry {
+                    frame,
+                    // The innermost tag, for filenames.
+                    tag: file
+                        .tags
+                        .iter()
+                        .filter(|tag| (tag.from as usize..=tag.to as usize).contains(&frame))
+                        .min_by_key(|tag| tag.to - tag.from),
+                })
+                .collect(),
+            false => tags
+                .iter()
+                .flat_map(|&tag| {
+                    (tag.from as usize..=tag.to as usize).map(move |frame| Entry {
+                        frame,
+                        tag: Some(tag),
+                    })
+                })
+                .collect(),
+        };
+
+        let split_tags = options.split_tags;
+        let format = options.filename_format.as_deref().unwrap_or(
+            match (options.split_layers, split_tags) {
+                (false, false) => "{title} {frame}.{extension}",
+                (true, false) => "{title} ({layer}) {frame}.{extension}",
+                (false, true) => "{title} #{tag} {frame}.{extension}",
+                (true, true) => "{title} ({layer}) #{tag} {frame}.{extension}",
+            },
+        );
+        let layers: Vec<Option<usize>> = match options.split_layers {
+            true => (0..file.layers.len())
+                .filter(|&i| shown[i] && file.layers[i].kind != LayerKind::Group)
+                .map(Some)
+                .collect(),
+            false => vec![None],
+        };
+
+        let canvas = Rect {
+            x: 0,
+            y: 0,
+            w: file.header.width as u32,
+            h: file.header.height as u32,
+        };
+        let mut cells = Vec::new();
+        for &layer in &layers {
+            let shown: Vec<bool> = match layer {
+                Some(layer) => (0..file.layers.len()).map(|i| i == layer).collect(),
+                None => shown.clone(),
+            };
+            for entry in &entries {
+                let image = file.render_layers(entry.frame, &shown);
+                let trimmed = match options.trim {
+                    true => image.opaque_bounds(),
+                    false => canvas,
+                };
+                let duration = file.frames[entry.frame].duration as u32;
+                let filename = format_filename(format, |token| match token {
+                    "title" => Some(title.to_string()),
+                    "extension" => Some(extension.to_string()),
+                    "layer" => Some(layer.map_or("", |i| &file.layers[i].name).to_string()),
+                    "tag" => Some(entry.tag.map_or("", |tag| &tag.name).to_string()),
+                    "duration" => Some(duration.to_string()),
+                    token if token.starts_with("tagframe") => {
+                        frame_number(token, "tagframe", entry.tag_frame())
+                    }
+                    token => frame_number(token, "frame", entry.frame),
+                });
+                cells.push((image, trimmed, filename, duration));
+            }
+        }
+
+        let margin = options.inner_padding + options.extrude as u32;
+        let boxes: Vec<(u32, u32)> = cells
+            .iter()
+            .map(|(_, trimmed, _, _)| (trimmed.w + 2 * margin, trimmed.h + 2 * margin))
+            .collect();
+        let (places, size) = layout(options, &boxes);
+        let border = options.border_padding;
+        let size = Dimensions {
+            w: size.w + 2 * border,
+            h: size.h + 2 * border,
+        };
+        let mut image = RgbaImage::new(size.w, size.h);
+        let mut frames = Vec::with_capacity(cells.len());
+        for ((pixels, trimmed, filename, duration), (x, y)) in cells.into_iter().zip(places) {
+            let inner = options.inner_padding;
+            let frame = Rect {
+                x: border + x + options.extrude as u32,
+                y: border + y + options.extrude as u32,
+                w: trimmed.w + 2 * inner,
+                h: trimmed.h + 2 * inner,
+            };
+            let pixels = pixels.crop(trimmed);
+            image.draw(
+                &pixels,
+                (frame.x + inner) as i32,
+                (frame.y + inner) as i32,
+                255,
+            );
+            if options.extrude {
+                extrude(&mut image, frame);
+            }
+            frames.push(Frame {
+                filename,
+                data: FrameData {
+                    frame,
+                    rotated: false,
+                    trimmed: trimmed != canvas,
+                    sprite_source_size: trimmed,
+                    source_size: Dimensions {
+                        w: canvas.w,
+                        h: canvas.h,
+                    },
+                    duration,
+                },
+            });
+        }
+
+        let mut meta = Metadata {
+            app: env!("CARGO_PKG_REPOSITORY").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            format: "RGBA8888".to_string(),
+            size,
+            scale: "1".to_string(),
+            image: None,
+            frame_tags: Vec::new(),
+            layers: Vec::new(),
+            slices: Vec::new(),
+        };
+        if options.list_tags {
+            meta.frame_tags = match tags.is_empty() {
+                true => file.tags.clone(),
+                false => {
+                    let mut from = 0;
+                    tags.iter()
+                        .map(|&tag| {
+                            let count = tag.to - tag.from;
+                            from += count + 1;
+                            Frametag {
+                                from: from - count - 1,
+                                to: from - 1,
+                                ..tag.clone()
+                            }
+                        })
+                        .collect()
+                }
+            };
+        }
+        if options.list_layers {
+            let exported = |i: usize| match options.split_layers {
+                true => layers.contains(&Some(i)),
+                false => shown[i] && file.layers[i].kind != LayerKind::Group,
+            };
+            meta.layers = file
+                .layers
+                .iter()
+                .enumerate()
+                .filter(|&(i, layer)| match layer.kind {
+                    // Groups are listed when they hold exported layers.
+                    LayerKind::Group => (0..file.layers.len()).any(|j| {
+                        let mut parent = parents[j];
+                        while parent.is_some_and(|p| p != i) {
+                            parent = parents[parent.unwrap_or(i)];
+                        }
+                        exported(j) && parent == Some(i)
+                    }),
+                    _ => exported(i),
+                })
+                .map(|(i, layer)| {
+                    let group = layer.kind == LayerKind::Group;
+                    Layer {
+                        name: layer.name.clone(),
+                        group: parents[i].map(|p| file.layers[p].name.clone()),
+                        opacity: (!group).then_some(layer.opacity as u32),
+                        blend_mode: (!group).then_some(layer.blend_mode),
+                        color: layer.user_data.color,
+                        data: layer.user_data.text.clone(),
+                    }
+                })
+                .collect();
+        }
+        if options.list_slices {
+            meta.slices = match tags.is_empty() {
+                true => file.slices.clone(),
+                false => file
+                    .slices
+                    .iter()
+                    .map(|slice| remap_slice(slice, &entries))
+                    .collect(),
+            };
+        }
+        Ok(SpriteSheet {
+            image,
+            data: SpritesheetData { frames, meta },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complex() -> AseFile {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        AseFile::from_bytes(file.src_aseprite).unwrap()
+    }
+
+    #[test]
+    fn test_like_aseprite() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let expected: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        let options = SheetOptions {
+            list_tags: true,
+            list_layers: true,
+            list_slices: true,
+            ..SheetOptions::default()
+        };
+        let sheet = SpriteSheet::from_ase(&complex(), "complex.aseprite", &options).unwrap();
+        assert_eq!(expected.frames, sheet.data.frames);
+        assert_eq!(expected.meta.size, sheet.data.meta.size);
+        // Aseprite 1.2 didn't export tag colors.
+        let mut tags = sheet.data.meta.frame_tags.clone();
+        tags.iter_mut().for_each(|tag| tag.color = None);
+        assert_eq!(expected.meta.frame_tags, tags);
+        assert_eq!(expected.meta.slices, sheet.data.meta.slices);
+        for i in 0..sheet.data.frames.len() {
+            assert_eq!(
+                complex().render_frame(i),
+                sheet.data.extract_frame(&sheet.image, i).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let ase = complex();
+        let options = SheetOptions {
+            sheet_type: SheetType::Packed,
+            tag: Some("ping-pong".to_string()),
+            split_tags: true,
+            split_layers: true,
+            trim: true,
+            extrude: true,
+            border_padding: 2,
+            shape_padding: 1,
+            inner_padding: 1,
+            filename_format: Some("{layer}/{tag}_{tagframe01}.{frame000}".to_string()),
+            list_tags: true,
+            list_layers: true,
+            ..SheetOptions::default()
+        };
+        let sheet = SpriteSheet::from_ase(&ase, "complex.aseprite", &options).unwrap();
+        let data = &sheet.data;
+        // Groups have no opacity of their own.
+        let layers: Vec<&str> = data
+            .meta
+            .layers
+            .iter()
+            .filter(|l| l.opacity.is_some())
+            .map(|l| l.name.as_str())
+            .collect();
+        assert_eq!(2 * layers.len(), data.frames.len());
+        assert_eq!(
+            format!("{}/ping-pong_02.003", layers[0]),
+            data.frames[1].filename
+        );
+        let tag = &data.meta.frame_tags[0];
+        assert_eq!(("ping-pong", 0, 1), (tag.name.as_str(), tag.from, tag.to));
+
+        let mut taken: Vec<Rect> = Vec::new();
+        for (i, frame) in data.frames.iter().enumerate() {
+            let rect = frame.frame;
+            // Extruded edges and padding keep frames apart.
+            let outer = Rect {
+                x: rect.x - 1,
+                y: rect.y - 1,
+                w: rect.w + 3,
+                h: rect.h + 3,
+            };
+            assert!(outer.x >= 2 && outer.y >= 2);
+            assert!(taken.iter().all(|t| {
+                t.x + t.w <= outer.x
+                    || outer.x + outer.w <= t.x
+                    || t.y + t.h <= outer.y
+                    || outer.y + outer.h <= t.y
+            }));
+            taken.push(outer);
+
+            let layer = ase.layers.iter().position(|l| l.name == layers[i / 2]);
+            let shown: Vec<bool> = (0..ase.layers.len()).map(|l| Some(l) == layer).collect();
+            let expected = ase.render_layers(2 + i % 2, &shown);
+            assert_eq!(expected, data.extract_frame(&sheet.image, i).unwrap());
+        }
+
+        let missing = SheetOptions {
+            tag: Some("nothing".to_string()),
+            ..SheetOptions::default()
+        };
+        assert!(SpriteSheet::from_ase(&ase, "complex.aseprite", &missing).is_err());
+    }
+
+    #[test]
+    fn test_layouts() {
+        let boxes = [(3, 2), (1, 4), (2, 2)];
+        let mut options = SheetOptions {
+            shape_padding: 1,
+            ..SheetOptions::default()
+        };
+        let expected = [
+            (SheetType::Horizontal, vec![(0, 0), (4, 0), (6, 0)], (8, 4)),
+            (SheetType::Vertical, vec![(0, 0), (0, 3), (0, 8)], (3, 10)),
+            (SheetType::Rows, vec![(0, 0), (4, 0), (0, 5)], (5, 7)),
+            (SheetType::Columns, vec![(0, 0), (0, 5), (4, 0)], (6, 9)),
+        ];
+        for (sheet_type, places, size) in expected {
+            options.sheet_type = sheet_type;
+            let (actual, dimensions) = layout(&options, &boxes);
+            assert_eq!(places, actual, "{:?}", sheet_type);
+            assert_eq!(size, (dimensions.w, dimensions.h), "{:?}", sheet_type);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Drawing images in a terminal, for looking at sprites over SSH or in
+//! build logs.
+//!
+//! Every character is two pixels stacked on top of each other, drawn as
+//! the half block `▀` with 24-bit ANSI colors, so pixels come out about
+//! square. Terminals without truecolor support show the wrong colors.
+
+use std::fmt::Write as _;
+
+use crate::image::RgbaImage;
+use crate::{Color, Rect};
+
+/// Upper half block, the top pixel in the foreground color.
+const UPPER_HALF: char = '▀';
+/// Lower half block, for when only the bottom pixel is shown.
+const LOWER_HALF: char = '▄';
+
+/// How an image is drawn.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct TerminalOptions {
+    /// How many times larger than the image the drawing is, 1 or more.
+    pub scale: u32,
+    /// Color drawn behind the image. If `None`, pixels less than half
+    /// opaque show the terminal's own background and the others are
+    /// drawn opaque.
+    pub background: Option<Color>,
+    /// Rectangles of the image to draw a line around, such as slice
+    /// bounds. The lines go inside the rectangles, after scaling.
+    pub outlines: Vec<Rect>,
+    /// Color of the outlines.
+    pub outline_color: Color,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            background: None,
+            outlines: Vec::new(),
+            outline_color: Color {
+                r: 255,
+                g: 0,
+                b: 255,
+                a: 255,
+            },
+        }
+    }
+}
+
+/// Draws a one pixel line just inside `rect`.
+fn outline(image: &mut RgbaImage, rect: Rect, color: Color) {
+    if rect.w == 0 || rect.h == 0 {
+        return;
+    }
+    let right = rect.x.saturating_add(rect.w - 1);
+    let bottom = rect.y.saturating_add(rect.h - 1);
+    // Lines are cut off at the edge of the image.
+    for x in rect.x..=right.min(image.width.saturating_sub(1)) {
+        image.set_pixel(x, rect.y, color);
+        image.set_pixel(x, bottom, color);
+    }
+    for y in rect.y..=bottom.min(image.height.saturating_sub(1)) {
+        image.set_pixel(rect.x, y, color);
+        image.set_pixel(right, y, color);
+    }
+}
+
+/// `image` as lines of text with ANSI escape codes, each ending with a
+/// reset and a newline.
+pub fn render(image: &RgbaImage, options: &TerminalOptions) -> String {
+    let scale = options.scale.max(1);
+    let mut image = match options.background {
+        Some(color) => {
+            let mut background = RgbaImage::filled(image.width, image.height, color);
+            background.draw(image, 0, 0, 255);
+            background
+        }
+        None => image.clone(),
+    };
+    if scale > 1 {
+        image = image.scale(scale);
+    }
+    for rect in &options.outlines {
+        let rect = Rect {
+            x: rect.x.saturating_mul(scale),
+            y: rect.y.saturating_mul(scale),
+            w: rect.w.saturating_mul(scale),
+            h: rect.h.saturating_mul(scale),
+        };
+        outline(&mut image, rect, options.outline_color);
+    }
+
+    let shown = |x: u32, y: u32| image.pixel(x, y).filter(|c| c.a >= 128);
+    let mut text = String::new();
+    for y in (0..image.height).step_by(2) {
+        for x in 0..image.width {
+            let _ = match (shown(x, y), shown(x, y + 1)) {
+                (Some(top), Some(bottom)) => write!(
+                    text,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m{}",
+                    top.r, top.g, top.b, bottom.r, bottom.g, bottom.b, UPPER_HALF
+                ),
+                (Some(top), None) => write!(
+                    text,
+                    "\x1b[0;38;2;{};{};{}m{}",
+                    top.r, top.g, top.b, UPPER_HALF
+                ),
+                (None, Some(bottom)) => write!(
+                    text,
+                    "\x1b[0;38;2;{};{};{}m{}",
+                    bottom.r, bottom.g, bottom.b, LOWER_HALF
+                ),
+                (None, None) => write!(text, "\x1b[0m "),
+            };
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    const BLUE: Color = Color {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+
+    #[test]
+    fn test_render() {
+        let mut image = RgbaImage::new(2, 3);
+        image.set_pixel(0, 0, RED);
+        image.set_pixel(0, 1, BLUE);
+        image.set_pixel(1, 1, BLUE);
+        image.set_pixel(1, 2, Color { a: 100, ..RED });
+        let text = render(&image, &TerminalOptions::default());
+        assert_eq!(
+            "\x1b[38;2;255;0;0;48;2;0;0;255m▀\x1b[0;38;2;0;0;255m▄\x1b[0m\n\
+             \x1b[0m \x1b[0m \x1b[0m\n",
+            text
+        );
+
+        let options = TerminalOptions {
+            scale: 2,
+            background: Some(BLUE),
+            ..TerminalOptions::default()
+        };
+        let text = render(&image, &options);
+        assert_eq!(3, text.lines().count());
+        assert_eq!(4, text.lines().last().unwrap().matches('▀').count());
+    }
+
+    #[test]
+    fn test_outlines() {
+        let options = TerminalOptions {
+            scale: 2,
+            outlines: vec![Rect {
+                x: 1,
+                y: 0,
+                w: 2,
+                h: 1,
+            }],
+            outline_color: RED,
+            ..TerminalOptions::default()
+        };
+        let text = render(&RgbaImage::new(4, 1), &options);
+        let red = "\x1b[38;2;255;0;0;48;2;255;0;0m▀";
+        assert_eq!(
+            format!(
+                "\x1b[0m \x1b[0m {}{}{}{}\x1b[0m \x1b[0m \x1b[0m\n",
+                red, red, red, red
+            ),
+            text
+        );
+
+        // Outlines running off the image are cut off.
+        let options = TerminalOptions {
+            scale: 2,
+            outlines: vec![
+                Rect {
+                    x: u32::MAX,
+                    y: 0,
+                    w: u32::MAX,
+                    h: 1,
+                },
+                Rect {
+                    x: 1,
+                    y: 0,
+                    w: u32::MAX,
+                    h: 1,
+                },
+            ],
+            outline_color: RED,
+            ..TerminalOptions::default()
+        };
+        let text = render(&RgbaImage::new(2, 1), &options);
+        assert_eq!(format!("\x1b[0m \x1b[0m {}{}\x1b[0m\n", red, red), text);
+    }
+}
@@ -0,0 +1,462 @@
+//! Exporting tilemap layers and slices to the JSON formats of the
+//! [Tiled](https://www.mapeditor.org/) map editor.
+//!
+//! A frame of a sprite becomes a map with a tile layer per tilemap layer
+//! and an object layer holding the slices. Each tileset becomes a
+//! separate tileset file whose image is a single column of tiles, which
+//! [`tileset_image`] draws.
+
+use serde_json::{json, Value};
+
+use crate::ase::{AseFile, LayerKind, PropertyValue, Tileset, UserData};
+use crate::image::RgbaImage;
+use crate::{Color, Error};
+
+/// Tiled's global tile id bit for a horizontally flipped tile.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+/// Tiled's global tile id bit for a vertically flipped tile.
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/// Tiled's global tile id bit for a tile flipped along its diagonal.
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+
+const VERSION: &str = "1.10";
+
+/// Tiled writes colors as `#aarrggbb`.
+fn tiled_color(color: Color) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        color.a, color.r, color.g, color.b
+    )
+}
+
+fn property(name: &str, value: &PropertyValue) -> Value {
+    let (kind, value) = match value {
+        PropertyValue::Bool(v) => ("bool", json!(v)),
+        PropertyValue::I8(v) => ("int", json!(v)),
+        PropertyValue::U8(v) => ("int", json!(v)),
+        PropertyValue::I16(v) => ("int", json!(v)),
+        PropertyValue::U16(v) => ("int", json!(v)),
+        PropertyValue::I32(v) => ("int", json!(v)),
+        PropertyValue::U32(v) => ("int", json!(v)),
+        PropertyValue::I64(v) => ("int", json!(v)),
+        PropertyValue::U64(v) => ("int", json!(v)),
+        PropertyValue::Fixed(v) => ("float", json!(PropertyValue::fixed_to_f64(*v))),
+        PropertyValue::F32(v) => ("float", json!(v)),
+        PropertyValue::F64(v) => ("float", json!(v)),
+        PropertyValue::String(v) => ("string", json!(v)),
+        // Tiled has no equivalent of the rest, so they're kept as JSON text.
+        other => ("string", json!(property_json(other).to_string())),
+    };
+    json!({ "name": name, "type": kind, "value": value })
+}
+
+fn property_json(value: &PropertyValue) -> Value {
+    match value {
+        PropertyValue::Point { x, y } => json!({ "x": x, "y": y }),
+        PropertyValue::Size { w, h } => json!({ "w": w, "h": h }),
+        PropertyValue::Rect { x, y, w, h } => json!({ "x": x, "y": y, "w": w, "h": h }),
+        PropertyValue::Vector(values) => values.iter().map(property_json).collect(),
+        PropertyValue::Map(map) => map
+            .iter()
+            .map(|(k, v)| (k.clone(), property_json(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        PropertyValue::Uuid(uuid) => json!(uuid
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()),
+        other => property("", other)["value"].clone(),
+    }
+}
+
+/// Tiled properties for the text, color and user properties of user data.
+fn properties(user_data: &UserData) -> Vec<Value> {
+    let mut properties = Vec::new();
+    if let Some(text) = &user_data.text {
+        properties.push(json!({ "name": "data", "type": "string", "value": text }));
+    }
+    if let Some(color) = user_data.color {
+        properties.push(json!({ "name": "color", "type": "color", "value": tiled_color(color) }));
+    }
+    if let Some(user) = user_data.user_properties() {
+        properties.extend(user.iter().map(|(name, value)| property(name, value)));
+    }
+    properties
+}
+
+/// First global tile id of each tileset, in the order of [`AseFile::tilesets`].
+/// Tiles are counted by the header, as linked tilesets have no images.
+fn first_gids(file: &AseFile) -> Vec<u32> {
+    let mut next = 1u32;
+    file.tilesets
+        .iter()
+        .map(|tileset| {
+            let first = next;
+            next = next.saturating_add(tileset.tile_count);
+            first
+        })
+        .collect()
+}
+
+/// Tiled JSON map of the tilemap layers and slices of a frame.
+///
+/// The map's tile size is the one of the tilesets its tilemap layers use
+/// and its size covers the canvas. A Tiled map has a single tile size, so
+/// layers using tilesets with different tile sizes are an error.
+/// `tileset_source` gives the path each tileset file is referenced by,
+/// such as `"tiles.tsj"`. Empty tiles and tile ids the tileset doesn't
+/// have become gid 0.
+pub fn map(
+    file: &AseFile,
+    frame: usize,
+    tileset_source: impl Fn(&Tileset) -> String,
+) -> Result<String, Error> {
+    let tile_size = |t: &Tileset| (t.tile_width.max(1) as u32, t.tile_height.max(1) as u32);
+    let mut used = file.layers.iter().filter_map(|layer| match layer.kind {
+        LayerKind::Tilemap(id) => file.tilesets.iter().find(|t| t.id == id),
+        _ => None,
+    });
+    let (tile_width, tile_height) = used
+        .next()
+        .or(file.tilesets.first())
+        .map_or((16, 16), tile_size);
+    if let Some(other) = used.find(|t| tile_size(t) != (tile_width, tile_height)) {
+        let (w, h) = tile_size(other);
+        return Err(Error::format(format!(
+            "tileset {:?} has {}x{} tiles but the map has {}x{} ones",
+            other.name, w, h, tile_width, tile_height
+        )));
+    }
+    let width = (file.header.width as u32).div_ceil(tile_width);
+    let height = (file.header.height as u32).div_ceil(tile_height);
+    let first_gids = first_gids(file);
+    let visible = file.visible_layers();
+    let layer_opacity = file.header.flags & crate::ase::Header::FLAG_LAYER_OPACITY != 0;
+
+    let mut layers = Vec::new();
+    let mut next_id = 1;
+    for (index, layer) in file.layers.iter().enumerate() {
+        let LayerKind::Tilemap(tileset_id) = layer.kind else {
+            continue;
+        };
+        let Some(tileset_index) = file.tilesets.iter().position(|t| t.id == tileset_id) else {
+            continue;
+        };
+        let tileset = &file.tilesets[tileset_index];
+        let (tw, th) = (
+            tileset.tile_width.max(1) as i32,
+            tileset.tile_height.max(1) as i32,
+        );
+        let mut data = vec![0u32; width as usize * height as usize];
+        let (mut offset_x, mut offset_y) = (0, 0);
+        let mut opacity = if layer_opacity { layer.opacity } else { 255 };
+
+        if let (Some(cel), Some(tilemap)) = (file.cel(frame, index), file.tilemap(frame, index)) {
+            opacity = (opacity as u32 * cel.opacity as u32 / 255) as u8;
+            // Cels off the tile grid keep the remainder as a layer offset.
+            let (left, top) = ((cel.x as i32).div_euclid(tw), (cel.y as i32).div_euclid(th));
+            offset_x = (cel.x as i32).rem_euclid(tw);
+            offset_y = (cel.y as i32).rem_euclid(th);
+            for (i, tile) in tilemap.tiles.iter().enumerate() {
+                let x = left + (i % tilemap.width as usize) as i32;
+                let y = top + (i / tilemap.width as usize) as i32;
+                let empty = tile.id == 0 && tileset.flags & Tileset::FLAG_EMPTY_TILE_0 != 0;
+                if empty
+                    || tile.id >= tileset.tile_count
+                    || !(0..width as i32).contains(&x)
+                    || !(0..height as i32).contains(&y)
+                {
+                    continue;
+                }
+                let mut gid = first_gids[tileset_index] + tile.id;
+                if tile.flip_x {
+                    gid |= FLIPPED_HORIZONTALLY;
+                }
+                if tile.flip_y {
+                    gid |= FLIPPED_VERTICALLY;
+                }
+                if tile.flip_diagonal {
+                    gid |= FLIPPED_DIAGONALLY;
+                }
+                data[y as usize * width as usize + x as usize] = gid;
+            }
+        }
+
+        layers.push(json!({
+            "type": "tilelayer",
+            "id": next_id,
+            "name": layer.name,
+            "x": 0,
+            "y": 0,
+            "width": width,
+            "height": height,
+            "offsetx": offset_x,
+            "offsety": offset_y,
+            "opacity": opacity as f64 / 255.0,
+            "visible": visible[index],
+            "properties": properties(&layer.user_data),
+            "data": data,
+        }));
+        next_id += 1;
+    }
+
+    let mut objects = Vec::new();
+    for (slice, user_data) in file.slices.iter().zip(&file.slice_user_data) {
+        let Some(key) = slice.key_at(frame as u32) else {
+            continue;
+        };
+        let mut properties = properties(user_data);
+        if let Some(pivot) = key.pivot {
+            properties.push(json!({ "name": "pivot_x", "type": "int", "value": pivot.x }));
+            properties.push(json!({ "name": "pivot_y", "type": "int", "value": pivot.y }));
+        }
+        objects.push(json!({
+            "id": objects.len() + 1,
+            "name": slice.name,
+            "type": "",
+            "x": key.bounds.x,
+            "y": key.bounds.y,
+            "width": key.bounds.w,
+            "height": key.bounds.h,
+            "rotation": 0,
+            "visible": true,
+            "properties": properties,
+        }));
+    }
+    let next_object_id = objects.len() + 1;
+    layers.push(json!({
+        "type": "objectgroup",
+        "id": next_id,
+        "name": "Slices",
+        "x": 0,
+        "y": 0,
+        "opacity": 1,
+        "visible": true,
+        "draworder": "topdown",
+        "objects": objects,
+    }));
+
+    let tilesets: Vec<Value> = file
+        .tilesets
+        .iter()
+        .zip(&first_gids)
+        .map(|(tileset, first_gid)| json!({ "firstgid": first_gid, "source": tileset_source(tileset) }))
+        .collect();
+
+    let map = json!({
+        "type": "map",
+        "version": VERSION,
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "infinite": false,
+        "width": width,
+        "height": height,
+        "tilewidth": tile_width,
+        "tileheight": tile_height,
+        "nextlayerid": next_id + 1,
+        "nextobjectid": next_object_id,
+        "properties": properties(&file.user_data),
+        "layers": layers,
+        "tilesets": tilesets,
+    });
+    Ok(serde_json::to_string_pretty(&map).expect("maps are valid JSON"))
+}
+
+/// Tiled JSON tileset whose image, at path `image`, is [`tileset_image`].
+pub fn tileset(tileset: &Tileset, image: &str) -> String {
+    let count = tileset.tiles.len();
+    let tiles: Vec<Value> = tileset
+        .tile_user_data
+        .iter()
+        .enumerate()
+        .filter(|(_, user_data)| **user_data != UserData::default())
+        .map(|(id, user_data)| json!({ "id": id, "properties": properties(user_data) }))
+        .collect();
+    let tileset = json!({
+        "type": "tileset",
+        "version": VERSION,
+        "name": tileset.name,
+        "tilewidth": tileset.tile_width,
+        "tileheight": tileset.tile_height,
+        "tilecount": count,
+        "columns": 1,
+        "margin": 0,
+        "spacing": 0,
+        "image": image,
+        "imagewidth": tileset.tile_width,
+        "imageheight": tileset.tile_height as usize * count,
+        "properties": properties(&tileset.user_data),
+        "tiles": tiles,
+    });
+    serde_json::to_string_pretty(&tileset).expect("tilesets are valid JSON")
+}
+
+/// Image of the tiles of a tileset one below the other, as RGBA.
+pub fn tileset_image(file: &AseFile, tileset: &Tileset) -> RgbaImage {
+    let (width, height) = (tileset.tile_width as u32, tileset.tile_height as u32);
+    let mut image = RgbaImage::new(width, height * tileset.tiles.len() as u32);
+    for (i, tile) in tileset.tiles.iter().enumerate() {
+        let tile = file.to_rgba(tile, false);
+        let top = i * (width * height * 4) as usize;
+        image.data[top..top + tile.data.len()].copy_from_slice(&tile.data);
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ase::{CelContent, CelImage, Tile, Tilemap};
+
+    fn sprite() -> AseFile {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let mut ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        let tile = |index: u8| CelImage {
+            width: 4,
+            height: 4,
+            data: vec![index; 16],
+        };
+        let mut tileset = ase.tilesets.first().cloned().unwrap_or_else(|| Tileset {
+            id: 0,
+            flags: Tileset::FLAG_TILES | Tileset::FLAG_EMPTY_TILE_0,
+            name: "room".to_string(),
+            tile_width: 4,
+            tile_height: 4,
+            base_index: 1,
+            tile_count: 0,
+            tiles: vec![],
+            link: None,
+            user_data: UserData::default(),
+            tile_user_data: vec![],
+        });
+        tileset.tiles = vec![tile(0), tile(1), tile(2)];
+        tileset.tile_count = 3;
+        ase.tilesets = vec![tileset];
+
+        let mut layer = ase.layers[1].clone();
+        layer.name = "Tiles".to_string();
+        layer.kind = LayerKind::Tilemap(0);
+        layer.child_level = 0;
+        ase.layers = vec![layer];
+
+        let mut cel = ase.frames[0].cels[0].clone();
+        cel.layer = 0;
+        cel.x = 4;
+        cel.y = 0;
+        cel.content = CelContent::Tilemap(Tilemap {
+            width: 1,
+            height: 2,
+            tiles: vec![
+                Tile {
+                    id: 2,
+                    flip_x: true,
+                    ..Tile::default()
+                },
+                Tile::default(),
+            ],
+        });
+        ase.frames[0].cels = vec![cel];
+        ase
+    }
+
+    #[test]
+    fn test_map() {
+        let ase = sprite();
+        let map: Value =
+            serde_json::from_str(&map(&ase, 0, |t| format!("{}.tsj", t.name)).unwrap()).unwrap();
+        assert_eq!(
+            (2, 2),
+            (
+                map["width"].as_u64().unwrap(),
+                map["height"].as_u64().unwrap()
+            )
+        );
+        assert_eq!(
+            json!([{ "firstgid": 1, "source": "room.tsj" }]),
+            map["tilesets"]
+        );
+
+        let tiles = &map["layers"][0];
+        assert_eq!("Tiles", tiles["name"]);
+        assert_eq!(json!([0, 3 | FLIPPED_HORIZONTALLY, 0, 0]), tiles["data"]);
+
+        let objects = map["layers"][1]["objects"].as_array().unwrap();
+        assert_eq!(ase.slices.len(), objects.len());
+        let top_left = objects.iter().find(|o| o["name"] == "Top Left").unwrap();
+        assert_eq!(
+            json!([
+                { "name": "data", "type": "string", "value": "Top Left User Data" },
+                { "name": "color", "type": "color", "value": "#ff6acd5b" },
+            ]),
+            top_left["properties"]
+        );
+        let pivot = objects
+            .iter()
+            .find(|o| o["name"] == "Top Right Pivot")
+            .unwrap();
+        let pivot_x = pivot["properties"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "pivot_x")
+            .unwrap();
+        assert_eq!(json!(6), pivot_x["value"]);
+
+        // Tiled can't place a second layer whose tiles are another size.
+        let mut ase = ase;
+        let mut tileset = ase.tilesets[0].clone();
+        tileset.id = 1;
+        tileset.name = "big".to_string();
+        tileset.tile_width = 8;
+        ase.tilesets.push(tileset);
+        let mut layer = ase.layers[0].clone();
+        layer.kind = LayerKind::Tilemap(1);
+        ase.layers.push(layer);
+        assert!(super::map(&ase, 0, |t| t.name.clone()).is_err());
+        ase.tilesets[1].tile_width = ase.tilesets[0].tile_width;
+        assert!(super::map(&ase, 0, |t| t.name.clone()).is_ok());
+    }
+
+    #[test]
+    fn test_first_gids() {
+        let mut ase = sprite();
+        // Tilesets linked to another file have a count but no tiles.
+        let mut linked = ase.tilesets[0].clone();
+        linked.id = 1;
+        linked.tiles = vec![];
+        linked.tile_count = 5;
+        ase.tilesets.insert(0, linked);
+        assert_eq!(vec![1, 6], first_gids(&ase));
+    }
+
+    #[test]
+    fn test_tileset() {
+        let ase = sprite();
+        let tileset = &ase.tilesets[0];
+        let json: Value = serde_json::from_str(&super::tileset(tileset, "room.png")).unwrap();
+        assert_eq!(json!(3), json["tilecount"]);
+        assert_eq!(json!(12), json["imageheight"]);
+
+        let image = tileset_image(&ase, tileset);
+        assert_eq!((4, 12), (image.width, image.height));
+        assert_eq!(ase.palette.get(2), image.pixel(0, 8));
+    }
+
+    #[test]
+    fn test_properties() {
+        let mut user_data = UserData::default();
+        let mut user = crate::ase::Properties::new();
+        user.insert("solid".to_string(), PropertyValue::Bool(true));
+        user.insert("speed".to_string(), PropertyValue::Fixed(0x18000));
+        user.insert("spawn".to_string(), PropertyValue::Point { x: 1, y: -2 });
+        user_data.properties.insert(0, user);
+        assert_eq!(
+            vec![
+                json!({ "name": "solid", "type": "bool", "value": true }),
+                json!({ "name": "spawn", "type": "string", "value": "{\"x\":1,\"y\":-2}" }),
+                json!({ "name": "speed", "type": "float", "value": 1.5 }),
+            ],
+            properties(&user_data)
+        );
+    }
+}
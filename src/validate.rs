@@ -0,0 +1,231 @@
+//! Checking sheets and .ase files for mistakes that still load.
+//!
+//! Loading only fails when the data can't be read at all; these checks
+//! find what a game would trip over later, such as tags past the last
+//! frame or frames lying outside the sheet.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::ase::AseFile;
+use crate::{Frametag, Slice, SpritesheetData};
+
+/// How bad a problem is.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Severity {
+    /// Using the data as it is will go wrong.
+    Error,
+    /// Probably a mistake, but the data can be used.
+    Warning,
+}
+
+/// A problem found in a sheet or file.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// How bad it is.
+    pub severity: Severity,
+    /// Short kebab-case name of the check, such as `tag-range`.
+    pub code: &'static str,
+    /// What is wrong, for people.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(code: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message,
+        }
+    }
+
+    fn warning(code: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message,
+        }
+    }
+}
+
+/// Checks a sheet loaded from Aseprite's JSON export.
+pub fn sheet(sheet: &SpritesheetData) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if sheet.frames.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            "no-frames",
+            "the sheet has no frames".to_string(),
+        ));
+    }
+    let size = sheet.meta.size;
+    for (i, frame) in sheet.frames.iter().enumerate() {
+        let rect = frame.frame;
+        let (w, h) = match frame.rotated {
+            false => (rect.w, rect.h),
+            true => (rect.h, rect.w),
+        };
+        if rect.x as u64 + w as u64 > size.w as u64 || rect.y as u64 + h as u64 > size.h as u64 {
+            diagnostics.push(Diagnostic::error(
+                "frame-outside-sheet",
+                format!(
+                    "frame {} ({}) at {},{} of {}x{} lies outside the {}x{} sheet",
+                    i, frame.filename, rect.x, rect.y, w, h, size.w, size.h
+                ),
+            ));
+        }
+        let trimmed = frame.sprite_source_size;
+        let source = frame.source_size;
+        if trimmed.x as u64 + trimmed.w as u64 > source.w as u64
+            || trimmed.y as u64 + trimmed.h as u64 > source.h as u64
+        {
+            diagnostics.push(Diagnostic::error(
+                "trim-outside-source",
+                format!(
+                    "frame {} ({}) is trimmed to pixels outside its {}x{} source",
+                    i, frame.filename, source.w, source.h
+                ),
+            ));
+        }
+        if frame.duration == 0 {
+            diagnostics.push(zero_duration(i));
+        }
+    }
+    check_tags(&sheet.meta.frame_tags, sheet.frames.len(), &mut diagnostics);
+    check_slices(&sheet.meta.slices, sheet.frames.len(), &mut diagnostics);
+    diagnostics
+}
+
+/// Checks an .ase file.
+pub fn ase(file: &AseFile) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (i, frame) in file.frames.iter().enumerate() {
+        if frame.duration == 0 {
+            diagnostics.push(zero_duration(i));
+        }
+        for cel in &frame.cels {
+            if cel.layer as usize >= file.layers.len() {
+                diagnostics.push(Diagnostic::error(
+                    "cel-layer",
+                    format!(
+                        "frame {} has a cel on layer {} of {}",
+                        i,
+                        cel.layer,
+                        file.layers.len()
+                    ),
+                ));
+            }
+        }
+    }
+    check_tags(&file.tags, file.frames.len(), &mut diagnostics);
+    check_slices(&file.slices, file.frames.len(), &mut diagnostics);
+    diagnostics
+}
+
+fn zero_duration(frame: usize) -> Diagnostic {
+    Diagnostic::warning(
+        "zero-duration",
+        format!("frame {} lasts no time at all", frame),
+    )
+}
+
+fn check_tags(tags: &[Frametag], frames: usize, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names = HashSet::new();
+    for tag in tags {
+        if tag.from > tag.to || tag.to as usize >= frames {
+            diagnostics.push(Diagnostic::error(
+                "tag-range",
+                format!(
+                    "tag {} covers frames {} to {} of {}",
+                    tag.name, tag.from, tag.to, frames
+                ),
+            ));
+        }
+        if !names.insert(&tag.name) {
+            diagnostics.push(Diagnostic::warning(
+                "duplicate-tag",
+                format!("there's more than one tag called {}", tag.name),
+            ));
+        }
+    }
+}
+
+fn check_slices(slices: &[Slice], frames: usize, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names = HashSet::new();
+    for slice in slices {
+        if slice.keys.is_empty() {
+            diagnostics.push(Diagnostic::warning(
+                "slice-without-keys",
+                format!("slice {} has no keys", slice.name),
+            ));
+        }
+        for key in &slice.keys {
+            if key.frame as usize >= frames {
+                diagnostics.push(Diagnostic::warning(
+                    "slice-key-range",
+                    format!(
+                        "slice {} has a key at frame {} of {}",
+                        slice.name, key.frame, frames
+                    ),
+                ));
+            }
+        }
+        if !names.insert(&slice.name) {
+            diagnostics.push(Diagnostic::warning(
+                "duplicate-slice",
+                format!("there's more than one slice called {}", slice.name),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&'static str> {
+        diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_exports() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let sheet: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        assert_eq!(Vec::<Diagnostic>::new(), super::sheet(&sheet));
+        let ase = AseFile::from_bytes(file.src_aseprite).unwrap();
+        assert_eq!(Vec::<Diagnostic>::new(), super::ase(&ase));
+    }
+
+    #[test]
+    fn test_sheet() {
+        let file = aseprite_test_data::FileSet::complex_1_2_25();
+        let mut sheet: SpritesheetData = serde_json::from_slice(file.array_json).unwrap();
+        sheet.frames[0].frame.x = sheet.meta.size.w;
+        sheet.frames[1].duration = 0;
+        let mut tag = sheet.meta.frame_tags[0].clone();
+        tag.to = 100;
+        sheet.meta.frame_tags.push(tag);
+
+        let diagnostics = super::sheet(&sheet);
+        assert_eq!(
+            vec![
+                "frame-outside-sheet",
+                "zero-duration",
+                "tag-range",
+                "duplicate-tag"
+            ],
+            codes(&diagnostics)
+        );
+        assert_eq!(
+            serde_json::json!({
+                "severity": "error",
+                "code": "tag-range",
+                "message": "tag start covers frames 0 to 100 of 9",
+            }),
+            serde_json::to_value(&diagnostics[2]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+// Generated by aseprite::codegen, don't edit.
+
+/// Sprites.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SpriteId {
+    /// `hero`
+    Hero,
+    /// `slime`
+    Slime,
+}
+
+impl SpriteId {
+    /// Every variant, in the order of the sheet.
+    pub const ALL: [SpriteId; 2] = [SpriteId::Hero, SpriteId::Slime];
+
+    /// Name in the sheet.
+    pub const fn name(self) -> &'static str {
+        match self {
+            SpriteId::Hero => "hero",
+            SpriteId::Slime => "slime",
+        }
+    }
+
+    /// Sheet of the sprite, parsed the first time it's asked for.
+    pub fn sheet(self) -> &'static aseprite::SpritesheetData {
+        fn parse(json: &[u8]) -> aseprite::SpritesheetData {
+            aseprite::SpritesheetData::from_json_with_limits(json, &aseprite::Limits::default())
+                .expect("generated from a valid sheet")
+        }
+        match self {
+            SpriteId::Hero => {
+                static SHEET: std::sync::OnceLock<aseprite::SpritesheetData> =
+                    std::sync::OnceLock::new();
+                SHEET.get_or_init(|| parse(include_bytes!("../export/sheet.json")))
+            }
+            SpriteId::Slime => {
+                static SHEET: std::sync::OnceLock<aseprite::SpritesheetData> =
+                    std::sync::OnceLock::new();
+                SHEET.get_or_init(|| parse(include_bytes!("slime.json")))
+            }
+        }
+    }
+}
+
+/// Tags of `hero`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum HeroTag {
+    /// `idle`
+    Idle,
+    /// `run`
+    Run,
+}
+
+impl HeroTag {
+    /// Every variant, in the order of the sheet.
+    pub const ALL: [HeroTag; 2] = [HeroTag::Idle, HeroTag::Run];
+
+    /// Name in the sheet.
+    pub const fn name(self) -> &'static str {
+        match self {
+            HeroTag::Idle => "idle",
+            HeroTag::Run => "run",
+        }
+    }
+
+    /// Frames of the tag, first to last.
+    pub const fn frames(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            HeroTag::Idle => 0..=1,
+            HeroTag::Run => 1..=3,
+        }
+    }
+
+    /// The tag in the sheet.
+    pub fn tag(self) -> &'static aseprite::Frametag {
+        SpriteId::Hero
+            .sheet()
+            .frame_tag(self.name())
+            .expect("generated from this sheet")
+    }
+}
+
+/// Tags of `slime`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SlimeTag {
+    /// `walk-left`
+    WalkLeft,
+    /// `2x`
+    N2x,
+    /// `Walk Left`
+    WalkLeft2,
+}
+
+impl SlimeTag {
+    /// Every variant, in the order of the sheet.
+    pub const ALL: [SlimeTag; 3] = [SlimeTag::WalkLeft, SlimeTag::N2x, SlimeTag::WalkLeft2];
+
+    /// Name in the sheet.
+    pub const fn name(self) -> &'static str {
+        match self {
+            SlimeTag::WalkLeft => "walk-left",
+            SlimeTag::N2x => "2x",
+            SlimeTag::WalkLeft2 => "Walk Left",
+        }
+    }
+
+    /// Frames of the tag, first to last.
+    pub const fn frames(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            SlimeTag::WalkLeft => 0..=4,
+            SlimeTag::N2x => 5..=5,
+            SlimeTag::WalkLeft2 => 6..=7,
+        }
+    }
+
+    /// The tag in the sheet.
+    pub fn tag(self) -> &'static aseprite::Frametag {
+        SpriteId::Slime
+            .sheet()
+            .frame_tag(self.name())
+            .expect("generated from this sheet")
+    }
+}
+
+/// Slices of `slime`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SlimeSlice {
+    /// `hitbox`
+    Hitbox,
+}
+
+impl SlimeSlice {
+    /// Every variant, in the order of the sheet.
+    pub const ALL: [SlimeSlice; 1] = [SlimeSlice::Hitbox];
+
+    /// Name in the sheet.
+    pub const fn name(self) -> &'static str {
+        match self {
+            SlimeSlice::Hitbox => "hitbox",
+        }
+    }
+
+    /// The slice in the sheet.
+    pub fn slice(self) -> &'static aseprite::Slice {
+        SpriteId::Slime
+            .sheet()
+            .meta
+            .slices
+            .iter()
+            .find(|slice| slice.name == self.name())
+            .expect("generated from this sheet")
+    }
+}
+
+/// Layers of `slime`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SlimeLayer {
+    /// `body`
+    Body,
+    /// `eyes`
+    Eyes,
+}
+
+impl SlimeLayer {
+    /// Every variant, in the order of the sheet.
+    pub const ALL: [SlimeLayer; 2] = [SlimeLayer::Body, SlimeLayer::Eyes];
+
+    /// Name in the sheet.
+    pub const fn name(self) -> &'static str {
+        match self {
+            SlimeLayer::Body => "body",
+            SlimeLayer::Eyes => "eyes",
+        }
+    }
+}